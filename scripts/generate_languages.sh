@@ -0,0 +1,59 @@
+#!/bin/bash
+
+# Regenerates ziit-core/src/languages.json from GitHub Linguist's languages.yml.
+# Names Ziit already reports, the "config" category Linguist lacks,
+# extensions shared by several languages and languages Linguist does not know
+# about are controlled by scripts/language_overrides.json.
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+OVERRIDES="$SCRIPT_DIR/language_overrides.json"
+OUTPUT="$SCRIPT_DIR/../ziit-core/src/languages.json"
+LINGUIST_URL="https://raw.githubusercontent.com/github-linguist/linguist/main/lib/linguist/languages.yml"
+
+for tool in curl yq jq; do
+    if ! command -v "$tool" >/dev/null; then
+        echo "Error: $tool is required" >&2
+        exit 1
+    fi
+done
+
+if [[ -n "${LINGUIST_LANGUAGES:-}" ]]; then
+    languages_yml=$(cat "$LINGUIST_LANGUAGES")
+else
+    languages_yml=$(curl -fsSL "$LINGUIST_URL")
+fi
+
+echo "$languages_yml" | yq -o=json '.' | jq --slurpfile overrides "$OVERRIDES" '
+    $overrides[0] as $o
+    | to_entries
+    | map({
+        name: ($o.rename[.key] // .key),
+        type: .value.type,
+        extensions: (.value.extensions // []),
+        filenames: (.value.filenames // []),
+        aliases: (.value.aliases // [])
+    })
+    # Renames can fold several Linguist languages into one Ziit name.
+    | (. + $o.additions)
+    | group_by(.name)
+    | map({
+        name: .[0].name,
+        type: ($o.types[.[0].name] // .[0].type),
+        extensions: (map(.extensions[]) | unique),
+        filenames: (map(.filenames[]) | unique),
+        aliases: (map(.aliases[]) | unique)
+    })
+    # An extension claimed by several languages stays only with the owner
+    # listed in "claims", and is dropped entirely when it has no owner.
+    | ([.[].extensions[] | ascii_downcase] | group_by(.) | map(select(length > 1) | .[0])) as $shared
+    | map(.name as $name | .extensions |= map(select(
+        (ascii_downcase) as $extension
+        | ($shared | index($extension)) == null
+            or ($o.claims | has($extension) and .[$extension] == $name)
+    )))
+    | sort_by(.name | ascii_downcase)
+' | jq -c '.[]' | awk 'BEGIN { print "[" } NR > 1 { print prev "," } { prev = "  " $0 } END { print prev; print "]" }' > "$OUTPUT"
+
+echo "Wrote $(grep -c '"name"' "$OUTPUT") languages to $OUTPUT"
@@ -1,5 +1,24 @@
 #!/bin/bash
 
+# Regenerates the languages ziit-ls is bound to in extension.toml. Zed only
+# starts the server for languages listed there, so the list is collected from
+# every config.toml in zed-industries/zed, the extensions registry, extensions
+# installed locally (ZED_EXTENSIONS_DIR) and scripts/extra_languages.txt for
+# names none of those know about. Existing entries are kept so languages are
+# never dropped by a registry change.
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+MANIFEST="$SCRIPT_DIR/../extension.toml"
+EXTRA_LANGUAGES="$SCRIPT_DIR/extra_languages.txt"
+
+if [[ -z "${ZED_EXTENSIONS_DIR:-}" ]]; then
+    if [[ "$(uname)" == "Darwin" ]]; then
+        ZED_EXTENSIONS_DIR="$HOME/Library/Application Support/Zed/extensions/installed"
+    else
+        ZED_EXTENSIONS_DIR="${XDG_DATA_HOME:-$HOME/.local/share}/zed/extensions/installed"
+    fi
+fi
+
 fetch_files() {
     local repo_owner=$1
     local repo_name=$2
@@ -40,19 +59,79 @@ process_language_files() {
         local id="${file%/config.toml}"
         id="${id##*/}"
 
-        if [[ $LANGUAGES == *"$name\","* && $LANGUAGE_IDS == *"$name\" = \"$id\","* ]]; then
-            echo "Skipping language $name with id $id (already added)"
-        else
-            echo "Adding language $name with id $id"
-            LANGUAGES+="    \"$name\",\n"
-            LANGUAGE_IDS+=" \"$name\" = \"$id\","
-        fi
+        add_language "$name" "$id"
     done
 }
 
-hardcoded_languages() {
-    LANGUAGES+="    \"edge\",\n"
-    LANGUAGE_IDS+=" \"edge\" = \"edge\","
+add_language() {
+    local name=$1
+    local id=$2
+
+    if [[ -z "$name" || $LANGUAGES == *"\"$name\","* ]]; then
+        return
+    fi
+
+    echo "Adding language $name with id $id"
+    LANGUAGES+="    \"$name\",\n"
+    LANGUAGE_IDS+=" \"$name\" = \"$id\","
+}
+
+local_languages() {
+    if [[ ! -d "$ZED_EXTENSIONS_DIR" ]]; then
+        return
+    fi
+
+    while IFS= read -r -d '' file; do
+        local name=$(grep -oP '^name = "\K[^"]*' "$file")
+        local id=$(basename "$(dirname "$file")")
+        add_language "$name" "$id"
+    done < <(find "$ZED_EXTENSIONS_DIR" -path "*/languages/*/config.toml" -print0)
+}
+
+extra_languages() {
+    if [[ ! -f "$EXTRA_LANGUAGES" ]]; then
+        return
+    fi
+
+    while IFS= read -r name; do
+        name="${name%%#*}"
+        name="$(echo "$name" | xargs)"
+        add_language "$name" "$name"
+    done < "$EXTRA_LANGUAGES"
+}
+
+manifest_languages() {
+    awk '/^languages = \[/{in_list=1; next} in_list && /^\]/{exit} in_list' "$MANIFEST" | grep -oP '^\s*"\K[^"]*'
+}
+
+existing_languages() {
+    while IFS= read -r name; do
+        add_language "$name" "$name"
+    done < <(manifest_languages)
+}
+
+# Refuses to write a list that lost any language the manifest already has.
+check_nothing_dropped() {
+    local dropped=0
+    while IFS= read -r name; do
+        if [[ $LANGUAGES != *"\"$name\","* ]]; then
+            echo "Error: $name would be dropped from $MANIFEST" >&2
+            dropped=1
+        fi
+    done < <(manifest_languages)
+    return $dropped
+}
+
+write_manifest() {
+    local list=$(echo -e "$LANGUAGES" | sed 's/^    /  /')
+    local tmp=$(mktemp)
+
+    awk -v list="$list" '
+        /^languages = \[/ && !done { print list; skip=1; next }
+        skip && /^\]/ { skip=0; done=1; next }
+        !skip
+    ' "$MANIFEST" > "$tmp"
+    mv "$tmp" "$MANIFEST"
 }
 
 # Fetch main repo files with error handling
@@ -73,6 +152,8 @@ REPOSITORIES=($(echo "$EXTENSIONS_RESPONSE" | grep -oP 'url = \K.*'))
 LANGUAGES="languages = [\n"
 LANGUAGE_IDS="language_ids = {"
 
+existing_languages
+
 for repository in "${REPOSITORIES[@]}"; do
     repo_owner=$(echo "$repository" | cut -d'/' -f4)
     repo_name=$(echo "$repository" | cut -d'/' -f5 | sed 's/.git$//')
@@ -85,10 +166,15 @@ done
 echo "Processing zed-industries/zed"
 process_language_files "$FILES" "zed-industries" "zed" "crates/languages/src/**/config.toml" "extensions/**/languages/**/config.toml"
 
-hardcoded_languages
+echo "Processing locally installed extensions in $ZED_EXTENSIONS_DIR"
+local_languages
+extra_languages
 
 LANGUAGES+="]"
 LANGUAGE_IDS+="}"
 
-echo -e $LANGUAGES
+if ! check_nothing_dropped; then
+    exit 1
+fi
+write_manifest
 echo -e $LANGUAGE_IDS
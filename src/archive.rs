@@ -0,0 +1,216 @@
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Component, Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    // In order of preference when a release ships several formats.
+    pub const ALL: [ArchiveFormat; 2] = [ArchiveFormat::Zip, ArchiveFormat::TarGz];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::TarGz => ".tar.gz",
+        }
+    }
+}
+
+// A file or, when `contents` is None, a directory.
+struct ArchiveEntry {
+    name: String,
+    contents: Option<Vec<u8>>,
+}
+
+fn is_safe_entry_name(name: &str) -> bool {
+    !name.is_empty()
+        && Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn zip_entries(data: &[u8]) -> Result<Vec<ArchiveEntry>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|err| format!("failed to open zip archive: {err}"))?;
+
+    (0..archive.len())
+        .map(|index| {
+            let mut file = archive
+                .by_index(index)
+                .map_err(|err| format!("corrupt zip entry: {err}"))?;
+            let name = file.name().to_string();
+            let contents = if file.is_dir() {
+                None
+            } else {
+                let mut contents = Vec::new();
+                file.read_to_end(&mut contents)
+                    .map_err(|err| format!("failed to inflate {name}: {err}"))?;
+                Some(contents)
+            };
+            Ok(ArchiveEntry { name, contents })
+        })
+        .collect()
+}
+
+fn tar_entries(data: &[u8]) -> Result<Vec<ArchiveEntry>, String> {
+    let mut archive = tar::Archive::new(GzDecoder::new(data));
+    let tar_error = |err: std::io::Error| format!("failed to read tar archive: {err}");
+
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(tar_error)? {
+        let mut entry = entry.map_err(tar_error)?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_file() {
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).map_err(tar_error)?;
+            entries.push(ArchiveEntry {
+                name,
+                contents: Some(contents),
+            });
+        } else if entry_type.is_dir() {
+            entries.push(ArchiveEntry {
+                name,
+                contents: None,
+            });
+        } else {
+            log::debug!("Skipping unsupported tar entry {}", name);
+        }
+    }
+
+    Ok(entries)
+}
+
+// Release archives may wrap everything in a single top-level directory;
+// strip it so the binary always lands directly in the destination.
+fn strip_common_root(entries: &mut Vec<ArchiveEntry>) {
+    let root_of = |name: &str| name.split('/').next().unwrap_or_default().to_string();
+    let Some(root) = entries.first().map(|entry| root_of(&entry.name)) else {
+        return;
+    };
+
+    let nested = entries.iter().all(|entry| {
+        let name = entry.name.trim_end_matches('/');
+        name == root || name.starts_with(&format!("{root}/"))
+    });
+    let has_nested_file = entries
+        .iter()
+        .any(|entry| entry.contents.is_some() && entry.name.contains('/'));
+    if !nested || !has_nested_file {
+        return;
+    }
+
+    entries.retain(|entry| entry.name.trim_end_matches('/') != root);
+    for entry in entries.iter_mut() {
+        entry.name = entry.name[root.len() + 1..].to_string();
+    }
+}
+
+pub fn extract(format: ArchiveFormat, data: &[u8], destination: &str) -> Result<(), String> {
+    let mut entries = match format {
+        ArchiveFormat::Zip => zip_entries(data)?,
+        ArchiveFormat::TarGz => tar_entries(data)?,
+    };
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| !is_safe_entry_name(entry.name.trim_end_matches('/')))
+    {
+        return Err(format!("refusing to extract unsafe path {}", entry.name));
+    }
+    strip_common_root(&mut entries);
+
+    let destination = Path::new(destination);
+    fs::create_dir_all(destination)
+        .map_err(|err| format!("failed to create {}: {err}", destination.display()))?;
+
+    for entry in entries {
+        let path = destination.join(&entry.name);
+        let Some(contents) = entry.contents else {
+            fs::create_dir_all(&path)
+                .map_err(|err| format!("failed to create {}: {err}", path.display()))?;
+            continue;
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+        }
+        fs::write(&path, contents)
+            .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn stored_zip(name: &str, contents: &[u8]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file(name, options).unwrap();
+        zip.write_all(contents).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_extract_stored_entry() {
+        let dir = std::env::temp_dir().join(format!("ziit-zed-archive-{}", std::process::id()));
+        let zip = stored_zip("ziit-ls", b"binary");
+
+        extract(ArchiveFormat::Zip, &zip, dir.to_str().unwrap()).unwrap();
+        assert_eq!(fs::read(dir.join("ziit-ls")).unwrap(), b"binary");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rejects_unsafe_entry_names() {
+        assert!(is_safe_entry_name("ziit-ls"));
+        assert!(is_safe_entry_name("bin/ziit-ls"));
+        assert!(!is_safe_entry_name("../ziit-ls"));
+        assert!(!is_safe_entry_name("/etc/passwd"));
+
+        let dir = std::env::temp_dir().join(format!("ziit-zed-unsafe-{}", std::process::id()));
+        let zip = stored_zip("../evil", b"x");
+        assert!(extract(ArchiveFormat::Zip, &zip, dir.to_str().unwrap()).is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *contents).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_extract_tar_gz_strips_common_root() {
+        let dir = std::env::temp_dir().join(format!("ziit-zed-tar-{}", std::process::id()));
+        let archive = tar_gz(&[
+            ("ziit-ls-x86_64-unknown-linux-musl/ziit-ls", b"binary"),
+            ("ziit-ls-x86_64-unknown-linux-musl/LICENSE", b"license"),
+        ]);
+
+        extract(ArchiveFormat::TarGz, &archive, dir.to_str().unwrap()).unwrap();
+        assert_eq!(fs::read(dir.join("ziit-ls")).unwrap(), b"binary");
+        assert_eq!(fs::read(dir.join("LICENSE")).unwrap(), b"license");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const LOCK_FILE: &str = ".ziit-install.lock";
+const RETRY_INTERVAL: Duration = Duration::from_millis(250);
+const WAIT_TIMEOUT: Duration = Duration::from_secs(300);
+// A lock older than this belongs to an install that crashed or was killed.
+const STALE_AFTER: Duration = Duration::from_secs(600);
+
+pub struct InstallLock;
+
+impl InstallLock {
+    pub fn acquire() -> Result<Self, String> {
+        let started = SystemTime::now();
+
+        loop {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(LOCK_FILE)
+            {
+                Ok(_) => return Ok(Self),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale() {
+                        log::warn!("Removing stale install lock {}", LOCK_FILE);
+                        fs::remove_file(LOCK_FILE).ok();
+                        continue;
+                    }
+
+                    if started.elapsed().unwrap_or_default() > WAIT_TIMEOUT {
+                        return Err(format!(
+                            "timed out waiting for another language server install to finish \
+                             (remove {LOCK_FILE} from the extension directory if none is running)"
+                        ));
+                    }
+
+                    thread::sleep(RETRY_INTERVAL);
+                }
+                Err(err) => return Err(format!("failed to create install lock: {err}")),
+            }
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        fs::remove_file(LOCK_FILE).ok();
+    }
+}
+
+fn is_stale() -> bool {
+    fs::metadata(LOCK_FILE)
+        .and_then(|stat| stat.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_AFTER)
+}
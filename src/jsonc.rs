@@ -0,0 +1,71 @@
+use zed_extension_api::serde_json::{self, Value};
+
+// Zed settings files are JSON with comments and trailing commas, which
+// serde_json rejects. Strips both outside of strings before parsing.
+pub fn parse(text: &str) -> Option<Value> {
+    let mut json = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => json.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                json.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&c| c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            (']' | '}', _) => {
+                let trimmed = json.trim_end().len();
+                if json[..trimmed].ends_with(',') {
+                    json.truncate(trimmed - 1);
+                }
+                json.push(c);
+            }
+            _ => json.push(c),
+        }
+    }
+
+    serde_json::from_str(&json).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_settings_with_comments() {
+        let settings = parse(
+            r#"{
+                // Tracked with Ziit
+                "ziit": {
+                    "baseUrl": "https://ziit.example.com/", /* self-hosted */
+                    "excludes": ["target", "node_modules",],
+                },
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(settings["ziit"]["baseUrl"], "https://ziit.example.com/");
+        assert_eq!(settings["ziit"]["excludes"][1], "node_modules");
+        assert!(parse("{ \"broken\": }").is_none());
+    }
+}
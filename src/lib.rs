@@ -1,27 +1,98 @@
+mod archive;
+mod install_lock;
+mod jsonc;
+mod signature;
+mod version;
+
+use archive::ArchiveFormat;
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use zed_extension_api::{
-    self as zed, settings::LspSettings, Command, Extension, LanguageServerId, Result, Worktree,
+    self as zed,
+    http_client::{HttpMethod, HttpRequest, RedirectPolicy},
+    settings::{ContextServerSettings, LspSettings},
+    Command, ContextServerId, Extension, GithubRelease, LanguageServerId, Project, Result,
+    SlashCommand, SlashCommandOutput, SlashCommandOutputSection, Worktree,
 };
 
+const BINARY_PATH_ENV_VAR: &str = "ZIIT_LS_PATH";
+const RELEASE_REPO: &str = "0PandaDEV/ziit-zed";
+const ZIIT_SETTINGS_KEYS: &[&str] = &["apiKey", "baseUrl", "interval", "excludes"];
+const WORKTREE_SETTINGS_FILE: &str = ".zed/settings.json";
+const DEFAULT_BASE_URL: &str = "https://ziit.app";
+const STATUS_FILE: &str = "ziit-status.json";
+const STATUS_FILE_ENV_VAR: &str = "ZIIT_STATUS_FILE";
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+const DOWNLOAD_RETRY_BACKOFF: Duration = Duration::from_secs(2);
+const DEFAULT_KEPT_VERSIONS: usize = 3;
+const UPDATE_CHECK_FILE: &str = ".ziit-update-check";
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const UPDATE_PENDING_FILE: &str = ".ziit-update-pending";
+const PROXY_ENV_VARS: &[&str] = &[
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
 struct ZiitExtension {
-    cached_binary_path: Option<String>,
+    // Remembered from the last `language_server_command` so slash commands can
+    // report download progress against the running server.
+    language_server_id: Option<LanguageServerId>,
+    // The binary the last `language_server_command` resolved to, which
+    // cleanup must leave alone while it is running.
+    binary_path: Option<String>,
 }
 
 impl ZiitExtension {
-    fn target_triple(&self) -> Result<String, String> {
+    fn linux_libc(&self, worktree: &Worktree) -> &'static str {
+        let libc = self.extension_setting(worktree, "libc");
+        match libc.as_ref().and_then(|libc| libc.as_str()) {
+            Some("musl") => return "musl",
+            Some("gnu") => return "gnu",
+            Some(other) => log::warn!("Unknown libc {:?}, detecting automatically", other),
+            None => {}
+        }
+
+        // Alpine has no glibc and NixOS lacks the standard dynamic loader path,
+        // so both need the statically linked musl build.
+        if worktree.which("apk").is_some() || worktree.which("nixos-version").is_some() {
+            "musl"
+        } else {
+            "gnu"
+        }
+    }
+
+    fn target_triple(&self, worktree: &Worktree) -> Result<String, String> {
+        let target_triple = self.extension_setting(worktree, "targetTriple");
+        if let Some(target_triple) = target_triple.as_ref().and_then(|triple| triple.as_str()) {
+            return Ok(target_triple.to_string());
+        }
+
         let (platform, arch) = zed::current_platform();
         let (arch, os) = {
             let arch = match arch {
                 zed::Architecture::Aarch64 => "aarch64",
                 zed::Architecture::X8664 => "x86_64",
-                _ => return Err(format!("unsupported architecture: {arch:?}")),
+                _ => match platform {
+                    zed::Os::Linux => machine_architecture()
+                        .ok_or_else(|| format!("unsupported architecture: {arch:?}"))?,
+                    _ => return Err(format!("unsupported architecture: {arch:?}")),
+                },
             };
 
             let os = match platform {
-                zed::Os::Mac => "apple-darwin",
-                zed::Os::Linux => "unknown-linux-gnu",
-                zed::Os::Windows => "pc-windows-msvc",
+                zed::Os::Mac => "apple-darwin".to_string(),
+                zed::Os::Linux => match (arch, self.linux_libc(worktree)) {
+                    ("armv7", "musl") => "unknown-linux-musleabihf".to_string(),
+                    ("armv7", _) => "unknown-linux-gnueabihf".to_string(),
+                    (_, libc) => format!("unknown-linux-{libc}"),
+                },
+                zed::Os::Windows => "pc-windows-msvc".to_string(),
             };
 
             (arch, os)
@@ -30,128 +101,565 @@ impl ZiitExtension {
         Ok(format!("{}-{}", arch, os))
     }
 
+    // The top-level `ziit` block of the worktree's `.zed/settings.json`. Zed
+    // doesn't hand extensions their own settings, so the file is read
+    // directly; `lsp.ziit-ls.settings` still works for user-wide settings.
+    fn ziit_settings(
+        &self,
+        worktree: &Worktree,
+    ) -> zed::serde_json::Map<String, zed::serde_json::Value> {
+        let worktree_settings = worktree
+            .read_text_file(WORKTREE_SETTINGS_FILE)
+            .ok()
+            .and_then(|text| jsonc::parse(&text))
+            .and_then(|settings| settings.get("ziit").cloned());
+        let lsp_settings = LspSettings::for_worktree("ziit-ls", worktree)
+            .ok()
+            .and_then(|lsp_settings| lsp_settings.settings);
+
+        let mut ziit_settings = zed::serde_json::Map::new();
+        for settings in [worktree_settings, lsp_settings].into_iter().flatten() {
+            let zed::serde_json::Value::Object(settings) = settings else {
+                continue;
+            };
+            for key in ZIIT_SETTINGS_KEYS {
+                if let Some(value) = settings.get(*key) {
+                    ziit_settings
+                        .entry(key.to_string())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+        }
+        ziit_settings
+    }
+
+    fn fetch_release_asset(&self, release: &GithubRelease, name: &str) -> Result<String> {
+        let asset = release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| format!("release {} has no asset {}", release.version, name))?;
+
+        let response = HttpRequest::builder()
+            .method(HttpMethod::Get)
+            .url(&asset.download_url)
+            .redirect_policy(RedirectPolicy::FollowAll)
+            .build()?
+            .fetch()
+            .map_err(|err| format!("failed to fetch {name}: {err}"))?;
+
+        Ok(String::from_utf8_lossy(&response.body).to_string())
+    }
+
+    fn expected_checksum(&self, release: &GithubRelease, asset_name: &str) -> Result<String> {
+        let body = self.fetch_release_asset(release, &format!("{asset_name}.sha256"))?;
+        let checksum = body
+            .split_whitespace()
+            .next()
+            .map(|checksum| checksum.to_ascii_lowercase())
+            .filter(|checksum| {
+                checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit())
+            })
+            .ok_or_else(|| format!("malformed checksum file for {asset_name}"))?;
+
+        Ok(checksum)
+    }
+
+    // Downloads an archive and checks it against the published checksum,
+    // retrying with backoff so a flaky connection doesn't fail the install.
+    fn download_verified_archive(
+        &self,
+        url: &str,
+        archive_path: &str,
+        asset_name: &str,
+        expected_checksum: &str,
+        attempts: u32,
+    ) -> Result<Vec<u8>> {
+        let mut attempt = 1;
+        loop {
+            let result = zed::download_file(url, archive_path, zed::DownloadedFileType::Uncompressed)
+                .map_err(|err| format!("failed to download {asset_name}: {err}"))
+                .and_then(|()| {
+                    fs::read(archive_path)
+                        .map_err(|err| format!("failed to read downloaded archive: {err}"))
+                })
+                .and_then(|archive| {
+                    let actual_checksum = format!("{:x}", Sha256::digest(&archive));
+                    if actual_checksum == expected_checksum {
+                        Ok(archive)
+                    } else {
+                        Err(format!(
+                            "checksum mismatch for {asset_name}: expected {expected_checksum}, got {actual_checksum}"
+                        ))
+                    }
+                });
+            fs::remove_file(archive_path).ok();
+
+            match result {
+                Ok(archive) => return Ok(archive),
+                Err(err) if attempt < attempts => {
+                    let backoff = DOWNLOAD_RETRY_BACKOFF * 2u32.pow(attempt - 1);
+                    log::warn!(
+                        "Download attempt {} of {} failed: {}. Retrying in {:?}",
+                        attempt,
+                        attempts,
+                        err,
+                        backoff
+                    );
+                    std::thread::sleep(backoff);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn fetch_release(&self, worktree: &Worktree) -> Result<GithubRelease> {
+        match self.pinned_version(worktree) {
+            Some(tag) => zed::github_release_by_tag_name(RELEASE_REPO, &tag)
+                .map_err(|err| format!("failed to fetch release {tag}: {err}")),
+            None => zed::latest_github_release(
+                RELEASE_REPO,
+                zed::GithubReleaseOptions {
+                    require_assets: true,
+                    pre_release: self.use_preview_channel(worktree),
+                },
+            ),
+        }
+    }
+
     fn download(
         &self,
         language_server_id: &LanguageServerId,
+        worktree: &Worktree,
         binary: &str,
-        repo: &str,
+        release: &GithubRelease,
+        keep_dirs: &[&str],
+        attempts: u32,
     ) -> Result<String> {
-        let release = zed::latest_github_release(
-            repo,
-            zed::GithubReleaseOptions {
-                require_assets: true,
-                pre_release: false,
-            },
-        )?;
-
-        let target_triple = self.target_triple()?;
-        let asset_name = format!("{binary}-{target_triple}.zip");
-        let asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == asset_name)
-            .ok_or_else(|| format!("no asset found matching {:?}", asset_name))?;
+        let target_triple = self.target_triple(worktree)?;
+        let (format, asset) = ArchiveFormat::ALL
+            .into_iter()
+            .find_map(|format| {
+                let asset_name = format!("{binary}-{target_triple}{}", format.extension());
+                release
+                    .assets
+                    .iter()
+                    .find(|asset| asset.name == asset_name)
+                    .map(|asset| (format, asset))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "release {version} has no prebuilt {binary} for {target_triple}; build it \
+                     from source with `cargo install --locked --git https://github.com/{RELEASE_REPO} \
+                     --tag {version} {binary}` and make sure it is on your PATH, or point \
+                     {BINARY_PATH_ENV_VAR} or lsp.{binary}.binary.path at the built binary",
+                    version = release.version,
+                )
+            })?;
 
+        let asset_name = &asset.name;
         let version_dir = format!("{binary}-{}", release.version);
-        let binary_path = if target_triple.ends_with("pc-windows-msvc") {
-            Path::new(&version_dir)
-                .join(format!("{binary}.exe"))
-                .to_string_lossy()
-                .to_string()
-        } else {
-            Path::new(&version_dir)
-                .join(binary)
-                .to_string_lossy()
-                .to_string()
-        };
+        let binary_path = Path::new(&version_dir)
+            .join(executable_name(binary))
+            .to_string_lossy()
+            .to_string();
+
+        // Held until the end of this function so other worktrees never see a
+        // half-extracted version directory or race the cleanup below.
+        let _lock = install_lock::InstallLock::acquire()?;
 
-        if !fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
+        if !fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
             zed::set_language_server_installation_status(
                 language_server_id,
                 &zed::LanguageServerInstallationStatus::Downloading,
             );
 
-            zed::download_file(
+            let expected_checksum = self.expected_checksum(release, asset_name)?;
+            let archive_path = format!("{version_dir}{}", format.extension());
+            let archive = self.download_verified_archive(
                 &asset.download_url,
-                &version_dir,
-                zed::DownloadedFileType::Zip,
-            )
-            .map_err(|err| format!("failed to download file: {err}"))?;
+                &archive_path,
+                asset_name,
+                &expected_checksum,
+                attempts,
+            )?;
+
+            // Fails closed: a release without a valid signature is never installed.
+            let signature = self.fetch_release_asset(release, &format!("{asset_name}.minisig"))?;
+            signature::verify(&archive, &signature, asset_name)?;
+
+            install_staged(format, &archive, &version_dir, &executable_name(binary))?;
+
+            // Only garbage-collect once the new version is in place. Keep the
+            // newest few around so users can roll back to a previous one with
+            // `"version": "previous"`.
+            let retained: Vec<String> = self
+                .installed_versions()
+                .into_iter()
+                .take(self.kept_versions(worktree))
+                .map(|(_, dir_name)| dir_name)
+                .collect();
+
+            let in_use_dir = self
+                .binary_path
+                .as_deref()
+                .and_then(|path| Path::new(path).parent())
+                .and_then(|dir| dir.to_str());
 
             let entries = fs::read_dir(".")
                 .map_err(|err| format!("failed to list working directory {err}"))?;
 
             for entry in entries {
                 let entry = entry.map_err(|err| format!("failed to load directory entry {err}"))?;
-                if let Some(file_name) = entry.file_name().to_str() {
-                    if file_name.starts_with(binary) && file_name != version_dir {
-                        fs::remove_dir_all(entry.path()).ok();
-                    }
+                let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                // Only release versions and their leftover staging directories;
+                // local installs are left to the user.
+                let collectable = file_name
+                    .strip_prefix(&format!("{binary}-"))
+                    .map(|rest| rest.strip_prefix("staging-").unwrap_or(rest))
+                    .is_some_and(|version| version::parse(version).is_some());
+                if collectable
+                    && file_name != version_dir
+                    && in_use_dir != Some(file_name.as_str())
+                    && !keep_dirs.contains(&file_name.as_str())
+                    && !retained.contains(&file_name)
+                {
+                    fs::remove_dir_all(entry.path()).ok();
                 }
             }
         }
 
         zed::make_file_executable(&binary_path)?;
 
-        if !fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
-            return Err(format!("Binary not available after download: {}", binary_path).into());
+        if !fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
+            return Err(format!(
+                "Binary not available after download: {}",
+                binary_path
+            ));
         }
 
         log::info!("Successfully prepared binary at: {}", binary_path);
         Ok(binary_path)
     }
 
-    fn language_server_binary_path(
-        &mut self,
-        language_server_id: &LanguageServerId,
-        worktree: &Worktree,
-    ) -> Result<String> {
-        let ls_name = if cfg!(windows) {
-            "ziit-ls.exe"
+    fn extension_setting(&self, worktree: &Worktree, key: &str) -> Option<zed::serde_json::Value> {
+        LspSettings::for_worktree("ziit-ls", worktree)
+            .ok()?
+            .settings?
+            .get(key)
+            .cloned()
+    }
+
+    fn rollback_requested(&self, worktree: &Worktree) -> bool {
+        self.extension_setting(worktree, "version")
+            .is_some_and(|version| version.as_str() == Some("previous"))
+    }
+
+    fn pinned_version(&self, worktree: &Worktree) -> Option<String> {
+        let version = self
+            .extension_setting(worktree, "version")?
+            .as_str()?
+            .trim()
+            .to_string();
+
+        if version.is_empty() {
+            None
+        } else if version.starts_with('v') {
+            Some(version)
         } else {
-            "ziit-ls"
-        };
+            Some(format!("v{version}"))
+        }
+    }
 
-        log::debug!("Looking for language server binary: {}", ls_name);
+    fn local_archive(&self, worktree: &Worktree) -> Option<String> {
+        let archive = self.extension_setting(worktree, "localArchive")?;
+        let archive = archive.as_str()?.trim();
+        let path = archive.strip_prefix("file://").unwrap_or(archive);
 
+        if path.is_empty() {
+            None
+        } else {
+            Some(path.to_string())
+        }
+    }
 
+    fn install_local_archive(&self, archive_path: &str, ls_name: &str) -> Result<String> {
+        let archive = fs::read(archive_path).map_err(|err| {
+            format!("failed to read local language server archive {archive_path}: {err}")
+        })?;
 
-        let dev_paths = vec![
-            format!(
-                "/home/pandadev/Developer/Extensions/ziit-zed/ziit-ls/target/release/{}",
-                ls_name
-            ),
-            format!(
-                "/home/pandadev/Developer/Extensions/ziit-zed/target/release/{}",
-                ls_name
+        // Key the install directory on the archive contents so replacing the
+        // archive picks up the new binary.
+        let checksum = format!("{:x}", Sha256::digest(&archive));
+        let install_dir = format!("ziit-ls-local-{}", &checksum[..12]);
+        let binary_path = Path::new(&install_dir)
+            .join(ls_name)
+            .to_string_lossy()
+            .to_string();
+
+        if !fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
+            log::info!(
+                "Installing language server from local archive: {}",
+                archive_path
+            );
+            let format = if archive_path.ends_with(".tar.gz") || archive_path.ends_with(".tgz") {
+                ArchiveFormat::TarGz
+            } else {
+                ArchiveFormat::Zip
+            };
+            install_staged(format, &archive, &install_dir, ls_name)?;
+        }
+
+        zed::make_file_executable(&binary_path)?;
+
+        if !fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
+            return Err(format!(
+                "local archive {} does not contain {}",
+                archive_path, ls_name
+            ));
+        }
+
+        Ok(binary_path)
+    }
+
+    // Release metadata and archives are fetched by Zed itself, which only
+    // honors its own `proxy` setting, so point users there when a proxy is
+    // configured in their shell but the download failed.
+    fn with_proxy_hint(&self, worktree: &Worktree, err: String) -> String {
+        let proxy = worktree
+            .shell_env()
+            .into_iter()
+            .find(|(key, value)| PROXY_ENV_VARS.contains(&key.as_str()) && !value.is_empty());
+
+        match proxy {
+            Some((key, _)) => format!(
+                "{err} ({key} is set in your shell, but Zed downloads extension assets through \
+                 its own HTTP client; set the \"proxy\" option in your Zed settings to route \
+                 them through the proxy)"
             ),
-        ];
+            None => err,
+        }
+    }
+
+    // Versioned install directories, newest first.
+    fn installed_versions(&self) -> Vec<(version::Version, String)> {
+        let mut versions: Vec<_> = fs::read_dir(".")
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let dir_name = entry.file_name().to_str()?.to_string();
+                let parsed = version::parse(dir_name.strip_prefix("ziit-ls-")?)?;
+                Some((parsed, dir_name))
+            })
+            .collect();
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+        versions
+    }
+
+    // Installed (version, binary path) pairs, newest first.
+    fn installed_binaries(&self, ls_name: &str) -> Vec<(String, String)> {
+        self.installed_versions()
+            .into_iter()
+            .filter_map(|(_, dir_name)| {
+                let binary_path = Path::new(&dir_name).join(ls_name);
+                binary_path.is_file().then(|| {
+                    (
+                        dir_name["ziit-ls-".len()..].to_string(),
+                        binary_path.to_string_lossy().to_string(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn newest_installed_version(&self, ls_name: &str) -> Option<(String, String)> {
+        self.installed_binaries(ls_name).into_iter().next()
+    }
+
+    fn kept_versions(&self, worktree: &Worktree) -> usize {
+        self.extension_setting(worktree, "keepVersions")
+            .and_then(|keep| keep.as_u64())
+            .map_or(DEFAULT_KEPT_VERSIONS, |keep| keep.max(1) as usize)
+    }
+
+    fn auto_update_enabled(&self, worktree: &Worktree) -> bool {
+        self.extension_setting(worktree, "autoUpdate")
+            .and_then(|auto_update| auto_update.as_bool())
+            .unwrap_or(true)
+    }
+
+    fn update_check_due(&self) -> bool {
+        let last_check = fs::read_to_string(UPDATE_CHECK_FILE)
+            .ok()
+            .and_then(|contents| contents.trim().parse::<u64>().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
 
-        for dev_path in &dev_paths {
-            if fs::metadata(dev_path).map_or(false, |stat| stat.is_file()) {
-                log::info!("Using local development binary at: {}", dev_path);
-                return Ok(dev_path.clone());
+        match last_check {
+            Some(last_check) => SystemTime::now()
+                .duration_since(last_check)
+                .map_or(true, |elapsed| elapsed >= UPDATE_CHECK_INTERVAL),
+            None => true,
+        }
+    }
+
+    fn record_update_check(&self) {
+        if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            fs::write(UPDATE_CHECK_FILE, now.as_secs().to_string()).ok();
+        }
+    }
+
+    // Downloads a newer version next to the running one and marks it as
+    // staged; it is promoted on the next start. Tried once: a failure waits
+    // for the next daily check rather than retrying.
+    fn check_for_update(
+        &self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+        installed_version: &str,
+    ) {
+        self.record_update_check();
+
+        let release = match self.fetch_release(worktree) {
+            Ok(release) => release,
+            Err(err) => {
+                log::warn!("Failed to check for language server updates: {}", err);
+                return;
             }
+        };
+
+        if !version::is_newer(&release.version, installed_version) {
+            log::debug!("Language server {} is up to date", installed_version);
+            return;
         }
 
+        log::info!(
+            "Staging language server {} for the next restart",
+            release.version
+        );
+        let installed_dir = format!("ziit-ls-{installed_version}");
+        let staged = self.download(
+            language_server_id,
+            worktree,
+            "ziit-ls",
+            &release,
+            &[installed_dir.as_str()],
+            1,
+        );
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::None,
+        );
 
-        let worktree_root = worktree.root_path();
-        let local_binary = format!("{}/target/release/{}", worktree_root, ls_name);
-        if fs::metadata(&local_binary).map_or(false, |stat| stat.is_file()) {
-            log::info!(
-                "Using local development binary from worktree at: {}",
-                local_binary
-            );
-            return Ok(local_binary);
+        match staged {
+            Ok(_) => {
+                fs::write(UPDATE_PENDING_FILE, &release.version).ok();
+            }
+            Err(err) => log::warn!("Failed to stage language server update: {}", err),
         }
+    }
+
+    // Switches to the version staged by an earlier check, if it is still
+    // installed. Never downloads anything.
+    fn promote_staged_update(&self, ls_name: &str) -> Option<(String, String)> {
+        let staged_version = fs::read_to_string(UPDATE_PENDING_FILE)
+            .ok()
+            .map(|version| version.trim().to_string())?;
+        fs::remove_file(UPDATE_PENDING_FILE).ok();
+
+        let staged = self
+            .installed_binaries(ls_name)
+            .into_iter()
+            .find(|(version, _)| *version == staged_version)?;
+        log::info!("Switching to staged language server {}", staged_version);
+        Some(staged)
+    }
+
+    // Checks for a newer release right away instead of waiting for the daily
+    // update check. Extensions cannot restart language servers themselves, so
+    // the new version is picked up on the next restart.
+    fn update_now(&self, worktree: &Worktree) -> Result<String> {
+        let ls_name = executable_name("ziit-ls");
+        let language_server_id = self
+            .language_server_id
+            .as_ref()
+            .ok_or("ziit-ls has not been started yet; open a file to start it first")?;
+        let installed = self.newest_installed_version(&ls_name);
 
-        let local_binary_subdir = format!("{}/ziit-ls/target/release/{}", worktree_root, ls_name);
-        if fs::metadata(&local_binary_subdir).map_or(false, |stat| stat.is_file()) {
+        self.record_update_check();
+        let release = self
+            .fetch_release(worktree)
+            .map_err(|err| self.with_proxy_hint(worktree, err))?;
+
+        let installed_dir = match &installed {
+            Some((installed_version, _))
+                if !version::is_newer(&release.version, installed_version) =>
+            {
+                return Ok(format!(
+                    "ziit-ls {installed_version} is already the newest version. Run \
+                     `editor: restart language server` to restart it.\n"
+                ));
+            }
+            Some((installed_version, _)) => format!("ziit-ls-{installed_version}"),
+            None => String::new(),
+        };
+
+        let result = self.download(
+            language_server_id,
+            worktree,
+            "ziit-ls",
+            &release,
+            &[installed_dir.as_str()],
+            DOWNLOAD_ATTEMPTS,
+        );
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::None,
+        );
+        result.map_err(|err| self.with_proxy_hint(worktree, err))?;
+
+        Ok(format!(
+            "Downloaded ziit-ls {}. Run `editor: restart language server` to switch to it.\n",
+            release.version
+        ))
+    }
+
+    fn use_preview_channel(&self, worktree: &Worktree) -> bool {
+        let channel = self.extension_setting(worktree, "channel");
+        match channel.as_ref().and_then(|channel| channel.as_str()) {
+            Some("preview") => true,
+            Some("stable") | None => false,
+            Some(other) => {
+                log::warn!("Unknown release channel {:?}, using stable", other);
+                false
+            }
+        }
+    }
+
+    fn language_server_binary_path(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<String> {
+        let ls_name = &executable_name("ziit-ls");
+
+        log::debug!("Looking for language server binary: {}", ls_name);
+
+        if let Some(path) = worktree
+            .shell_env()
+            .into_iter()
+            .find(|(key, value)| key == BINARY_PATH_ENV_VAR && !value.is_empty())
+            .map(|(_, value)| value)
+        {
             log::info!(
-                "Using local development binary from worktree subdir at: {}",
-                local_binary_subdir
+                "Using language server from {}: {}",
+                BINARY_PATH_ENV_VAR,
+                path
             );
-            return Ok(local_binary_subdir);
+            return Ok(path);
         }
 
         if let Some(path) = worktree.which(ls_name) {
@@ -159,35 +667,53 @@ impl ZiitExtension {
             return Ok(path.clone());
         }
 
-        let target_triple = self.target_triple()?;
+        let target_triple = self.target_triple(worktree)?;
         if let Some(path) = worktree.which(&target_triple) {
             log::debug!("Found language server via target triple: {}", path);
             return Ok(path.clone());
         }
 
-        if let Some(path) = &self.cached_binary_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
-                log::debug!("Using cached language server path: {}", path);
-                return Ok(path.clone());
-            }
+        if let Some(archive_path) = self.local_archive(worktree) {
+            let binary_path = self.install_local_archive(&archive_path, ls_name)?;
+            return Ok(binary_path);
         }
 
-        if let Ok(entries) = fs::read_dir(".") {
-            for entry in entries.flatten() {
-                if let Some(dir_name) = entry.file_name().to_str() {
-                    if dir_name.starts_with("ziit-ls-v") {
-                        let potential_binary = entry.path().join(ls_name);
-                        if potential_binary.exists() && potential_binary.is_file() {
-                            let binary_path_str = potential_binary.to_string_lossy().to_string();
-                            log::info!(
-                                "Found existing language server binary at: {}",
-                                binary_path_str
-                            );
-                            self.cached_binary_path = Some(binary_path_str.clone());
-                            return Ok(binary_path_str);
-                        }
-                    }
+        if self.rollback_requested(worktree) {
+            let (previous_version, binary_path) = self
+                .installed_binaries(ls_name)
+                .into_iter()
+                .nth(1)
+                .ok_or_else(|| {
+                    "\"version\": \"previous\" is set, but no previous language server \
+                     version is installed"
+                        .to_string()
+                })?;
+            log::info!("Rolled back to language server {}", previous_version);
+            return Ok(binary_path);
+        }
+
+        let pinned_version = self.pinned_version(worktree);
+        if let Some(version) = &pinned_version {
+            let pinned_binary = Path::new(&format!("ziit-ls-{version}")).join(ls_name);
+            if pinned_binary.is_file() {
+                let binary_path = pinned_binary.to_string_lossy().to_string();
+                log::debug!("Using pinned language server {}: {}", version, binary_path);
+                return Ok(binary_path);
+            }
+        } else {
+            // Prefer an update staged by a previous check, then the newest
+            // installed version.
+            let installed = self
+                .promote_staged_update(ls_name)
+                .or_else(|| self.newest_installed_version(ls_name));
+            if let Some((installed_version, binary_path)) = installed {
+                log::info!("Found existing language server binary at: {}", binary_path);
+                // A staged update is only picked up on the next start, so the
+                // download never replaces the binary returned here.
+                if self.auto_update_enabled(worktree) && self.update_check_due() {
+                    self.check_for_update(language_server_id, worktree, &installed_version);
                 }
+                return Ok(binary_path);
             }
         }
 
@@ -197,19 +723,216 @@ impl ZiitExtension {
         );
 
         log::debug!("Downloading language server binary from GitHub");
-        let binary_path = self.download(language_server_id, "ziit-ls", "0PandaDEV/ziit-zed")?;
+        let binary_path = self
+            .fetch_release(worktree)
+            .and_then(|release| {
+                self.download(
+                    language_server_id,
+                    worktree,
+                    "ziit-ls",
+                    &release,
+                    &[],
+                    DOWNLOAD_ATTEMPTS,
+                )
+            })
+            .map_err(|err| self.with_proxy_hint(worktree, err))
+            .inspect_err(|err| {
+                zed::set_language_server_installation_status(
+                    language_server_id,
+                    &zed::LanguageServerInstallationStatus::Failed(err.clone()),
+                );
+            })?;
+        self.record_update_check();
         log::debug!("Downloaded language server to: {}", binary_path);
 
-        self.cached_binary_path = Some(binary_path.clone());
-
         Ok(binary_path)
     }
 }
 
+// Zed only distinguishes x86, x86-64 and AArch64 hosts, so ask the system for
+// the architectures we ship that it cannot name (Raspberry Pi, RISC-V boards).
+fn machine_architecture() -> Option<&'static str> {
+    let output = zed::process::Command::new("uname")
+        .arg("-m")
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "armv7l" | "armv7" | "armv8l" => Some("armv7"),
+        "riscv64" => Some("riscv64gc"),
+        _ => None,
+    }
+}
+
+// Extracts into a staging directory next to `install_dir` and only renames it
+// into place once the binary checks out, so a crash or a bad archive never
+// leaves a half-populated install directory that looks usable.
+fn install_staged(
+    format: ArchiveFormat,
+    archive: &[u8],
+    install_dir: &str,
+    binary_name: &str,
+) -> Result<()> {
+    let staging_dir = staging_dir_name(install_dir);
+    let staged_binary = Path::new(&staging_dir)
+        .join(binary_name)
+        .to_string_lossy()
+        .to_string();
+
+    fs::remove_dir_all(&staging_dir).ok();
+    let staged = archive::extract(format, archive, &staging_dir)
+        .and_then(|()| verify_extracted_binary(&staged_binary))
+        .and_then(|()| zed::make_file_executable(&staged_binary));
+    if let Err(err) = staged {
+        fs::remove_dir_all(&staging_dir).ok();
+        return Err(err);
+    }
+
+    fs::remove_dir_all(install_dir).ok();
+    fs::rename(&staging_dir, install_dir).map_err(|err| {
+        fs::remove_dir_all(&staging_dir).ok();
+        format!("failed to move {staging_dir} into place at {install_dir}: {err}")
+    })
+}
+
+// `ziit-ls-v1.2.3` is staged as `ziit-ls-staging-v1.2.3`, which never parses
+// as an installed version but is still swept up by the cleanup of old
+// `ziit-ls*` directories if an install is interrupted.
+fn staging_dir_name(install_dir: &str) -> String {
+    match install_dir.strip_prefix("ziit-ls-") {
+        Some(rest) => format!("ziit-ls-staging-{rest}"),
+        None => format!("{install_dir}-staging"),
+    }
+}
+
+fn verify_extracted_binary(binary_path: &str) -> Result<()> {
+    match fs::metadata(binary_path) {
+        Ok(stat) if stat.is_file() && stat.len() > 0 => Ok(()),
+        Ok(_) => Err(format!(
+            "extracted binary {binary_path} is empty or not a file"
+        )),
+        Err(_) => Err(format!("release archive did not contain {binary_path}")),
+    }
+}
+
+// Zed's log is shared by every extension, so keep API keys out of it.
+fn redact_api_keys(value: &zed::serde_json::Value) -> zed::serde_json::Value {
+    use zed::serde_json::Value;
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if key == "apiKey" {
+                        Value::String("<redacted>".to_string())
+                    } else {
+                        redact_api_keys(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_api_keys).collect()),
+        other => other.clone(),
+    }
+}
+
+// The extension itself is compiled to WASM, so `cfg!(windows)` is always
+// false here; ask Zed which platform it is running on instead.
+fn executable_name(binary: &str) -> String {
+    match zed::current_platform().0 {
+        zed::Os::Windows => format!("{binary}.exe"),
+        zed::Os::Mac | zed::Os::Linux => binary.to_string(),
+    }
+}
+
+fn format_duration(total_seconds: u64) -> String {
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+fn status_report() -> Result<String> {
+    let contents = fs::read_to_string(STATUS_FILE).map_err(|_| {
+        "No status available yet. Open a file so the Ziit language server starts, then try again."
+            .to_string()
+    })?;
+    let status: zed::serde_json::Value = zed::serde_json::from_str(&contents)
+        .map_err(|err| format!("failed to parse {STATUS_FILE}: {err}"))?;
+
+    let today = status
+        .get("todaySeconds")
+        .and_then(|seconds| seconds.as_u64())
+        .map_or_else(|| "unknown".to_string(), format_duration);
+    let online = status.get("online").and_then(|online| online.as_bool());
+    let api_key_valid = status.get("apiKeyValid").and_then(|valid| valid.as_bool());
+    let queued = status
+        .get("queuedHeartbeats")
+        .and_then(|queued| queued.as_u64())
+        .unwrap_or(0);
+    let updated_at = status
+        .get("updatedAt")
+        .and_then(|updated_at| updated_at.as_str())
+        .unwrap_or("unknown");
+
+    let sync = match (api_key_valid, online) {
+        (Some(false), _) => "API key missing or invalid".to_string(),
+        (_, Some(false)) => format!("offline, {queued} heartbeats queued"),
+        _ if queued > 0 => format!("online, {queued} heartbeats waiting to sync"),
+        _ => "online, all heartbeats synced".to_string(),
+    };
+
+    Ok(format!(
+        "Ziit status\nToday: {today}\nSync: {sync}\nUpdated: {updated_at}\n"
+    ))
+}
+
+fn read_status_file() -> Option<zed::serde_json::Value> {
+    let contents = fs::read_to_string(STATUS_FILE).ok()?;
+    zed::serde_json::from_str(&contents).ok()
+}
+
+fn dashboard_url(worktree: Option<&Worktree>) -> String {
+    let configured_base_url = worktree
+        .and_then(|worktree| LspSettings::for_worktree("ziit-ls", worktree).ok())
+        .and_then(|lsp_settings| {
+            [lsp_settings.initialization_options, lsp_settings.settings]
+                .into_iter()
+                .flatten()
+                .find_map(|options| options.get("baseUrl")?.as_str().map(str::to_string))
+        });
+    if let Some(base_url) = configured_base_url {
+        return format!("{}/dashboard", base_url.trim_end_matches('/'));
+    }
+
+    read_status_file()
+        .and_then(|status| status.get("dashboardUrl")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{DEFAULT_BASE_URL}/dashboard"))
+}
+
+fn open_in_browser(url: &str) -> Result<()> {
+    let (platform, _) = zed::current_platform();
+    let mut command = match platform {
+        zed::Os::Mac => zed::process::Command::new("open").arg(url),
+        zed::Os::Linux => zed::process::Command::new("xdg-open").arg(url),
+        zed::Os::Windows => zed::process::Command::new("cmd").args(["/C", "start", "", url]),
+    };
+
+    let output = command.output()?;
+    if output.status == Some(0) {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
 impl Extension for ZiitExtension {
     fn new() -> Self {
         Self {
-            cached_binary_path: None,
+            language_server_id: None,
+            binary_path: None,
         }
     }
 
@@ -218,20 +941,53 @@ impl Extension for ZiitExtension {
         language_server_id: &LanguageServerId,
         worktree: &Worktree,
     ) -> Result<Command> {
-        let binary_path = self.language_server_binary_path(language_server_id, worktree)?;
+        self.language_server_id = Some(language_server_id.clone());
 
-        if let Err(err) = fs::metadata(&binary_path) {
-            return Err(format!("Binary not found at path {}: {}", binary_path, err).into());
-        }
+        let binary_settings = LspSettings::for_worktree("ziit-ls", worktree)
+            .ok()
+            .and_then(|lsp_settings| lsp_settings.binary);
+
+        let binary_path = match binary_settings
+            .as_ref()
+            .and_then(|binary| binary.path.clone())
+        {
+            Some(path) => {
+                log::info!("Using language server binary from settings: {}", path);
+                path
+            }
+            None => {
+                let binary_path = self.language_server_binary_path(language_server_id, worktree)?;
+                if let Err(err) = fs::metadata(&binary_path) {
+                    return Err(format!("Binary not found at path {}: {}", binary_path, err));
+                }
+                binary_path
+            }
+        };
 
         log::info!("Executing language server binary: {}", binary_path);
+        self.binary_path = Some(binary_path.clone());
+
+        let args = binary_settings
+            .as_ref()
+            .and_then(|binary| binary.arguments.clone())
+            .unwrap_or_else(|| vec!["--standalone".to_string()]);
 
-        let args = vec!["--standalone".to_string()];
+        let mut env = worktree.shell_env();
+        if let Ok(work_dir) = std::env::current_dir() {
+            env.push((
+                STATUS_FILE_ENV_VAR.to_string(),
+                work_dir.join(STATUS_FILE).to_string_lossy().to_string(),
+            ));
+        }
+        if let Some(binary_env) = binary_settings.and_then(|binary| binary.env) {
+            env.retain(|(key, _)| !binary_env.contains_key(key));
+            env.extend(binary_env);
+        }
 
         Ok(Command {
             command: binary_path,
             args,
-            env: worktree.shell_env(),
+            env,
         })
     }
 
@@ -240,14 +996,27 @@ impl Extension for ZiitExtension {
         _language_server_id: &LanguageServerId,
         worktree: &Worktree,
     ) -> Result<Option<zed::serde_json::Value>> {
-        let settings = LspSettings::for_worktree("ziit-ls", worktree)
+        let mut settings = LspSettings::for_worktree("ziit-ls", worktree)
             .ok()
-            .and_then(|lsp_settings| lsp_settings.initialization_options.clone());
+            .and_then(|lsp_settings| lsp_settings.initialization_options);
+
+        // Explicit initialization options take precedence over the `ziit` block.
+        let ziit_settings = self.ziit_settings(worktree);
+        if !ziit_settings.is_empty() {
+            let mut options = match settings.take() {
+                Some(zed::serde_json::Value::Object(options)) => options,
+                _ => zed::serde_json::Map::new(),
+            };
+            for (key, value) in ziit_settings {
+                options.entry(key).or_insert(value);
+            }
+            settings = Some(zed::serde_json::Value::Object(options));
+        }
 
         if let Some(options) = &settings {
-            log::info!(
+            log::debug!(
                 "Passing initialization options to language server: {:?}",
-                options
+                redact_api_keys(options)
             );
             return Ok(Some(options.clone()));
         }
@@ -276,7 +1045,7 @@ impl Extension for ZiitExtension {
                 zed::serde_json::from_str::<zed::serde_json::Value>(&config_content)
             {
                 log::info!("Successfully read config from file: {}", config_path);
-                log::info!("Config from file: {:?}", config_json);
+                log::debug!("Config from file: {:?}", redact_api_keys(&config_json));
                 return Ok(Some(config_json));
             }
         }
@@ -290,16 +1059,111 @@ impl Extension for ZiitExtension {
         Ok(None)
     }
 
+    fn run_slash_command(
+        &self,
+        command: SlashCommand,
+        _args: Vec<String>,
+        worktree: Option<&Worktree>,
+    ) -> Result<SlashCommandOutput> {
+        match command.name.as_str() {
+            "ziit-status" => {
+                let text = status_report()?;
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Ziit status".to_string(),
+                    }],
+                    text,
+                })
+            }
+            "ziit-dashboard" => {
+                let url = dashboard_url(worktree);
+                let text = match open_in_browser(&url) {
+                    Ok(()) => format!("Opened the Ziit dashboard: {url}\n"),
+                    Err(err) => {
+                        log::warn!("Failed to open browser: {}", err);
+                        format!("Open the Ziit dashboard at {url}\n")
+                    }
+                };
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Ziit dashboard".to_string(),
+                    }],
+                    text,
+                })
+            }
+            "ziit-update" => {
+                let worktree = worktree.ok_or("open a project to update ziit-ls")?;
+                let text = self.update_now(worktree)?;
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Ziit update".to_string(),
+                    }],
+                    text,
+                })
+            }
+            command => Err(format!("unknown slash command: \"{command}\"")),
+        }
+    }
+
+    fn context_server_command(
+        &mut self,
+        context_server_id: &ContextServerId,
+        project: &Project,
+    ) -> Result<Command> {
+        let command_settings =
+            ContextServerSettings::for_project(context_server_id.as_ref(), project)
+                .ok()
+                .and_then(|settings| settings.command);
+
+        let binary_path = match command_settings
+            .as_ref()
+            .and_then(|command| command.path.clone())
+        {
+            Some(path) => path,
+            None => {
+                let (_, binary_path) = self
+                    .newest_installed_version(&executable_name("ziit-ls"))
+                    .ok_or_else(|| {
+                    "The Ziit language server is not installed yet; open a file once so \
+                         the extension can download it"
+                        .to_string()
+                })?;
+                std::env::current_dir()
+                    .map(|work_dir| work_dir.join(&binary_path).to_string_lossy().to_string())
+                    .unwrap_or(binary_path)
+            }
+        };
+
+        let args = command_settings
+            .as_ref()
+            .and_then(|command| command.arguments.clone())
+            .unwrap_or_else(|| vec!["--mcp".to_string()]);
+        let env = command_settings
+            .and_then(|command| command.env)
+            .map(|env| env.into_iter().collect())
+            .unwrap_or_default();
+
+        Ok(Command {
+            command: binary_path,
+            args,
+            env,
+        })
+    }
+
     fn language_server_workspace_configuration(
         &mut self,
         _language_server_id: &LanguageServerId,
         worktree: &Worktree,
     ) -> Result<Option<zed::serde_json::Value>> {
-        let settings = LspSettings::for_worktree("ziit-ls", worktree)
-            .ok()
-            .and_then(|lsp_settings| lsp_settings.settings.clone());
+        let ziit_settings = self.ziit_settings(worktree);
+        if ziit_settings.is_empty() {
+            return Ok(None);
+        }
 
-        Ok(settings)
+        Ok(Some(zed::serde_json::Value::Object(ziit_settings)))
     }
 }
 
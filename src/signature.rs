@@ -0,0 +1,34 @@
+use minisign_verify::{PublicKey, Signature};
+
+// Minisign public key of the release signing key. Every downloaded archive
+// must come with a `<asset>.minisig` signed by the matching secret key.
+const RELEASE_PUBLIC_KEY: &str = "RWQB9LW+fwgtqwJn76aPiaqotThlbA68yGuQDc55bWx58HXqadTj9Ux5";
+
+pub fn verify(archive: &[u8], signature: &str, asset_name: &str) -> Result<(), String> {
+    let public_key = PublicKey::from_base64(RELEASE_PUBLIC_KEY)
+        .map_err(|err| format!("invalid release public key: {err}"))?;
+    let signature = Signature::decode(signature)
+        .map_err(|err| format!("malformed signature file for {asset_name}: {err}"))?;
+
+    public_key
+        .verify(archive, &signature, false)
+        .map_err(|err| format!("signature verification failed for {asset_name}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNATURE: &str = "untrusted comment: signature from rsign secret key
+RUQB9LW+fwgtq2CPegV11q278NAdDYSt1hzjh3n3Wb6C1HurMNLTemZQliKm/otFJ6mO0cJesfDALUsfUaKLX4dUzCF/kws2zA0=
+trusted comment: timestamp:1792077674\tfile:t.zip\tprehashed
+z/GwHT4t5IN8wnFfuway/+NQCMIWRn40RN6xgVxzaielMAmEvVUdcgYjOlzBgZ7vXeMNL/1m1lAzok4dOqavBQ==
+";
+
+    #[test]
+    fn test_only_signed_archives_are_accepted() {
+        assert!(verify(b"test\n", SIGNATURE, "t.zip").is_ok());
+        assert!(verify(b"tampered\n", SIGNATURE, "t.zip").is_err());
+        assert!(verify(b"test\n", "not a signature", "t.zip").is_err());
+    }
+}
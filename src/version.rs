@@ -0,0 +1,82 @@
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    numbers: [u64; 3],
+    pre_release: Option<String>,
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers.cmp(&other.numbers).then_with(|| {
+            match (&self.pre_release, &other.pre_release) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            }
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Parses release tags like `v1.2.3` or `v1.3.0-rc.1`.
+pub fn parse(tag: &str) -> Option<Version> {
+    let tag = tag.strip_prefix('v').unwrap_or(tag);
+    let (core, pre_release) = match tag.split_once('-') {
+        Some((core, pre_release)) => (core, Some(pre_release.to_string())),
+        None => (tag, None),
+    };
+
+    let mut numbers = [0u64; 3];
+    let mut parts = core.split('.');
+    for number in &mut numbers {
+        *number = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Version {
+        numbers,
+        pre_release,
+    })
+}
+
+pub fn is_newer(candidate: &str, installed: &str) -> bool {
+    match (parse(candidate), parse(installed)) {
+        (Some(candidate), Some(installed)) => candidate > installed,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_release_tags() {
+        assert!(parse("v1.2.3").is_some());
+        assert!(parse("1.2.3").is_some());
+        assert!(parse("v1.2").is_none());
+        assert!(parse("v1.2.3.4").is_none());
+        assert!(parse("latest").is_none());
+        assert!(parse("staging-v1.3.0-rc.1").is_none());
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        assert!(is_newer("v1.10.0", "v1.9.9"));
+        assert!(is_newer("v2.0.0", "v1.99.0"));
+        assert!(is_newer("v1.3.0", "v1.3.0-rc.2"));
+        assert!(is_newer("v1.3.0-rc.2", "v1.3.0-rc.1"));
+        assert!(!is_newer("v1.2.3", "v1.2.3"));
+        assert!(!is_newer("v1.2.2", "v1.2.3"));
+        assert!(!is_newer("garbage", "v1.2.3"));
+    }
+}
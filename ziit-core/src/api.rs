@@ -0,0 +1,300 @@
+use crate::heartbeat::Heartbeat;
+use crate::request_log::{self, RequestRecord};
+use crate::{metrics, redact};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DailySummaryResponse {
+    pub summaries: Vec<SummaryEntry>,
+    pub timezone: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SummaryEntry {
+    pub date: String,
+    #[serde(rename = "totalSeconds")]
+    pub total_seconds: u64,
+    #[serde(rename = "hourlyData")]
+    pub hourly_data: Option<Vec<HourlyData>>,
+    #[serde(default)]
+    pub projects: Option<HashMap<String, u64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HourlyData {
+    pub seconds: u64,
+}
+
+// Times one request for the metrics and keeps it in the request log.
+struct Tracked {
+    endpoint: &'static str,
+    method: &'static str,
+    url: String,
+    at: DateTime<Utc>,
+    started: Instant,
+}
+
+impl Tracked {
+    fn start(endpoint: &'static str, method: &'static str, url: &str) -> Self {
+        Self {
+            endpoint,
+            method,
+            url: url.to_string(),
+            at: Utc::now(),
+            started: Instant::now(),
+        }
+    }
+
+    fn finish(self, status: Option<reqwest::StatusCode>, error: Option<String>) {
+        let elapsed = self.started.elapsed();
+        metrics::observe_api_latency(self.endpoint, elapsed);
+        request_log::record(RequestRecord {
+            at: self.at,
+            method: self.method.to_string(),
+            url: self.url,
+            status: status.map(|status| status.as_u16()),
+            duration_ms: elapsed.as_millis() as u64,
+            error,
+        });
+    }
+
+    fn fail(self, error: reqwest::Error) -> anyhow::Error {
+        self.finish(None, Some(error.to_string()));
+        error.into()
+    }
+}
+
+#[tracing::instrument(name = "api", skip_all, fields(endpoint = "heartbeats"))]
+pub async fn send_heartbeat_request(
+    base_url: &str,
+    api_key: &str,
+    heartbeat: Heartbeat,
+) -> Result<()> {
+    let url = format!("{}/api/external/heartbeats", base_url);
+    let client = reqwest::Client::new();
+
+    log::debug!("Sending heartbeat to: {}", url);
+    if redact::log_payloads() {
+        log::debug!(
+            "Heartbeat JSON being sent:\n{}",
+            serde_json::to_string_pretty(&heartbeat)?
+        );
+    }
+
+    let tracked = Tracked::start("heartbeats", "POST", &url);
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .json(&heartbeat)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => return Err(tracked.fail(e)),
+    };
+
+    log::debug!("Response status: {}", response.status());
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_body = response.text().await.unwrap_or_default();
+        log::error!("Heartbeat failed with status {}: {}", status, error_body);
+        tracked.finish(Some(status), Some(error_body));
+        if redact::log_payloads() {
+            log::debug!(
+                "Failed request was: POST {} with body:\n{}",
+                url,
+                serde_json::to_string_pretty(&heartbeat)?
+            );
+        }
+        return Err(anyhow!("Failed to send heartbeat: HTTP {}", status));
+    }
+
+    tracked.finish(Some(status), None);
+    log::info!("Heartbeat sent successfully!");
+    Ok(())
+}
+
+#[tracing::instrument(name = "api", skip_all, fields(endpoint = "batch", count = heartbeats.len()))]
+pub async fn send_batch_heartbeats_request(
+    base_url: &str,
+    api_key: &str,
+    heartbeats: Vec<Heartbeat>,
+) -> Result<()> {
+    let url = format!("{}/api/external/batch", base_url);
+    let client = reqwest::Client::new();
+
+    log::debug!(
+        "Sending {} heartbeats in batch to: {}",
+        heartbeats.len(),
+        url
+    );
+
+    let tracked = Tracked::start("batch", "POST", &url);
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .json(&heartbeats)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => return Err(tracked.fail(e)),
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_body = response.text().await.unwrap_or_default();
+        log::error!(
+            "Batch heartbeat failed with status {}: {}",
+            status,
+            error_body
+        );
+        tracked.finish(Some(status), Some(error_body));
+        return Err(anyhow!("Failed to send batch heartbeats: HTTP {}", status));
+    }
+
+    tracked.finish(Some(status), None);
+    log::debug!("Batch heartbeats sent successfully");
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+struct HeartbeatSchemaResponse {
+    version: u32,
+}
+
+// Older servers do not have this endpoint, which callers treat as v1.
+#[tracing::instrument(name = "api", skip_all, fields(endpoint = "schema"))]
+pub async fn fetch_heartbeat_schema_request(base_url: &str, api_key: &str) -> Result<u32> {
+    let url = format!("{}/api/external/heartbeats/schema", base_url);
+    let client = reqwest::Client::new();
+
+    log::debug!("Fetching heartbeat schema from: {}", url);
+
+    let tracked = Tracked::start("schema", "GET", &url);
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => return Err(tracked.fail(e)),
+    };
+
+    let status = response.status();
+    tracked.finish(Some(status), None);
+    if !status.is_success() {
+        return Err(anyhow!("Failed to fetch heartbeat schema: HTTP {}", status));
+    }
+
+    Ok(response.json::<HeartbeatSchemaResponse>().await?.version)
+}
+
+pub async fn send_error_reports_request(
+    base_url: &str,
+    api_key: &str,
+    reports: &[crate::telemetry::ErrorReport],
+) -> Result<()> {
+    let url = format!("{}/api/external/errors", base_url);
+    let tracked = Tracked::start("errors", "POST", &url);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .json(reports)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => return Err(tracked.fail(e)),
+    };
+
+    let status = response.status();
+    tracked.finish(Some(status), None);
+    if !status.is_success() {
+        return Err(anyhow!("Failed to send error reports: HTTP {}", status));
+    }
+    Ok(())
+}
+
+// The server's clock as reported in the Date header of any response.
+pub async fn fetch_server_time(base_url: &str) -> Result<DateTime<Utc>> {
+    let tracked = Tracked::start("time", "HEAD", base_url);
+    let response = match reqwest::Client::new().head(base_url).send().await {
+        Ok(response) => response,
+        Err(e) => return Err(tracked.fail(e)),
+    };
+    tracked.finish(Some(response.status()), None);
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .ok_or_else(|| anyhow!("No Date header in the response"))?
+        .to_str()?;
+    Ok(DateTime::parse_from_rfc2822(date)?.with_timezone(&Utc))
+}
+
+pub async fn fetch_daily_summary_request(
+    base_url: &str,
+    api_key: &str,
+) -> Result<DailySummaryResponse> {
+    fetch_summary_request(base_url, api_key, "today").await
+}
+
+#[tracing::instrument(name = "api", skip_all, fields(endpoint = "stats", range = time_range))]
+pub async fn fetch_summary_request(
+    base_url: &str,
+    api_key: &str,
+    time_range: &str,
+) -> Result<DailySummaryResponse> {
+    let midnight_offset_seconds = crate::timezone::configured()
+        .await
+        .midnight_offset_seconds();
+
+    let url = format!(
+        "{}/api/external/stats?timeRange={}&midnightOffsetSeconds={}&t={}",
+        base_url,
+        time_range,
+        midnight_offset_seconds,
+        Utc::now().timestamp_millis()
+    );
+
+    let client = reqwest::Client::new();
+
+    log::debug!("Fetching {} summary from: {}", time_range, url);
+
+    let tracked = Tracked::start("stats", "GET", &url);
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => return Err(tracked.fail(e)),
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_body = response.text().await.unwrap_or_default();
+        log::error!(
+            "Summary fetch failed with status {}: {}",
+            status,
+            error_body
+        );
+        tracked.finish(Some(status), Some(error_body));
+        return Err(anyhow!("Failed to fetch summary: HTTP {}", status));
+    }
+
+    tracked.finish(Some(status), None);
+    let summary = response.json::<DailySummaryResponse>().await?;
+    log::debug!("Summary fetched successfully");
+
+    Ok(summary)
+}
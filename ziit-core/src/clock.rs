@@ -0,0 +1,120 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::time::Instant;
+
+// How far the wall clock may drift from the monotonic clock between two
+// heartbeats before it counts as a jump (sleep/resume, NTP step, manual change).
+const CLOCK_JUMP_THRESHOLD_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    pub at: DateTime<Utc>,
+    // Set when the wall clock jumped since the previous stamp, by how much.
+    pub clock_jump_seconds: Option<i64>,
+}
+
+// Issues heartbeat timestamps that never go backwards. The wall clock is
+// checked against a monotonic reference so jumps can be reported alongside
+// the heartbeat instead of silently skewing durations on the server.
+#[derive(Debug, Default)]
+pub struct HeartbeatClock {
+    anchor: Option<(Instant, DateTime<Utc>)>,
+    last_issued: Option<DateTime<Utc>>,
+}
+
+impl HeartbeatClock {
+    pub fn now(&mut self) -> Stamp {
+        self.stamp(Utc::now(), Instant::now())
+    }
+
+    pub fn stamp(&mut self, wall: DateTime<Utc>, monotonic: Instant) -> Stamp {
+        let clock_jump_seconds = self.anchor.and_then(|(anchor_monotonic, anchor_wall)| {
+            let elapsed = monotonic.saturating_duration_since(anchor_monotonic);
+            let expected = anchor_wall + TimeDelta::from_std(elapsed).ok()?;
+            let drift = (wall - expected).num_seconds();
+            (drift.abs() > CLOCK_JUMP_THRESHOLD_SECONDS).then_some(drift)
+        });
+        if let Some(drift) = clock_jump_seconds {
+            log::warn!("Wall clock jumped by {} seconds", drift);
+        }
+        self.anchor = Some((monotonic, wall));
+
+        // Timestamps are sent with second precision, so bump anything that
+        // would not sort strictly after the previous heartbeat.
+        let at = match self.last_issued {
+            Some(last) if wall < last + TimeDelta::seconds(1) => last + TimeDelta::seconds(1),
+            _ => wall,
+        };
+        self.last_issued = Some(at);
+
+        Stamp {
+            at,
+            clock_jump_seconds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_steady_clock_is_untouched() {
+        let mut clock = HeartbeatClock::default();
+        let wall = Utc::now();
+        let monotonic = Instant::now();
+
+        let first = clock.stamp(wall, monotonic);
+        let second = clock.stamp(
+            wall + TimeDelta::seconds(120),
+            monotonic + Duration::from_secs(120),
+        );
+
+        assert_eq!(first.at, wall);
+        assert_eq!(second.at, wall + TimeDelta::seconds(120));
+        assert_eq!(second.clock_jump_seconds, None);
+    }
+
+    #[test]
+    fn test_backwards_jump_is_clamped_and_annotated() {
+        let mut clock = HeartbeatClock::default();
+        let wall = Utc::now();
+        let monotonic = Instant::now();
+
+        clock.stamp(wall, monotonic);
+        let stamp = clock.stamp(
+            wall - TimeDelta::seconds(3600),
+            monotonic + Duration::from_secs(10),
+        );
+
+        assert_eq!(stamp.at, wall + TimeDelta::seconds(1));
+        assert_eq!(stamp.clock_jump_seconds, Some(-3610));
+    }
+
+    #[test]
+    fn test_forward_jump_keeps_wall_time() {
+        let mut clock = HeartbeatClock::default();
+        let wall = Utc::now();
+        let monotonic = Instant::now();
+
+        clock.stamp(wall, monotonic);
+        let resumed = wall + TimeDelta::seconds(8 * 3600);
+        let stamp = clock.stamp(resumed, monotonic + Duration::from_secs(5));
+
+        assert_eq!(stamp.at, resumed);
+        assert_eq!(stamp.clock_jump_seconds, Some(8 * 3600 - 5));
+    }
+
+    #[test]
+    fn test_same_second_stamps_stay_ordered() {
+        let mut clock = HeartbeatClock::default();
+        let wall = Utc::now();
+        let monotonic = Instant::now();
+
+        let first = clock.stamp(wall, monotonic);
+        let second = clock.stamp(wall, monotonic);
+
+        assert!(second.at > first.at);
+        assert_eq!(second.clock_jump_seconds, None);
+    }
+}
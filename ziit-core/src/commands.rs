@@ -1,4 +1,5 @@
 use crate::config::{read_config_file, write_config_file};
+use crate::vcs::{git_version, set_git_path};
 use anyhow::Result;
 
 pub async fn set_api_key(api_key: String) -> Result<String> {
@@ -27,6 +28,8 @@ pub async fn get_dashboard_url() -> Result<String> {
 
 pub async fn get_config_status() -> Result<ConfigStatus> {
     let config = read_config_file().await?;
+    set_git_path(config.git_path.as_ref().map(std::path::PathBuf::from));
+    let git_version = tokio::task::spawn_blocking(git_version).await?;
 
     Ok(ConfigStatus {
         has_api_key: config.api_key.is_some(),
@@ -34,6 +37,7 @@ pub async fn get_config_status() -> Result<ConfigStatus> {
             .base_url
             .unwrap_or_else(|| "https://ziit.app".to_string()),
         config_path: get_config_path_string()?,
+        git_version,
     })
 }
 
@@ -42,6 +46,7 @@ pub struct ConfigStatus {
     pub has_api_key: bool,
     pub base_url: String,
     pub config_path: String,
+    pub git_version: Option<String>,
 }
 
 fn get_config_path_string() -> Result<String> {
@@ -0,0 +1,396 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+const CONFIG_FILE_NAME: &str = "config.json";
+const LEGACY_CONFIG_FILE_NAMES: &[&str] = &[".ziit.json", ".ziit.cfg"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ZiitConfig {
+    #[serde(rename = "apiKey")]
+    pub api_key: Option<String>,
+    #[serde(rename = "baseUrl")]
+    pub base_url: Option<String>,
+    #[serde(rename = "projectNameFormat")]
+    pub project_name_format: Option<ProjectNameFormat>,
+    #[serde(rename = "detachedHeadFormat")]
+    pub detached_head_format: Option<DetachedHeadFormat>,
+    #[serde(rename = "trackUncommittedChanges")]
+    pub track_uncommitted_changes: Option<bool>,
+    #[serde(rename = "nestedRepositoryPolicy")]
+    pub nested_repository_policy: Option<NestedRepositoryPolicy>,
+    #[serde(rename = "pathMappings")]
+    pub path_mappings: Option<Vec<PathMapping>>,
+    #[serde(rename = "gitPath")]
+    pub git_path: Option<String>,
+    #[serde(rename = "languageMap")]
+    pub language_map: Option<HashMap<String, String>>,
+    #[serde(rename = "generatedFilePolicy")]
+    pub generated_file_policy: Option<GeneratedFilePolicy>,
+    #[serde(rename = "maxFileSizeBytes")]
+    pub max_file_size_bytes: Option<u64>,
+    #[serde(rename = "skipBinaryFiles")]
+    pub skip_binary_files: Option<bool>,
+    #[serde(rename = "contentSampling")]
+    pub content_sampling: Option<bool>,
+    #[serde(rename = "dryRun")]
+    pub dry_run: Option<bool>,
+    #[serde(rename = "heartbeatSchema")]
+    pub heartbeat_schema: Option<HeartbeatSchema>,
+    #[serde(rename = "errorReporting")]
+    pub error_reporting: Option<bool>,
+    #[serde(rename = "dailySummary")]
+    pub daily_summary: Option<String>,
+    #[serde(rename = "additionalServers")]
+    pub additional_servers: Option<Vec<crate::mirror::MirrorTarget>>,
+    pub timezone: Option<String>,
+    pub locale: Option<String>,
+    #[serde(rename = "offlineRetentionDays")]
+    pub offline_retention_days: Option<u32>,
+    #[serde(rename = "historyRetentionDays")]
+    pub history_retention_days: Option<u32>,
+    #[serde(rename = "editorCoordination")]
+    pub editor_coordination: Option<EditorCoordination>,
+    #[serde(rename = "startupWarmupSeconds")]
+    pub startup_warmup_seconds: Option<u32>,
+    #[serde(rename = "heartbeatIntervalSeconds")]
+    pub heartbeat_interval_seconds: Option<u64>,
+    #[serde(rename = "dependencyPaths")]
+    pub dependency_paths: Option<Vec<String>>,
+    #[serde(rename = "dependencyFilePolicy")]
+    pub dependency_file_policy: Option<DependencyFilePolicy>,
+    #[serde(rename = "excludedDirectories")]
+    pub excluded_directories: Option<Vec<String>>,
+    #[serde(rename = "maxHeartbeatsPerMinute")]
+    pub max_heartbeats_per_minute: Option<u32>,
+    #[serde(rename = "editorName")]
+    pub editor_name: Option<String>,
+    #[serde(rename = "metricsPort")]
+    pub metrics_port: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PathMapping {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProjectNameFormat {
+    #[default]
+    #[serde(rename = "repo")]
+    Repo,
+    #[serde(rename = "org/repo")]
+    OrgRepo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DetachedHeadFormat {
+    #[default]
+    Sha,
+    Tag,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NestedRepositoryPolicy {
+    #[default]
+    Innermost,
+    Outermost,
+}
+
+// What to do with files in package caches and toolchain sources: count them
+// as reading time for the project the user came from, or ignore them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyFilePolicy {
+    #[default]
+    Attribute,
+    Skip,
+}
+
+// Whether to share an active-editor lease with other Ziit plugins so only
+// the editor in use reports. `override` always reports and takes the lease.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorCoordination {
+    #[default]
+    Off,
+    Lease,
+    Override,
+}
+
+// What to do with heartbeats for generated or vendored files.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratedFilePolicy {
+    #[default]
+    Tag,
+    Skip,
+}
+
+// Which heartbeat shape to send. `auto` asks the server and falls back to v1,
+// so older instances never see fields they do not know.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeartbeatSchema {
+    #[default]
+    Auto,
+    V1,
+    V2,
+}
+
+pub(crate) fn get_config_dir() -> Result<PathBuf> {
+    if let Ok(xdg_config_home) = std::env::var("XDG_CONFIG_HOME") {
+        if !xdg_config_home.is_empty() {
+            return Ok(PathBuf::from(xdg_config_home).join("ziit"));
+        }
+    }
+
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home_dir.join(".config").join("ziit"))
+}
+
+fn get_config_path() -> Result<PathBuf> {
+    let config_dir = get_config_dir()?;
+    Ok(config_dir.join(CONFIG_FILE_NAME))
+}
+
+fn get_legacy_config_paths() -> Result<Vec<PathBuf>> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(LEGACY_CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| home_dir.join(name))
+        .collect())
+}
+
+async fn ensure_config_dir() -> Result<()> {
+    fs::create_dir_all(get_config_dir()?).await?;
+    Ok(())
+}
+
+async fn exists(path: &Path) -> bool {
+    fs::try_exists(path).await.unwrap_or(false)
+}
+
+async fn migrate_legacy_config() -> Result<()> {
+    let config_path = get_config_path()?;
+
+    if exists(&config_path).await {
+        log::debug!("New config file already exists, skipping migration");
+        return Ok(());
+    }
+
+    let legacy_config_paths = get_legacy_config_paths()?;
+    for legacy_config_path in legacy_config_paths {
+        if !exists(&legacy_config_path).await {
+            continue;
+        }
+
+        log::info!(
+            "Migrating legacy config from {:?} to {:?}",
+            legacy_config_path,
+            config_path
+        );
+
+        match fs::read_to_string(&legacy_config_path).await {
+            Ok(content) => {
+                let legacy_config: ZiitConfig = serde_json::from_str(&content)?;
+
+                ensure_config_dir().await?;
+
+                let new_content = serde_json::to_string_pretty(&legacy_config)?;
+                fs::write(&config_path, new_content).await?;
+
+                if let Err(e) = fs::remove_file(&legacy_config_path).await {
+                    log::warn!("Could not remove legacy config file: {}", e);
+                } else {
+                    log::info!("Successfully migrated config and removed legacy file");
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!(
+                    "Could not read legacy config file {:?}: {}",
+                    legacy_config_path,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn register_secrets(config: &ZiitConfig) {
+    let mirror_keys = config
+        .additional_servers
+        .iter()
+        .flatten()
+        .map(|target| target.api_key.as_str());
+    for secret in config.api_key.as_deref().into_iter().chain(mirror_keys) {
+        crate::redact::register_secret(secret);
+    }
+}
+
+pub async fn read_config_file() -> Result<ZiitConfig> {
+    if let Err(e) = migrate_legacy_config().await {
+        log::warn!("Migration failed: {}", e);
+    }
+
+    let config_path = get_config_path()?;
+    log::debug!("Reading config from: {:?}", config_path);
+
+    match fs::read_to_string(&config_path).await {
+        Ok(content) => {
+            log::debug!(
+                "Successfully read config file, content length: {}",
+                content.len()
+            );
+            if crate::redact::log_payloads() {
+                log::debug!("Config file content: {}", crate::redact::redact(&content));
+            }
+            match serde_json::from_str::<ZiitConfig>(&content) {
+                Ok(config) => {
+                    register_secrets(&config);
+                    log::debug!(
+                        "Successfully parsed config. Has API key: {}",
+                        config.api_key.is_some()
+                    );
+                    log::debug!("Base URL: {:?}", config.base_url);
+                    Ok(config)
+                }
+                Err(e) => {
+                    log::error!("Failed to parse config JSON: {}", e);
+                    crate::telemetry::record_config_error(&format!(
+                        "Failed to parse config JSON: {}",
+                        e
+                    ));
+                    Err(anyhow::Error::from(e))
+                }
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            log::debug!("Config file does not exist at: {:?}", config_path);
+            ensure_config_dir().await?;
+            Ok(ZiitConfig::default())
+        }
+        Err(e) => {
+            log::error!("Error reading config file: {}", e);
+            Err(anyhow::Error::from(e))
+        }
+    }
+}
+
+pub async fn write_config_file(config: &ZiitConfig) -> Result<()> {
+    register_secrets(config);
+    let config_path = get_config_path()?;
+    ensure_config_dir().await?;
+
+    let content = serde_json::to_string_pretty(config)?;
+    fs::write(config_path, content).await?;
+    log::info!("Config file updated: {}", CONFIG_FILE_NAME);
+    Ok(())
+}
+
+pub async fn get_api_key() -> Result<Option<String>> {
+    log::debug!("get_api_key() called");
+    let config = read_config_file().await?;
+    log::debug!(
+        "get_api_key() returning: {}",
+        if config.api_key.is_some() {
+            "Some(***)"
+        } else {
+            "None"
+        }
+    );
+    Ok(config.api_key)
+}
+
+pub async fn get_base_url() -> Result<String> {
+    log::debug!("get_base_url() called");
+    let config = read_config_file().await?;
+    let url = config
+        .base_url
+        .unwrap_or_else(|| "https://ziit.app".to_string());
+    log::debug!("get_base_url() returning: {}", url);
+    Ok(url)
+}
+
+// Applies the settings an editor passes as initialization options or
+// workspace configuration and returns the names of the ones that changed.
+pub fn apply_client_settings(
+    config: &mut ZiitConfig,
+    settings: &serde_json::Value,
+) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+
+    if let Some(api_key) = settings.get("apiKey").and_then(serde_json::Value::as_str) {
+        if config.api_key.as_deref() != Some(api_key) {
+            config.api_key = Some(api_key.to_string());
+            changed.push("API key");
+        }
+    }
+    if let Some(base_url) = settings.get("baseUrl").and_then(serde_json::Value::as_str) {
+        if config.base_url.as_deref() != Some(base_url) {
+            config.base_url = Some(base_url.to_string());
+            changed.push("Base URL");
+        }
+    }
+    if let Some(interval) = settings.get("interval").and_then(serde_json::Value::as_u64) {
+        if config.heartbeat_interval_seconds != Some(interval) {
+            config.heartbeat_interval_seconds = Some(interval);
+            changed.push("Heartbeat interval");
+        }
+    }
+    if let Some(excludes) = settings
+        .get("excludes")
+        .and_then(serde_json::Value::as_array)
+    {
+        let excludes: Vec<String> = excludes
+            .iter()
+            .filter_map(|exclude| exclude.as_str().map(str::to_string))
+            .collect();
+        if config.excluded_directories.as_ref() != Some(&excludes) {
+            config.excluded_directories = Some(excludes);
+            changed.push("Excluded directories");
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_client_settings() {
+        let mut config = ZiitConfig {
+            base_url: Some("https://ziit.app".to_string()),
+            ..Default::default()
+        };
+        let settings = serde_json::json!({
+            "baseUrl": "https://ziit.app",
+            "interval": 60,
+            "excludes": ["target", 42],
+        });
+
+        assert_eq!(
+            apply_client_settings(&mut config, &settings),
+            ["Heartbeat interval", "Excluded directories"]
+        );
+        assert_eq!(config.heartbeat_interval_seconds, Some(60));
+        assert_eq!(
+            config.excluded_directories,
+            Some(vec!["target".to_string()])
+        );
+        assert!(apply_client_settings(&mut config, &settings).is_empty());
+    }
+}
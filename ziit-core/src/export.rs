@@ -0,0 +1,201 @@
+use crate::heartbeat::{EntityType, Heartbeat, HeartbeatCategory};
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use serde::Serialize;
+use std::path::PathBuf;
+
+const CSV_COLUMNS: &[&str] = &[
+    "entity",
+    "type",
+    "category",
+    "time",
+    "project",
+    "branch",
+    "language",
+    "is_write",
+    "lines",
+    "lineno",
+    "cursorpos",
+    "user_agent",
+    "machine",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(anyhow!("Unknown export format {:?}, use json or csv", name)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+// A heartbeat in the shape WakaTime uses for its API and data exports.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WakaTimeHeartbeat {
+    pub entity: String,
+    #[serde(rename = "type")]
+    pub entity_type: &'static str,
+    pub category: &'static str,
+    pub time: f64,
+    pub project: Option<String>,
+    pub branch: Option<String>,
+    pub language: Option<String>,
+    pub is_write: bool,
+    pub lines: Option<usize>,
+    pub lineno: Option<u32>,
+    pub cursorpos: Option<u32>,
+    pub user_agent: Option<String>,
+    pub machine: Option<String>,
+}
+
+// None for heartbeats whose timestamp cannot be read.
+pub fn to_wakatime(heartbeat: &Heartbeat) -> Option<WakaTimeHeartbeat> {
+    let time = DateTime::parse_from_rfc3339(&heartbeat.timestamp).ok()?;
+    let terminal = heartbeat.entity_type == Some(EntityType::Terminal);
+    let category = match heartbeat.category {
+        Some(HeartbeatCategory::Debugging) => "debugging",
+        None if terminal => "building",
+        None => "coding",
+    };
+
+    Some(WakaTimeHeartbeat {
+        entity: heartbeat
+            .file
+            .clone()
+            .or_else(|| heartbeat.project.clone())
+            .unwrap_or_else(|| heartbeat.editor.clone()),
+        entity_type: if terminal { "app" } else { "file" },
+        category,
+        time: time.timestamp_millis() as f64 / 1000.0,
+        project: heartbeat.project.clone(),
+        branch: heartbeat.branch.clone(),
+        language: heartbeat.language.clone(),
+        is_write: heartbeat.is_write.unwrap_or(false),
+        lines: heartbeat.lines,
+        lineno: heartbeat.line_number,
+        cursorpos: heartbeat.cursor_position,
+        user_agent: heartbeat.user_agent.clone(),
+        machine: heartbeat.machine.clone(),
+    })
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(heartbeats: &[WakaTimeHeartbeat]) -> String {
+    fn optional<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map(ToString::to_string).unwrap_or_default()
+    }
+
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push('\n');
+    for heartbeat in heartbeats {
+        let row = [
+            heartbeat.entity.clone(),
+            heartbeat.entity_type.to_string(),
+            heartbeat.category.to_string(),
+            format!("{:.3}", heartbeat.time),
+            optional(&heartbeat.project),
+            optional(&heartbeat.branch),
+            optional(&heartbeat.language),
+            heartbeat.is_write.to_string(),
+            optional(&heartbeat.lines),
+            optional(&heartbeat.lineno),
+            optional(&heartbeat.cursorpos),
+            optional(&heartbeat.user_agent),
+            optional(&heartbeat.machine),
+        ];
+        let row: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn default_export_path(format: ExportFormat) -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+    Ok(home_dir.join(format!(
+        "ziit-export-{}.{}",
+        chrono::Local::now().format("%Y-%m-%d"),
+        format.extension()
+    )))
+}
+
+pub fn export(heartbeats: &[Heartbeat], format: ExportFormat) -> Result<String> {
+    let converted: Vec<_> = heartbeats.iter().filter_map(to_wakatime).collect();
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&converted)?),
+        ExportFormat::Csv => Ok(to_csv(&converted)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(file: Option<&str>) -> Heartbeat {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": "2024-01-01T10:00:00Z",
+            "project": "ziit",
+            "language": "Rust",
+            "file": file,
+            "branch": "main",
+            "editor": "Zed",
+            "os": "linux",
+            "isWrite": true,
+            "lineNumber": 12,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_to_wakatime() {
+        let converted = to_wakatime(&heartbeat(Some("main.rs"))).unwrap();
+
+        assert_eq!(converted.entity, "main.rs");
+        assert_eq!(converted.entity_type, "file");
+        assert_eq!(converted.category, "coding");
+        assert_eq!(converted.time, 1704103200.0);
+        assert!(converted.is_write);
+        assert_eq!(converted.lineno, Some(12));
+
+        let mut terminal = heartbeat(None);
+        terminal.entity_type = Some(EntityType::Terminal);
+        let converted = to_wakatime(&terminal).unwrap();
+        assert_eq!(converted.entity, "ziit");
+        assert_eq!(converted.entity_type, "app");
+        assert_eq!(converted.category, "building");
+    }
+
+    #[test]
+    fn test_csv_export_quotes_fields() {
+        let csv = export(&[heartbeat(Some("a,\"b\".rs"))], ExportFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "\"a,\"\"b\"\".rs\",file,coding,1704103200.000,ziit,main,Rust,true,,12,,,"
+        );
+        assert_eq!(lines.next(), None);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::project_map::glob_match;
+use std::path::{Component, Path};
+use tokio::io::AsyncReadExt;
+
+const GENERATED_MARKER_READ_LIMIT: usize = 1024;
+// Same window git uses to decide whether a file is binary.
+const BINARY_SAMPLE_SIZE: usize = 8000;
+pub const DEFAULT_MAX_FILE_SIZE_BYTES: u64 = 50 * 1024 * 1024;
+
+const GENERATED_FILE_PATTERNS: &[&str] = &[
+    "*_pb.rs",
+    "*.pb.go",
+    "*.pb.cc",
+    "*.pb.h",
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    "*_pb.js",
+    "*_pb.d.ts",
+    "*.g.dart",
+    "*.freezed.dart",
+    "*.generated.*",
+    "*_generated.*",
+    "*.designer.cs",
+    "*.min.js",
+    "*.min.css",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "poetry.lock",
+    "go.sum",
+];
+
+const GENERATED_DIRECTORIES: &[&str] = &["dist", "__generated__", "generated"];
+
+const VENDORED_DIRECTORIES: &[&str] = &[
+    "vendor",
+    "vendors",
+    "node_modules",
+    "bower_components",
+    "third_party",
+    "third-party",
+    "site-packages",
+    "Pods",
+    "Carthage",
+];
+
+// Build output and installed packages, skipped unless `excludedDirectories`
+// replaces the list.
+pub const DEFAULT_EXCLUDED_DIRECTORIES: &[&str] =
+    &["node_modules", "target", "dist", ".venv", "build"];
+
+// Package caches and toolchain sources editors jump into through
+// go-to-definition. Matched against the whole path.
+const DEPENDENCY_PATH_PATTERNS: &[&str] = &[
+    "*/.cargo/registry/*",
+    "*/.cargo/git/checkouts/*",
+    "*/.rustup/toolchains/*",
+    "*/lib/rustlib/src/*",
+    "*/node_modules/*",
+    "*/go/pkg/mod/*",
+    "*/site-packages/*",
+    "*/dist-packages/*",
+    "*/.m2/repository/*",
+    "*/.gradle/caches/*",
+    "*/.pub-cache/*",
+    "*/.nuget/packages/*",
+];
+
+// Markers tools write into the header of files they generate.
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "DO NOT EDIT",
+    "<auto-generated",
+    "Code generated by",
+    "This file is automatically generated",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileClassification {
+    pub generated: bool,
+    pub vendored: bool,
+    pub binary: bool,
+    pub size: Option<u64>,
+}
+
+impl FileClassification {
+    pub fn is_generated_or_vendored(&self) -> bool {
+        self.generated || self.vendored
+    }
+}
+
+fn has_directory(path: &Path, names: &[&str]) -> bool {
+    let parent = match path.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    parent.components().any(|component| match component {
+        Component::Normal(name) => name.to_str().is_some_and(|name| names.contains(&name)),
+        _ => false,
+    })
+}
+
+pub fn classify_path(file_path: &str) -> FileClassification {
+    let path = Path::new(file_path);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+
+    FileClassification {
+        generated: GENERATED_FILE_PATTERNS
+            .iter()
+            .any(|pattern| glob_match(pattern, file_name))
+            || has_directory(path, GENERATED_DIRECTORIES),
+        vendored: has_directory(path, VENDORED_DIRECTORIES),
+        ..FileClassification::default()
+    }
+}
+
+// `path` should be relative to the workspace, so a checkout that happens to
+// live under a directory called `build` is not excluded as a whole.
+pub fn is_excluded<S: AsRef<str>>(path: &Path, directories: &[S]) -> bool {
+    let directories: Vec<&str> = directories
+        .iter()
+        .map(|directory| directory.as_ref().trim_matches(['/', '\\']))
+        .collect();
+    has_directory(path, &directories)
+}
+
+// Built-in patterns plus the `dependencyPaths` from the config.
+pub fn is_dependency_path(file_path: &str, extra_patterns: &[String]) -> bool {
+    let path = file_path.replace('\\', "/");
+    DEPENDENCY_PATH_PATTERNS
+        .iter()
+        .copied()
+        .chain(extra_patterns.iter().map(String::as_str))
+        .any(|pattern| glob_match(pattern, &path))
+}
+
+pub fn has_generated_marker(head: &str) -> bool {
+    GENERATED_MARKERS.iter().any(|marker| head.contains(marker))
+}
+
+pub fn is_binary_content(sample: &[u8]) -> bool {
+    sample.contains(&0)
+}
+
+// Path rules first, then a single bounded read of the header for binary
+// content and generated markers.
+pub async fn classify_file(file_path: &str) -> FileClassification {
+    let mut classification = classify_path(file_path);
+
+    let mut file = match tokio::fs::File::open(file_path).await {
+        Ok(file) => file,
+        Err(_) => return classification,
+    };
+    classification.size = file.metadata().await.ok().map(|metadata| metadata.len());
+
+    let mut buffer = vec![0; BINARY_SAMPLE_SIZE];
+    if let Ok(read) = file.read(&mut buffer).await {
+        let sample = &buffer[..read];
+        classification.binary = is_binary_content(sample);
+        if !classification.binary && !classification.generated {
+            let head = &sample[..read.min(GENERATED_MARKER_READ_LIMIT)];
+            classification.generated = has_generated_marker(&String::from_utf8_lossy(head));
+        }
+    }
+    classification
+}
+
+// Binary blobs and huge files (logs, dumps) never produce heartbeats; a
+// limit of 0 disables the size check.
+pub fn should_skip_file(
+    classification: &FileClassification,
+    max_file_size_bytes: u64,
+    skip_binary_files: bool,
+) -> bool {
+    if skip_binary_files && classification.binary {
+        return true;
+    }
+    max_file_size_bytes > 0
+        && classification
+            .size
+            .is_some_and(|size| size > max_file_size_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_path() {
+        assert!(classify_path("/repo/src/api_pb.rs").generated);
+        assert!(classify_path("/repo/web/dist/app.js").generated);
+        assert!(classify_path("/repo/lib/model.g.dart").generated);
+        assert!(classify_path("/repo/vendor/github.com/x/y.go").vendored);
+        assert!(classify_path("/repo/node_modules/react/index.js").vendored);
+        assert_eq!(
+            classify_path("/repo/src/main.rs"),
+            FileClassification::default()
+        );
+        assert!(!classify_path("/repo/src/vendor.rs").vendored);
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let defaults = DEFAULT_EXCLUDED_DIRECTORIES;
+        assert!(is_excluded(Path::new("target/debug/build.rs"), defaults));
+        assert!(is_excluded(
+            Path::new("web/node_modules/x/index.js"),
+            defaults
+        ));
+        assert!(!is_excluded(Path::new("src/build.rs"), defaults));
+        assert!(is_excluded(Path::new("out/app.js"), &["out/".to_string()]));
+        assert!(!is_excluded::<&str>(
+            Path::new("target/debug/build.rs"),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn test_is_dependency_path() {
+        let extra = ["*/third_party/sdk/*".to_string()];
+        assert!(is_dependency_path(
+            "/home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.228/src/lib.rs",
+            &[]
+        ));
+        assert!(is_dependency_path(
+            "C:\\Users\\me\\.rustup\\toolchains\\stable\\lib\\rustlib\\src\\rust\\library\\std\\src\\lib.rs",
+            &[]
+        ));
+        assert!(is_dependency_path("/repo/third_party/sdk/api.h", &extra));
+        assert!(!is_dependency_path("/repo/src/main.rs", &extra));
+    }
+
+    #[test]
+    fn test_should_skip_file() {
+        let text = FileClassification {
+            size: Some(1024),
+            ..FileClassification::default()
+        };
+        let binary = FileClassification {
+            binary: true,
+            size: Some(1024),
+            ..FileClassification::default()
+        };
+        let huge = FileClassification {
+            size: Some(200 * 1024 * 1024),
+            ..FileClassification::default()
+        };
+
+        assert!(!should_skip_file(&text, DEFAULT_MAX_FILE_SIZE_BYTES, true));
+        assert!(should_skip_file(&binary, DEFAULT_MAX_FILE_SIZE_BYTES, true));
+        assert!(!should_skip_file(
+            &binary,
+            DEFAULT_MAX_FILE_SIZE_BYTES,
+            false
+        ));
+        assert!(should_skip_file(&huge, DEFAULT_MAX_FILE_SIZE_BYTES, true));
+        assert!(!should_skip_file(&huge, 0, true));
+    }
+
+    #[tokio::test]
+    async fn test_classify_file_detects_binary() {
+        let dir = std::env::temp_dir().join(format!("ziit-classify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let blob = dir.join("image.dat");
+        std::fs::write(&blob, [0x89, b'P', b'N', b'G', 0, 0, 0, 13]).unwrap();
+        let source = dir.join("main.rs");
+        std::fs::write(&source, "// @generated\nfn main() {}\n").unwrap();
+
+        let classification = classify_file(blob.to_str().unwrap()).await;
+        assert!(classification.binary);
+        assert_eq!(classification.size, Some(8));
+        let classification = classify_file(source.to_str().unwrap()).await;
+        assert!(!classification.binary);
+        assert!(classification.generated);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_has_generated_marker() {
+        assert!(has_generated_marker("// @generated by protoc\n"));
+        assert!(has_generated_marker(
+            "// Code generated by mockgen. DO NOT EDIT.\n"
+        ));
+        assert!(!has_generated_marker("fn main() {}\n"));
+    }
+}
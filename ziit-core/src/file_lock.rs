@@ -0,0 +1,51 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+// An advisory lock shared by every ziit-ls process, released when dropped.
+// It only guards against other Ziit processes, nothing else honours it.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    // Waits for whoever holds the lock, off the async runtime.
+    pub async fn acquire(path: PathBuf) -> io::Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)?;
+            file.lock()?;
+            Ok(Self { _file: file })
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive_until_dropped() {
+        let path = std::env::temp_dir().join(format!("ziit-lock-{}.lock", std::process::id()));
+        let first = FileLock::acquire(path.clone()).await.unwrap();
+
+        let waiting = tokio::spawn(FileLock::acquire(path.clone()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(5), waiting).await;
+        assert!(second.is_ok_and(|joined| joined.is_ok_and(|lock| lock.is_ok())));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,2383 @@
+use crate::api::{
+    fetch_daily_summary_request, fetch_heartbeat_schema_request, send_batch_heartbeats_request,
+    send_heartbeat_request, DailySummaryResponse,
+};
+use crate::clock::{HeartbeatClock, Stamp};
+use crate::config::{
+    get_api_key, get_base_url, read_config_file, DependencyFilePolicy, EditorCoordination,
+    GeneratedFilePolicy, HeartbeatSchema, NestedRepositoryPolicy, ZiitConfig,
+};
+use crate::file_classification::{
+    classify_file, is_dependency_path, is_excluded, should_skip_file, DEFAULT_EXCLUDED_DIRECTORIES,
+    DEFAULT_MAX_FILE_SIZE_BYTES,
+};
+use crate::file_lock::FileLock;
+use crate::idle;
+use crate::language::{extract_file_name, resolve_language, LanguageCategory, LanguageSettings};
+use crate::lease::EditorLease;
+use crate::metrics;
+use crate::mirror::{MirrorStatus, MirrorTarget, Mirrors};
+use crate::project::{
+    apply_path_mappings, normalize_windows_path, read_git_head, DetectionSettings, ProjectDetector,
+    ProjectInfo,
+};
+use crate::recap::{self, RecapSchedule};
+use crate::redact;
+use crate::spill::SpillStore;
+use crate::stats::{self, LocalStats};
+use crate::telemetry::{self, ErrorKind};
+use crate::throttle::{RateLimiter, Throttle};
+use crate::timezone;
+use crate::vcs::set_git_path;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio::time::{interval, Duration};
+
+const HEARTBEAT_INTERVAL_SECONDS: u64 = 120;
+const MIN_HEARTBEAT_INTERVAL_SECONDS: u64 = 30;
+const OFFLINE_SYNC_INTERVAL_SECONDS: u64 = 30;
+const BRANCH_WATCH_INTERVAL_SECONDS: u64 = 10;
+const DAILY_SUMMARY_INTERVAL_SECONDS: u64 = 15 * 60;
+const OFFLINE_QUEUE_FILE_NAME: &str = "offline_heartbeats.jsonl";
+const OFFLINE_QUEUE_LOCK_FILE_NAME: &str = "offline_heartbeats.lock";
+// Earlier versions rewrote the whole queue as one pretty-printed JSON array.
+const LEGACY_OFFLINE_QUEUE_FILE_NAME: &str = "offline_heartbeats.json";
+const OFFLINE_SEGMENTS_DIR_NAME: &str = "offline_segments";
+const DRY_RUN_FILE_NAME: &str = "dry_run_heartbeats.jsonl";
+const LAST_RECAP_FILE_NAME: &str = "last_daily_recap";
+const MIRRORS_DIR_NAME: &str = "mirrors";
+const EDITOR_LEASE_FILE_NAME: &str = "active_editor.json";
+const RECAP_CHECK_INTERVAL_SECONDS: u64 = 60;
+// Every heartbeat is also kept locally so stats can be computed offline.
+const HISTORY_FILE_NAME: &str = "heartbeat_history.jsonl";
+const HISTORY_RETENTION_DAYS: u32 = 90;
+const HISTORY_TRIM_INTERVAL_SECONDS: u64 = 24 * 60 * 60;
+// Above this many queued heartbeats the oldest OFFLINE_SPILL_BATCH are moved
+// into a spill segment, so memory and the size of the JSONL queue file stay
+// bounded.
+const OFFLINE_MEMORY_LIMIT: usize = 1000;
+const OFFLINE_SPILL_BATCH: usize = 500;
+const STATUS_FILE_ENV_VAR: &str = "ZIIT_STATUS_FILE";
+const ACTIVITY_CHANNEL_CAPACITY: usize = 256;
+const ACTIVITY_DEBOUNCE_SECONDS: u64 = 120;
+// Roughly one editor event per second of continuous typing.
+const FULL_INTENSITY_EVENTS_PER_MINUTE: u64 = 60;
+// Restoring a workspace reopens and refocuses files the user is not working
+// on, so only edits count for this long after startup.
+const STARTUP_WARMUP_SECONDS: u32 = 10;
+const MAX_HEARTBEATS_PER_MINUTE: u32 = 30;
+const DEFAULT_EDITOR_NAME: &str = "Zed";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Heartbeat {
+    pub timestamp: String,
+    pub project: Option<String>,
+    pub language: Option<String>,
+    pub file: Option<String>,
+    pub branch: Option<String>,
+    pub editor: String,
+    pub os: String,
+    #[serde(
+        rename = "repositoryHost",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub repository_host: Option<String>,
+    #[serde(rename = "isDirty", default, skip_serializing_if = "Option::is_none")]
+    pub is_dirty: Option<bool>,
+    #[serde(
+        rename = "changedFiles",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub changed_files: Option<usize>,
+    #[serde(
+        rename = "languageCategory",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub language_category: Option<LanguageCategory>,
+    #[serde(
+        rename = "isGenerated",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub is_generated: Option<bool>,
+    #[serde(
+        rename = "isVendored",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub is_vendored: Option<bool>,
+    #[serde(
+        rename = "isDependency",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub is_dependency: Option<bool>,
+    #[serde(
+        rename = "embeddedLanguage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub embedded_language: Option<String>,
+    #[serde(
+        rename = "clockJumpSeconds",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub clock_jump_seconds: Option<i64>,
+    #[serde(
+        rename = "entityType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entity_type: Option<EntityType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<HeartbeatCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    // Project detection timed out and the project was derived from the path.
+    #[serde(
+        rename = "projectFallback",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub project_fallback: Option<bool>,
+    // Schema v2, only sent to servers that support it.
+    #[serde(rename = "isWrite", default, skip_serializing_if = "Option::is_none")]
+    pub is_write: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<usize>,
+    #[serde(
+        rename = "lineNumber",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub line_number: Option<u32>,
+    #[serde(
+        rename = "cursorPosition",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cursor_position: Option<u32>,
+    #[serde(rename = "userAgent", default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine: Option<String>,
+    // Editor event density since the previous heartbeat, 0 to 100.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intensity: Option<u8>,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(
+        project: Option<String>,
+        language: Option<String>,
+        file: Option<String>,
+        branch: Option<String>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            project,
+            language,
+            file,
+            branch,
+            editor: DEFAULT_EDITOR_NAME.to_string(),
+            os: std::env::consts::OS.to_string(),
+            repository_host: None,
+            is_dirty: None,
+            changed_files: None,
+            language_category: None,
+            is_generated: None,
+            is_vendored: None,
+            is_dependency: None,
+            embedded_language: None,
+            clock_jump_seconds: None,
+            entity_type: None,
+            category: None,
+            tags: None,
+            project_fallback: None,
+            is_write: None,
+            lines: None,
+            line_number: None,
+            cursor_position: None,
+            user_agent: Some(user_agent()),
+            machine: machine_name(),
+            intensity: None,
+        }
+    }
+
+    pub(crate) fn into_schema(mut self, schema: HeartbeatSchema) -> Self {
+        if schema != HeartbeatSchema::V2 {
+            self.is_write = None;
+            self.lines = None;
+            self.line_number = None;
+            self.cursor_position = None;
+            self.user_agent = None;
+            self.machine = None;
+            self.intensity = None;
+        }
+        self
+    }
+
+    fn set_stamp(&mut self, stamp: Stamp) {
+        self.timestamp = stamp.at.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        self.clock_jump_seconds = stamp.clock_jump_seconds;
+    }
+}
+
+// Written to $ZIIT_STATUS_FILE so the Zed extension can show the current
+// status without talking to the language server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatusSnapshot {
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[serde(rename = "todaySeconds")]
+    pub today_seconds: Option<u64>,
+    pub online: bool,
+    #[serde(rename = "apiKeyValid")]
+    pub api_key_valid: bool,
+    #[serde(rename = "queuedHeartbeats")]
+    pub queued_heartbeats: usize,
+    #[serde(
+        rename = "dashboardUrl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub dashboard_url: Option<String>,
+}
+
+// What is waiting in the offline queue on disk, read without loading or
+// repairing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueHealth {
+    pub queued: usize,
+    pub spilled: usize,
+    pub unreadable: usize,
+}
+
+// Whether heartbeats are currently reaching the server. Published on every
+// change so front ends can tell users their time is being queued.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncState {
+    pub online: bool,
+    #[serde(rename = "apiKeyValid")]
+    pub api_key_valid: bool,
+}
+
+// What the user was working in. Terminal activity carries the terminal's
+// working directory instead of a file, so build and debug time still counts
+// towards the right project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityType {
+    #[default]
+    File,
+    Terminal,
+}
+
+// Set on heartbeats sent while a debug session is active for their project.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeartbeatCategory {
+    Debugging,
+}
+
+// Zero-based, as reported by the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+// Editor activity as reported by the LSP handlers. Events are handed to a
+// single aggregator task which debounces, enriches and sends them in order.
+#[derive(Debug, Clone)]
+pub struct ActivityEvent {
+    pub file_path: Option<String>,
+    pub language_id: Option<String>,
+    pub force_send: bool,
+    pub entity_type: EntityType,
+    // Edits and saves count as writing, everything else as reading.
+    pub is_write: bool,
+    pub cursor: Option<CursorPosition>,
+}
+
+// Everything the manager mutates lives behind one lock. It is a std mutex on
+// purpose: the guard cannot be held across an await, so every transition is a
+// short synchronous step and there is no lock ordering to get wrong.
+#[derive(Debug)]
+struct State {
+    heartbeat_throttle: Throttle,
+    last_entity_type: EntityType,
+    debugging_projects: HashSet<String>,
+    clock: HeartbeatClock,
+    workspace_folders: Vec<PathBuf>,
+    nested_repository_policy: NestedRepositoryPolicy,
+    offline_heartbeats: VecDeque<Heartbeat>,
+    spilled_heartbeats: usize,
+    is_online: bool,
+    has_valid_api_key: bool,
+    today_seconds: Option<u64>,
+    last_summary: Option<(DateTime<Utc>, DailySummaryResponse)>,
+    // Schema version negotiated with the server at this base URL.
+    negotiated_schema: Option<(String, HeartbeatSchema)>,
+    last_recap_day: Option<NaiveDate>,
+    pending_notices: Vec<String>,
+    activity_events: u64,
+    last_heartbeat_at: Option<DateTime<Utc>>,
+    warmup_until: Option<DateTime<Utc>>,
+    // Project of the last heartbeat outside a dependency, which dependency
+    // files are attributed to.
+    origin_project: Option<ProjectInfo>,
+    rate_limiter: RateLimiter,
+    // Offline queue lines as this process last read or wrote them, to tell
+    // what other processes added or removed since.
+    offline_on_disk: HashSet<String>,
+}
+
+// Events per minute since the previous heartbeat, as a percentage of
+// continuous typing. Time beyond one heartbeat interval is idle time and does
+// not dilute a burst of activity after a break.
+fn intensity(events: u64, elapsed_seconds: u64) -> u8 {
+    let elapsed_seconds = elapsed_seconds.clamp(60, HEARTBEAT_INTERVAL_SECONDS);
+    let percent = events * 60 * 100 / (elapsed_seconds * FULL_INTENSITY_EVENTS_PER_MINUTE);
+    percent.min(100) as u8
+}
+
+fn queue_key(heartbeat: &Heartbeat) -> Option<String> {
+    serde_json::to_string(heartbeat).ok()
+}
+
+// Brings the in-memory queue in line with the file another process may have
+// rewritten: lines this process knew about that are gone were synced or
+// dropped elsewhere, lines it never saw were queued elsewhere. Returns how
+// many were picked up.
+fn merge_offline_queue(
+    queue: &mut VecDeque<Heartbeat>,
+    known: &HashSet<String>,
+    on_disk: Vec<Heartbeat>,
+) -> usize {
+    let on_disk_keys: HashSet<String> = on_disk.iter().filter_map(queue_key).collect();
+    queue.retain(|heartbeat| {
+        queue_key(heartbeat).is_none_or(|key| !known.contains(&key) || on_disk_keys.contains(&key))
+    });
+    let before = queue.len();
+    queue.extend(
+        on_disk
+            .into_iter()
+            .filter(|heartbeat| queue_key(heartbeat).is_some_and(|key| !known.contains(&key))),
+    );
+    queue.len() - before
+}
+
+// Drops queued heartbeats from before `cutoff` and returns how many went.
+// Anything without a readable timestamp is kept, the server decides on those.
+fn retain_recent(heartbeats: &mut VecDeque<Heartbeat>, cutoff: DateTime<Utc>) -> usize {
+    let before = heartbeats.len();
+    heartbeats.retain(|heartbeat| {
+        DateTime::parse_from_rfc3339(&heartbeat.timestamp)
+            .map_or(true, |timestamp| timestamp >= cutoff)
+    });
+    before - heartbeats.len()
+}
+
+impl State {
+    // The first edit or save ends the warmup early.
+    fn warming_up(&mut self, now: DateTime<Utc>, is_write: bool) -> bool {
+        if is_write {
+            self.warmup_until = None;
+        }
+        self.warmup_until.is_some_and(|until| now < until)
+    }
+
+    fn take_intensity(&mut self, now: DateTime<Utc>) -> u8 {
+        let elapsed_seconds = self
+            .last_heartbeat_at
+            .map_or(HEARTBEAT_INTERVAL_SECONDS, |last| {
+                (now - last).num_seconds().max(0) as u64
+            });
+        let events = std::mem::take(&mut self.activity_events);
+        self.last_heartbeat_at = Some(now);
+        intensity(events, elapsed_seconds)
+    }
+
+    fn new(workspace_folders: Vec<PathBuf>) -> Self {
+        Self {
+            heartbeat_throttle: Throttle::new(HEARTBEAT_INTERVAL_SECONDS),
+            last_entity_type: EntityType::default(),
+            debugging_projects: HashSet::new(),
+            clock: HeartbeatClock::default(),
+            workspace_folders,
+            nested_repository_policy: NestedRepositoryPolicy::default(),
+            offline_heartbeats: VecDeque::new(),
+            spilled_heartbeats: 0,
+            is_online: true,
+            has_valid_api_key: true,
+            today_seconds: None,
+            last_summary: None,
+            negotiated_schema: None,
+            last_recap_day: None,
+            pending_notices: Vec::new(),
+            activity_events: 0,
+            last_heartbeat_at: None,
+            warmup_until: None,
+            origin_project: None,
+            rate_limiter: RateLimiter::default(),
+            offline_on_disk: HashSet::new(),
+        }
+    }
+
+    fn set_online(&mut self, online: bool) {
+        if self.is_online != online {
+            self.is_online = online;
+            log::info!(
+                "Online status changed to: {}",
+                if online { "online" } else { "offline" }
+            );
+        }
+    }
+
+    fn set_api_key_valid(&mut self, valid: bool) {
+        if self.has_valid_api_key != valid {
+            self.has_valid_api_key = valid;
+            log::info!(
+                "API key status changed to: {}",
+                if valid { "valid" } else { "invalid" }
+            );
+        }
+    }
+
+    fn requeue_front(&mut self, batch: Vec<Heartbeat>) {
+        for hb in batch.into_iter().rev() {
+            self.offline_heartbeats.push_front(hb);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct HeartbeatManager {
+    state: Mutex<State>,
+    activity_tx: mpsc::Sender<ActivityEvent>,
+    activity_rx: Mutex<Option<mpsc::Receiver<ActivityEvent>>>,
+    project_detector: Arc<ProjectDetector>,
+    offline_queue_path: PathBuf,
+    history_path: PathBuf,
+    // In a dry run heartbeats go through the whole pipeline but are appended
+    // here instead of being sent, so users can audit what would leave.
+    dry_run_path: PathBuf,
+    force_dry_run: bool,
+    spill_store: SpillStore,
+    status_file: Option<PathBuf>,
+    // At most one heartbeat and one offline batch are sent at a time, so their
+    // results cannot race each other on the online/API key flags.
+    heartbeat_in_flight: Semaphore,
+    batch_in_flight: Semaphore,
+    sync_state_tx: watch::Sender<SyncState>,
+    daily_recap_tx: watch::Sender<Option<String>>,
+    last_recap_path: PathBuf,
+    mirrors: Arc<Mirrors>,
+    editor_lease: EditorLease,
+    offline_queue_lock_path: PathBuf,
+    client_name: Option<String>,
+}
+
+// Unset keeps the offline queue forever.
+async fn offline_retention() -> Option<TimeDelta> {
+    read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.offline_retention_days)
+        .map(|days| TimeDelta::days(days.into()))
+}
+
+// 0 keeps the local history forever.
+async fn history_retention() -> Option<TimeDelta> {
+    let days = read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.history_retention_days)
+        .unwrap_or(HISTORY_RETENTION_DAYS);
+    (days > 0).then(|| TimeDelta::days(days.into()))
+}
+
+// Keeps the latest event per file seen during the startup warmup, in the
+// order the files were last touched.
+fn hold_back(held_back: &mut Vec<ActivityEvent>, event: ActivityEvent) {
+    held_back.retain(|held| held.file_path != event.file_path);
+    held_back.push(event);
+}
+
+// The events to handle once the warmup is over. Only a real edit or save
+// replays what was held back; if the warmup simply runs out, the restored
+// files were never worked on and their events are dropped.
+fn release_held_back(
+    held_back: &mut Vec<ActivityEvent>,
+    event: Option<ActivityEvent>,
+) -> Vec<ActivityEvent> {
+    match event {
+        Some(event) if event.is_write => {
+            hold_back(held_back, event);
+            std::mem::take(held_back)
+        }
+        event => {
+            if !held_back.is_empty() {
+                log::debug!(
+                    "Warmup over without an edit, dropping {} held back events",
+                    held_back.len()
+                );
+                held_back.clear();
+            }
+            event.into_iter().collect()
+        }
+    }
+}
+
+async fn startup_warmup() -> TimeDelta {
+    let seconds = read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.startup_warmup_seconds)
+        .unwrap_or(STARTUP_WARMUP_SECONDS);
+    TimeDelta::seconds(seconds.into())
+}
+
+async fn heartbeat_interval() -> u64 {
+    read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.heartbeat_interval_seconds)
+        .map_or(HEARTBEAT_INTERVAL_SECONDS, |seconds| {
+            seconds.max(MIN_HEARTBEAT_INTERVAL_SECONDS)
+        })
+}
+
+async fn mirror_targets() -> Vec<MirrorTarget> {
+    read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.additional_servers)
+        .unwrap_or_default()
+}
+
+// `editorName` from the config, then the name the client reported in
+// `InitializeParams`, such as "Zed Preview".
+fn editor_name(config: &ZiitConfig, client_name: Option<&str>) -> String {
+    config
+        .editor_name
+        .as_deref()
+        .into_iter()
+        .chain(client_name)
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_EDITOR_NAME)
+        .to_string()
+}
+
+fn user_agent() -> String {
+    format!(
+        "ziit-zed/{} ({}-{})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+fn machine_name() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+async fn count_lines(path: &str) -> Option<usize> {
+    let data = fs::read(path).await.ok()?;
+    let newlines = data.iter().filter(|&&byte| byte == b'\n').count();
+    Some(newlines + usize::from(data.last().is_some_and(|&byte| byte != b'\n')))
+}
+
+fn normalize_activity_path(path: String, config: &ZiitConfig) -> String {
+    let path = if cfg!(windows) {
+        normalize_windows_path(&path)
+    } else {
+        path
+    };
+    match &config.path_mappings {
+        Some(mappings) => apply_path_mappings(&path, mappings),
+        None => path,
+    }
+}
+
+fn get_config_dir() -> Result<PathBuf> {
+    if let Ok(xdg_config_home) = std::env::var("XDG_CONFIG_HOME") {
+        if !xdg_config_home.is_empty() {
+            return Ok(PathBuf::from(xdg_config_home).join("ziit"));
+        }
+    }
+
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home_dir.join(".config").join("ziit"))
+}
+
+fn get_legacy_offline_path() -> Result<PathBuf> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home_dir.join(".ziit").join(LEGACY_OFFLINE_QUEUE_FILE_NAME))
+}
+
+pub(crate) async fn append_heartbeat_line(path: &Path, heartbeat: &Heartbeat) -> Result<()> {
+    let mut line = serde_json::to_string(heartbeat)?;
+    line.push('\n');
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir).await?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+// Parses a JSONL heartbeat file, returning the heartbeats that parsed and how
+// many non-empty lines did not.
+async fn read_history_file(path: &Path) -> Result<Vec<Heartbeat>> {
+    let data = match fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let (heartbeats, unreadable) = parse_heartbeat_lines(&data);
+    if unreadable > 0 {
+        log::warn!(
+            "Skipped {} unreadable lines in the heartbeat history",
+            unreadable
+        );
+    }
+    Ok(heartbeats)
+}
+
+// For callers without a running manager, such as the CLI.
+pub async fn read_history() -> Result<Vec<Heartbeat>> {
+    read_history_file(&get_config_dir()?.join(HISTORY_FILE_NAME)).await
+}
+
+pub async fn inspect_offline_queue() -> Result<QueueHealth> {
+    let config_dir = get_config_dir()?;
+    let mut health = QueueHealth {
+        spilled: SpillStore::new(config_dir.join(OFFLINE_SEGMENTS_DIR_NAME))
+            .spilled_count()
+            .await,
+        ..QueueHealth::default()
+    };
+
+    let legacy_path = config_dir.join(LEGACY_OFFLINE_QUEUE_FILE_NAME);
+    if path_exists(&legacy_path).await {
+        let data = fs::read_to_string(&legacy_path).await?;
+        match serde_json::from_str::<Vec<Heartbeat>>(&data) {
+            Ok(legacy) => health.queued += legacy.len(),
+            Err(_) => health.unreadable += 1,
+        }
+    }
+
+    let queue_path = config_dir.join(OFFLINE_QUEUE_FILE_NAME);
+    if path_exists(&queue_path).await {
+        let (queued, unreadable) = parse_heartbeat_lines(&fs::read_to_string(&queue_path).await?);
+        health.queued += queued.len();
+        health.unreadable += unreadable;
+    }
+    Ok(health)
+}
+
+pub(crate) fn parse_heartbeat_lines(data: &str) -> (Vec<Heartbeat>, usize) {
+    let mut heartbeats = Vec::new();
+    let mut unreadable = 0;
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(heartbeat) => heartbeats.push(heartbeat),
+            Err(_) => unreadable += 1,
+        }
+    }
+    (heartbeats, unreadable)
+}
+
+// Drops history lines from before `cutoff` and returns what is left and how
+// many went. Lines that do not parse are kept as they are.
+fn trim_history_lines(data: &str, cutoff: DateTime<Utc>) -> (String, usize) {
+    let mut kept = String::new();
+    let mut trimmed = 0;
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        let expired = serde_json::from_str::<Heartbeat>(line)
+            .ok()
+            .and_then(|heartbeat| DateTime::parse_from_rfc3339(&heartbeat.timestamp).ok())
+            .is_some_and(|timestamp| timestamp < cutoff);
+        if expired {
+            trimmed += 1;
+        } else {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    (kept, trimmed)
+}
+
+pub(crate) fn to_jsonl(heartbeats: &VecDeque<Heartbeat>) -> Result<String> {
+    let mut data = String::new();
+    for heartbeat in heartbeats {
+        data.push_str(&serde_json::to_string(heartbeat)?);
+        data.push('\n');
+    }
+    Ok(data)
+}
+
+// Pulls every complete top-level object out of a damaged JSON array and keeps
+// the ones that still parse as heartbeats.
+fn recover_heartbeats(data: &str) -> Vec<Heartbeat> {
+    let mut heartbeats = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, ch) in data.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = Some(index);
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(start) = start.take() {
+                        if let Ok(heartbeat) = serde_json::from_str(&data[start..=index]) {
+                            heartbeats.push(heartbeat);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    heartbeats
+}
+
+async fn path_exists(path: &Path) -> bool {
+    fs::try_exists(path).await.unwrap_or(false)
+}
+
+async fn migrate_offline_heartbeats(new_offline_path: &PathBuf) -> Result<()> {
+    let legacy_path = get_legacy_offline_path()?;
+
+    if path_exists(&legacy_path).await && !path_exists(new_offline_path).await {
+        log::info!(
+            "Migrating offline heartbeats from {:?} to {:?}",
+            legacy_path,
+            new_offline_path
+        );
+
+        if let Some(parent_dir) = new_offline_path.parent() {
+            fs::create_dir_all(parent_dir).await?;
+        }
+
+        fs::copy(&legacy_path, new_offline_path).await?;
+
+        if let Err(e) = fs::remove_file(&legacy_path).await {
+            log::warn!("Could not remove legacy offline heartbeats file: {}", e);
+        } else {
+            log::info!("Successfully migrated offline heartbeats and removed legacy file");
+        }
+
+        if let Some(legacy_dir) = legacy_path.parent() {
+            if path_exists(legacy_dir).await {
+                if let Ok(mut entries) = fs::read_dir(legacy_dir).await {
+                    let is_empty = matches!(entries.next_entry().await, Ok(None));
+                    if is_empty {
+                        if let Err(e) = fs::remove_dir(legacy_dir).await {
+                            log::debug!("Could not remove empty legacy directory: {}", e);
+                        } else {
+                            log::info!("Removed empty legacy directory: {:?}", legacy_dir);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl HeartbeatManager {
+    pub async fn new(workspace_folders: Vec<PathBuf>) -> Result<Self> {
+        let config_dir = get_config_dir()?;
+        fs::create_dir_all(&config_dir).await?;
+        let offline_queue_path = config_dir.join(OFFLINE_QUEUE_FILE_NAME);
+
+        if let Err(e) =
+            migrate_offline_heartbeats(&config_dir.join(LEGACY_OFFLINE_QUEUE_FILE_NAME)).await
+        {
+            log::warn!("Failed to migrate offline heartbeats: {}", e);
+        }
+
+        let (activity_tx, activity_rx) = mpsc::channel(ACTIVITY_CHANNEL_CAPACITY);
+        let manager = Self {
+            state: Mutex::new(State::new(workspace_folders)),
+            activity_tx,
+            activity_rx: Mutex::new(Some(activity_rx)),
+            project_detector: Arc::new(ProjectDetector::default()),
+            offline_queue_path,
+            offline_queue_lock_path: config_dir.join(OFFLINE_QUEUE_LOCK_FILE_NAME),
+            client_name: None,
+            history_path: config_dir.join(HISTORY_FILE_NAME),
+            dry_run_path: config_dir.join(DRY_RUN_FILE_NAME),
+            daily_recap_tx: watch::Sender::new(None),
+            last_recap_path: config_dir.join(LAST_RECAP_FILE_NAME),
+            mirrors: Arc::new(Mirrors::new(config_dir.join(MIRRORS_DIR_NAME))),
+            editor_lease: EditorLease::new(
+                config_dir.join(EDITOR_LEASE_FILE_NAME),
+                DEFAULT_EDITOR_NAME,
+                std::process::id(),
+            ),
+            force_dry_run: false,
+            spill_store: SpillStore::new(config_dir.join(OFFLINE_SEGMENTS_DIR_NAME)),
+            heartbeat_in_flight: Semaphore::new(1),
+            batch_in_flight: Semaphore::new(1),
+            sync_state_tx: watch::Sender::new(SyncState {
+                online: true,
+                api_key_valid: true,
+            }),
+            status_file: std::env::var_os(STATUS_FILE_ENV_VAR)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+        };
+
+        manager.load_offline_heartbeats().await?;
+        manager.state().spilled_heartbeats = manager.spill_store.spilled_count().await;
+        manager.state().last_recap_day = fs::read_to_string(&manager.last_recap_path)
+            .await
+            .ok()
+            .and_then(|day| day.trim().parse().ok());
+        log::info!("HeartbeatManager initialized. Call start_background_tasks() explicitly.");
+        Ok(manager)
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.force_dry_run = dry_run;
+        self
+    }
+
+    pub fn with_client_name(mut self, client_name: Option<String>) -> Self {
+        self.client_name = client_name;
+        self
+    }
+
+    pub async fn is_dry_run(&self) -> bool {
+        self.force_dry_run
+            || read_config_file()
+                .await
+                .ok()
+                .and_then(|config| config.dry_run)
+                .unwrap_or(false)
+    }
+
+    pub fn dry_run_path(&self) -> &Path {
+        &self.dry_run_path
+    }
+
+    pub async fn mirror_statuses(&self) -> Vec<MirrorStatus> {
+        self.mirrors.statuses().await
+    }
+
+    pub async fn sync_mirrors(&self) {
+        if self.is_dry_run().await {
+            return;
+        }
+        let targets = mirror_targets().await;
+        self.mirrors.sync(&targets).await;
+    }
+
+    pub fn start_background_tasks(self: &Arc<Self>) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut handles = Vec::new();
+        let activity_rx = self
+            .activity_rx
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(mut activity_rx) = activity_rx {
+            let s_activity = self.clone();
+            handles.push(tokio::spawn(async move {
+                let mut debouncer = Throttle::new(ACTIVITY_DEBOUNCE_SECONDS);
+                let heartbeat_interval = heartbeat_interval().await;
+                s_activity.state().heartbeat_throttle = Throttle::new(heartbeat_interval);
+                let mut timer = interval(Duration::from_secs(heartbeat_interval));
+                // The most recent event since the last tick, debounced or not.
+                // The timer only re-emits it, so an idle editor sends nothing.
+                let mut since_last_tick: Option<ActivityEvent> = None;
+                let warmup = startup_warmup().await;
+                s_activity.state().warmup_until = Some(Utc::now() + warmup);
+                let warmup_deadline =
+                    tokio::time::Instant::now() + warmup.to_std().unwrap_or_default();
+                let mut held_back: Vec<ActivityEvent> = Vec::new();
+                loop {
+                    let events = tokio::select! {
+                        event = activity_rx.recv() => {
+                            let Some(event) = event else {
+                                break;
+                            };
+                            if s_activity.state().warming_up(Utc::now(), event.is_write) {
+                                log::debug!("Warming up after startup, holding back {:?}", event.file_path);
+                                hold_back(&mut held_back, event);
+                                continue;
+                            }
+                            release_held_back(&mut held_back, Some(event))
+                        }
+                        _ = tokio::time::sleep_until(warmup_deadline), if !held_back.is_empty() => {
+                            s_activity.state().warmup_until = None;
+                            release_held_back(&mut held_back, None)
+                        }
+                        _ = timer.tick() => {
+                            let Some(event) = since_last_tick.take() else {
+                                log::debug!("No activity since the last tick, skipping heartbeat");
+                                continue;
+                            };
+                            if idle::is_away(heartbeat_interval).await {
+                                log::debug!("User is away from the keyboard, skipping heartbeat");
+                                continue;
+                            }
+                            s_activity
+                                .handle_editor_activity(ActivityEvent {
+                                    force_send: false,
+                                    ..event
+                                })
+                                .await;
+                            continue;
+                        }
+                    };
+
+                    for event in events {
+                        s_activity.state().activity_events += 1;
+                        if event.file_path.is_some() {
+                            // Any write within the interval makes it a writing interval.
+                            let wrote = since_last_tick
+                                .as_ref()
+                                .is_some_and(|previous| previous.is_write);
+                            since_last_tick = Some(ActivityEvent {
+                                is_write: event.is_write || wrote,
+                                ..event.clone()
+                            });
+                        }
+                        if !debouncer.try_emit(Utc::now(), &event.file_path, &None, event.force_send) {
+                            log::debug!("Debounced activity for {:?}", event.file_path);
+                            continue;
+                        }
+                        // Edits need a keypress, anything else may happen while the user is away.
+                        if !event.is_write && idle::is_away(heartbeat_interval).await {
+                            log::debug!("User is away from the keyboard, skipping activity");
+                            continue;
+                        }
+                        // Typing only happens in the focused editor.
+                        if event.is_write {
+                            s_activity.claim_editor_lease().await;
+                        }
+                        s_activity.handle_editor_activity(event).await;
+                    }
+                }
+            }));
+        }
+
+        let s_branch = self.clone();
+        handles.push(tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(BRANCH_WATCH_INTERVAL_SECONDS));
+            loop {
+                timer.tick().await;
+                s_branch.check_branch_change().await;
+            }
+        }));
+
+        let s_sync = self.clone();
+        handles.push(tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(OFFLINE_SYNC_INTERVAL_SECONDS));
+            loop {
+                timer.tick().await;
+                if let Err(e) = s_sync.sync_offline_heartbeats().await {
+                    log::error!("Error syncing offline heartbeats: {}", e);
+                }
+                s_sync.sync_mirrors().await;
+            }
+        }));
+
+        let s_history = self.clone();
+        handles.push(tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(HISTORY_TRIM_INTERVAL_SECONDS));
+            loop {
+                timer.tick().await;
+                if let Err(e) = s_history.trim_history().await {
+                    log::warn!("Could not trim the local history: {}", e);
+                }
+            }
+        }));
+
+        let s_summary = self.clone();
+        handles.push(tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(DAILY_SUMMARY_INTERVAL_SECONDS));
+            loop {
+                timer.tick().await;
+                if let Err(e) = s_summary.fetch_daily_summary().await {
+                    log::error!("Error fetching daily summary: {}", e);
+                }
+            }
+        }));
+
+        let s_recap = self.clone();
+        handles.push(tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(RECAP_CHECK_INTERVAL_SECONDS));
+            loop {
+                timer.tick().await;
+                s_recap.maybe_send_daily_recap(false).await;
+            }
+        }));
+        log::info!("HeartbeatManager background tasks started.");
+        handles
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn load_offline_heartbeats(&self) -> Result<()> {
+        let legacy_path = self
+            .offline_queue_path
+            .with_file_name(LEGACY_OFFLINE_QUEUE_FILE_NAME);
+        let mut heartbeats = VecDeque::new();
+        let mut needs_compaction = false;
+        let mut on_disk = HashSet::new();
+        let lock = self.lock_offline_queue().await;
+
+        if path_exists(&legacy_path).await {
+            match fs::read_to_string(&legacy_path).await {
+                Ok(data) => match serde_json::from_str::<VecDeque<Heartbeat>>(&data) {
+                    Ok(legacy) => {
+                        log::info!(
+                            "Migrating {} offline heartbeats from {:?}",
+                            legacy.len(),
+                            legacy_path
+                        );
+                        heartbeats.extend(legacy);
+                        fs::remove_file(&legacy_path).await.ok();
+                    }
+                    Err(e) => {
+                        log::error!("Error parsing offline heartbeats file: {}", e);
+                        let recovered = recover_heartbeats(&data);
+                        self.quarantine_offline_queue(&legacy_path, recovered.len())
+                            .await;
+                        heartbeats.extend(recovered);
+                    }
+                },
+                Err(e) => log::error!("Error reading offline heartbeats file: {}", e),
+            }
+            needs_compaction = true;
+        }
+
+        if path_exists(&self.offline_queue_path).await {
+            match fs::read_to_string(&self.offline_queue_path).await {
+                Ok(data) => {
+                    let (queued, unreadable) = parse_heartbeat_lines(&data);
+                    if unreadable > 0 {
+                        log::error!("{} unreadable lines in the offline queue", unreadable);
+                        metrics::record_dropped(unreadable);
+                        self.quarantine_offline_queue(&self.offline_queue_path, queued.len())
+                            .await;
+                        needs_compaction = true;
+                    } else {
+                        on_disk = queued.iter().filter_map(queue_key).collect();
+                    }
+                    heartbeats.extend(queued);
+                }
+                Err(e) => log::error!("Error reading offline heartbeats file: {}", e),
+            }
+        }
+
+        log::info!("Loaded {} offline heartbeats.", heartbeats.len());
+        metrics::set_queue_depth(heartbeats.len());
+        {
+            let mut state = self.state();
+            state.offline_heartbeats = heartbeats;
+            state.offline_on_disk = on_disk;
+        }
+        drop(lock);
+        if self.expire_offline_heartbeats().await > 0 {
+            needs_compaction = true;
+        }
+        if needs_compaction {
+            self.save_offline_heartbeats().await?;
+        }
+        Ok(())
+    }
+
+    async fn trim_history(&self) -> Result<()> {
+        let Some(retention) = history_retention().await else {
+            return Ok(());
+        };
+        let data = match fs::read_to_string(&self.history_path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let (kept, trimmed) = trim_history_lines(&data, Utc::now() - retention);
+        if trimmed == 0 {
+            return Ok(());
+        }
+        let partial = self.history_path.with_extension("jsonl.partial");
+        fs::write(&partial, kept).await?;
+        fs::rename(&partial, &self.history_path).await?;
+        log::info!(
+            "Dropped {} history entries older than {} days",
+            trimmed,
+            retention.num_days()
+        );
+        Ok(())
+    }
+
+    async fn expire_offline_heartbeats(&self) -> usize {
+        let Some(retention) = offline_retention().await else {
+            return 0;
+        };
+        let expired = retain_recent(&mut self.state().offline_heartbeats, Utc::now() - retention);
+        if expired > 0 {
+            log::info!(
+                "Dropped {} queued heartbeats older than {} days",
+                expired,
+                retention.num_days()
+            );
+            metrics::record_dropped(expired);
+        }
+        expired
+    }
+
+    // Keeps an unreadable queue file around for inspection instead of
+    // deleting it; the caller keeps whatever heartbeats still parsed.
+    async fn quarantine_offline_queue(&self, path: &Path, recovered_count: usize) {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("json");
+        let quarantine_path = path.with_file_name(format!(
+            "offline_heartbeats.corrupt-{}.{}",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            extension
+        ));
+        let moved = fs::rename(path, &quarantine_path).await;
+        if let Err(e) = &moved {
+            log::error!(
+                "Could not move corrupted offline heartbeats to {:?}: {}",
+                quarantine_path,
+                e
+            );
+        }
+
+        let notice = match moved {
+            Ok(()) => format!(
+                "Ziit: the offline heartbeat queue was corrupted. Recovered {} heartbeats; the \
+                 original file was kept at {}.",
+                recovered_count,
+                quarantine_path.display()
+            ),
+            Err(_) => format!(
+                "Ziit: the offline heartbeat queue at {} is corrupted. Recovered {} heartbeats.",
+                path.display(),
+                recovered_count
+            ),
+        };
+        log::warn!("{}", notice);
+        self.state().pending_notices.push(notice);
+    }
+
+    // Messages meant for the user, e.g. shown by the language server once it
+    // has a client to talk to.
+    pub fn take_notices(&self) -> Vec<String> {
+        std::mem::take(&mut self.state().pending_notices)
+    }
+
+    // Rewrites the queue file from memory. Queued heartbeats are otherwise
+    // only appended, so this is the compaction step and runs whenever
+    // heartbeats leave the queue.
+    pub async fn save_offline_heartbeats(&self) -> Result<()> {
+        let _lock = self.lock_offline_queue().await;
+        self.merge_offline_queue_file().await;
+        self.write_offline_queue().await
+    }
+
+    // Two Zed windows each run their own server on the same queue file.
+    // Without the lock the queue is still used, as it was before locking.
+    async fn lock_offline_queue(&self) -> Option<FileLock> {
+        match FileLock::acquire(self.offline_queue_lock_path.clone()).await {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                log::warn!("Could not lock the offline queue: {}", e);
+                None
+            }
+        }
+    }
+
+    // Callers hold the queue lock.
+    async fn merge_offline_queue_file(&self) {
+        let on_disk = match fs::read_to_string(&self.offline_queue_path).await {
+            Ok(data) => parse_heartbeat_lines(&data).0,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                log::warn!("Could not read the offline queue: {}", e);
+                return;
+            }
+        };
+        let mut state = self.state();
+        let State {
+            offline_heartbeats,
+            offline_on_disk,
+            ..
+        } = &mut *state;
+        let adopted = merge_offline_queue(offline_heartbeats, offline_on_disk, on_disk);
+        if adopted > 0 {
+            log::info!("Picked up {} heartbeats queued by another process", adopted);
+        }
+    }
+
+    // Callers hold the queue lock.
+    async fn write_offline_queue(&self) -> Result<()> {
+        let (data, written) = {
+            let state = self.state();
+            metrics::set_queue_depth(state.offline_heartbeats.len() + state.spilled_heartbeats);
+            let written: HashSet<String> = state
+                .offline_heartbeats
+                .iter()
+                .filter_map(queue_key)
+                .collect();
+            (to_jsonl(&state.offline_heartbeats)?, written)
+        };
+        if let Some(parent_dir) = self.offline_queue_path.parent() {
+            fs::create_dir_all(parent_dir).await?;
+        }
+        let partial = self.offline_queue_path.with_extension("jsonl.partial");
+        fs::write(&partial, data).await?;
+        fs::rename(&partial, &self.offline_queue_path).await?;
+        self.state().offline_on_disk = written;
+        Ok(())
+    }
+
+    pub async fn local_stats(&self, start: DateTime<Utc>) -> Result<LocalStats> {
+        let heartbeats = read_history_file(&self.history_path).await?;
+        Ok(stats::aggregate(&stats::since(heartbeats, start)))
+    }
+
+    pub async fn history(&self) -> Result<Vec<Heartbeat>> {
+        read_history_file(&self.history_path).await
+    }
+
+    pub async fn update_workspace_folders(&self, added: Vec<PathBuf>, removed: Vec<PathBuf>) {
+        let mut state = self.state();
+        state
+            .workspace_folders
+            .retain(|folder| !removed.contains(folder));
+        state.workspace_folders.extend(added);
+        log::info!("Workspace folders updated: {:?}", state.workspace_folders);
+    }
+
+    pub async fn status_snapshot(&self) -> StatusSnapshot {
+        let dashboard_url = crate::commands::get_dashboard_url().await.ok();
+        let state = self.state();
+        StatusSnapshot {
+            updated_at: Utc::now().to_rfc3339(),
+            today_seconds: state.today_seconds,
+            online: state.is_online,
+            api_key_valid: state.has_valid_api_key,
+            queued_heartbeats: state.offline_heartbeats.len() + state.spilled_heartbeats,
+            dashboard_url,
+        }
+    }
+
+    async fn write_status_snapshot(&self) {
+        let Some(status_file) = &self.status_file else {
+            return;
+        };
+
+        let snapshot = self.status_snapshot().await;
+        match serde_json::to_string_pretty(&snapshot) {
+            Ok(data) => {
+                if let Err(e) = fs::write(status_file, data).await {
+                    log::warn!("Could not write status file {:?}: {}", status_file, e);
+                }
+            }
+            Err(e) => log::warn!("Could not serialize status snapshot: {}", e),
+        }
+    }
+
+    fn set_online_status(&self, online: bool) {
+        self.state().set_online(online);
+        self.sync_state_tx.send_if_modified(|sync_state| {
+            let changed = sync_state.online != online;
+            sync_state.online = online;
+            changed
+        });
+    }
+
+    fn set_api_key_status(&self, valid: bool) {
+        self.state().set_api_key_valid(valid);
+        self.sync_state_tx.send_if_modified(|sync_state| {
+            let changed = sync_state.api_key_valid != valid;
+            sync_state.api_key_valid = valid;
+            changed
+        });
+    }
+
+    pub fn subscribe_sync_state(&self) -> watch::Receiver<SyncState> {
+        self.sync_state_tx.subscribe()
+    }
+
+    // Recaps are computed from the local history, so they work offline.
+    pub fn subscribe_daily_recaps(&self) -> watch::Receiver<Option<String>> {
+        self.daily_recap_tx.subscribe()
+    }
+
+    async fn maybe_send_daily_recap(&self, on_activity: bool) {
+        let config = read_config_file().await.unwrap_or_default();
+        let schedule = RecapSchedule::parse(config.daily_summary.as_deref());
+        let timezone = timezone::configured().await;
+        let now = timezone.local_time(Utc::now());
+        let Some(day) = schedule.due_day(now, on_activity) else {
+            return;
+        };
+        {
+            let mut state = self.state();
+            if state.last_recap_day.is_some_and(|last| last >= day) {
+                return;
+            }
+            state.last_recap_day = Some(day);
+        }
+        if let Err(e) = fs::write(&self.last_recap_path, day.to_string()).await {
+            log::warn!("Could not remember the last daily recap: {}", e);
+        }
+
+        let heartbeats = match read_history_file(&self.history_path).await {
+            Ok(heartbeats) => heartbeats,
+            Err(e) => {
+                log::warn!("Could not read the history for the daily recap: {}", e);
+                return;
+            }
+        };
+        let day_heartbeats = stats::between(
+            heartbeats,
+            timezone.start_of_day(day),
+            timezone.start_of_day(day + TimeDelta::days(1)),
+        );
+        if let Some(message) = recap::message(day, now.date(), &stats::aggregate(&day_heartbeats)) {
+            self.daily_recap_tx.send_replace(Some(message));
+        }
+    }
+
+    // Called when the editor gains focus, and for every edit.
+    pub async fn claim_editor_lease(&self) {
+        let coordination = read_config_file()
+            .await
+            .ok()
+            .and_then(|config| config.editor_coordination)
+            .unwrap_or_default();
+        if coordination == EditorCoordination::Off {
+            return;
+        }
+        if let Err(e) = self.editor_lease.claim(Utc::now()).await {
+            log::warn!("Could not claim the active editor lease: {}", e);
+        }
+    }
+
+    async fn another_editor_active(&self, coordination: EditorCoordination) -> bool {
+        match coordination {
+            EditorCoordination::Off => false,
+            EditorCoordination::Override => {
+                if let Err(e) = self.editor_lease.claim(Utc::now()).await {
+                    log::warn!("Could not claim the active editor lease: {}", e);
+                }
+                false
+            }
+            EditorCoordination::Lease => match self.editor_lease.held_elsewhere(Utc::now()).await {
+                Some(lease) => {
+                    log::debug!(
+                        "{} (pid {}) is the active editor, skipping heartbeat",
+                        lease.editor,
+                        lease.pid
+                    );
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    // Hands an event to the aggregator without waiting. When the queue is full
+    // the event is dropped: heartbeats are periodic, so losing one under load
+    // is cheaper than stalling the LSP handlers.
+    pub fn record_activity(&self, event: ActivityEvent) {
+        match self.activity_tx.try_send(event) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(event)) => {
+                log::warn!(
+                    "Activity queue full, dropping event for {:?}",
+                    event.file_path
+                );
+                metrics::record_activity_dropped();
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                log::error!("Activity pipeline stopped, dropping event");
+                metrics::record_activity_dropped();
+            }
+        }
+    }
+
+    #[tracing::instrument(
+        name = "activity",
+        skip_all,
+        fields(file = event.file_path.as_deref(), write = event.is_write)
+    )]
+    async fn handle_editor_activity(&self, event: ActivityEvent) {
+        let ActivityEvent {
+            file_path,
+            language_id,
+            force_send,
+            entity_type,
+            is_write,
+            cursor,
+        } = event;
+        log::debug!(
+            "handle_editor_activity called with file_path: {:?}",
+            file_path
+        );
+        log::debug!(
+            "handle_editor_activity called with language_id: {:?}",
+            language_id
+        );
+
+        if file_path.is_none() && !force_send {
+            log::debug!("Skipping heartbeat: no file path and not forced");
+            return;
+        }
+
+        let config = read_config_file().await.unwrap_or_default();
+        set_git_path(config.git_path.as_ref().map(PathBuf::from));
+
+        if self
+            .another_editor_active(config.editor_coordination.unwrap_or_default())
+            .await
+        {
+            return;
+        }
+
+        let file_path = file_path.map(|path| normalize_activity_path(path, &config));
+
+        if let (Some(path), EntityType::File) = (&file_path, entity_type) {
+            if self.is_excluded_file(path, &config) {
+                log::debug!("Skipping heartbeat for excluded file: {:?}", file_path);
+                return;
+            }
+        }
+
+        let dependency = match (&file_path, entity_type) {
+            (Some(path), EntityType::File) => {
+                is_dependency_path(path, config.dependency_paths.as_deref().unwrap_or_default())
+            }
+            _ => false,
+        };
+        let dependency_policy = config.dependency_file_policy.unwrap_or_default();
+        if dependency && dependency_policy == DependencyFilePolicy::Skip {
+            log::debug!("Skipping heartbeat for dependency file: {:?}", file_path);
+            return;
+        }
+
+        let classification = match (&file_path, entity_type) {
+            (Some(path), EntityType::File) => Some(classify_file(path).await),
+            _ => None,
+        };
+        let max_file_size_bytes = config
+            .max_file_size_bytes
+            .unwrap_or(DEFAULT_MAX_FILE_SIZE_BYTES);
+        let skip_binary_files = config.skip_binary_files.unwrap_or(true);
+        if classification.is_some_and(|classification| {
+            should_skip_file(&classification, max_file_size_bytes, skip_binary_files)
+        }) {
+            log::debug!(
+                "Skipping heartbeat for binary or oversized file: {:?}",
+                file_path
+            );
+            return;
+        }
+        if config.generated_file_policy.unwrap_or_default() == GeneratedFilePolicy::Skip
+            && classification
+                .is_some_and(|classification| classification.is_generated_or_vendored())
+        {
+            log::debug!(
+                "Skipping heartbeat for generated or vendored file: {:?}",
+                file_path
+            );
+            return;
+        }
+
+        let project_info = if dependency {
+            let Some(origin) = self.state().origin_project.clone() else {
+                log::debug!(
+                    "No project to attribute dependency file {:?} to, skipping heartbeat",
+                    file_path
+                );
+                return;
+            };
+            origin
+        } else {
+            let project_info = self.detect_project(file_path.clone(), &config).await;
+            self.state().origin_project = Some(project_info.clone());
+            project_info
+        };
+
+        let project_name = project_info.project;
+        log::debug!("Detected project: {:?}", project_name);
+
+        let branch_name = project_info.branch;
+        log::debug!("Detected branch: {:?}", branch_name);
+
+        let language_settings = LanguageSettings::from_config(&config);
+        let detected_language = match entity_type {
+            EntityType::File => {
+                resolve_language(
+                    file_path.as_deref(),
+                    language_id.as_deref(),
+                    &language_settings,
+                )
+                .await
+            }
+            EntityType::Terminal => None,
+        };
+        log::debug!("Detected language: {:?}", detected_language);
+        let language_category = detected_language
+            .as_ref()
+            .and_then(|language| language.category);
+        let embedded_language = detected_language
+            .as_ref()
+            .and_then(|language| language.embedded_language.clone());
+        let language = detected_language.map(|language| language.name);
+
+        let file_name = match entity_type {
+            EntityType::File => extract_file_name(file_path.as_deref()),
+            EntityType::Terminal => None,
+        };
+        log::debug!("Extracted file name: {:?}", file_name);
+
+        let git_head = project_info.git_head;
+
+        let should_send = {
+            let mut state = self.state();
+            if state.heartbeat_throttle.git_head_changed(&git_head) {
+                log::debug!(
+                    "Git HEAD changed from {:?} to {:?}",
+                    state.heartbeat_throttle.last_git_head(),
+                    git_head
+                );
+            }
+            let now = Utc::now();
+            let should_send = state
+                .heartbeat_throttle
+                .should_emit(now, &file_path, &git_head, force_send);
+            let max_per_minute = config
+                .max_heartbeats_per_minute
+                .unwrap_or(MAX_HEARTBEATS_PER_MINUTE);
+            if should_send && !state.rate_limiter.try_acquire(now, max_per_minute) {
+                log::warn!(
+                    "More than {} heartbeats per minute, dropping heartbeat for {:?}",
+                    max_per_minute,
+                    file_path
+                );
+                metrics::record_dropped(1);
+                return;
+            }
+            // Only recorded once the rate limiter let it through, so a dropped
+            // heartbeat does not hold back the next one for this file.
+            if should_send {
+                state
+                    .heartbeat_throttle
+                    .try_emit(now, &file_path, &git_head, force_send);
+                state.last_entity_type = entity_type;
+            }
+            should_send
+        };
+
+        if should_send {
+            log::info!("Sufficient activity, attempting to send heartbeat.");
+            log::debug!(
+                "Heartbeat details - Project: {:?}, Language: {:?}, File: {:?}, Branch: {:?}",
+                project_name,
+                language,
+                file_name,
+                branch_name
+            );
+            let mut heartbeat = Heartbeat::new(project_name, language, file_name, branch_name);
+            heartbeat.editor = editor_name(&config, self.client_name.as_deref());
+            heartbeat.repository_host = project_info.host;
+            heartbeat.is_dirty = project_info.changed_files.map(|count| count > 0);
+            heartbeat.changed_files = project_info.changed_files;
+            heartbeat.tags = Some(project_info.tags).filter(|tags| !tags.is_empty());
+            heartbeat.project_fallback =
+                Some(project_info.used_fallback).filter(|used_fallback| *used_fallback);
+            heartbeat.language_category = language_category;
+            heartbeat.embedded_language = embedded_language;
+            heartbeat.is_generated = classification.map(|classification| classification.generated);
+            heartbeat.is_vendored = classification.map(|classification| classification.vendored);
+            heartbeat.is_dependency = Some(dependency).filter(|dependency| *dependency);
+            heartbeat.entity_type = Some(entity_type);
+            // Dependencies are read-only context, whatever the editor reports.
+            heartbeat.is_write = Some(is_write && !dependency);
+            heartbeat.line_number = cursor.map(|cursor| cursor.line + 1);
+            heartbeat.cursor_position = cursor.map(|cursor| cursor.character + 1);
+            if let (Some(path), EntityType::File) = (&file_path, entity_type) {
+                heartbeat.lines = count_lines(path).await;
+            }
+            heartbeat.category = heartbeat
+                .project
+                .as_ref()
+                .filter(|project| self.state().debugging_projects.contains(*project))
+                .map(|_| HeartbeatCategory::Debugging);
+            heartbeat.intensity = Some(self.state().take_intensity(Utc::now()));
+            heartbeat.set_stamp(self.state().clock.now());
+            if let Err(e) = append_heartbeat_line(&self.history_path, &heartbeat).await {
+                log::warn!("Could not record heartbeat in the local history: {}", e);
+            }
+
+            if redact::log_payloads() {
+                if let Ok(json) = serde_json::to_string_pretty(&heartbeat) {
+                    log::debug!("Heartbeat JSON payload:\n{}", json);
+                }
+            }
+
+            if let Err(e) = self.process_heartbeat(heartbeat).await {
+                log::error!("Error processing heartbeat: {}", e);
+            }
+            self.maybe_send_daily_recap(true).await;
+        } else {
+            log::debug!("Skipping heartbeat: not enough activity or time passed.");
+        }
+    }
+
+    fn is_excluded_file(&self, file_path: &str, config: &ZiitConfig) -> bool {
+        let path = Path::new(file_path);
+        let relative = self
+            .state()
+            .workspace_folders
+            .iter()
+            .find_map(|folder| path.strip_prefix(folder).ok())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| path.to_path_buf());
+        match &config.excluded_directories {
+            Some(directories) => is_excluded(&relative, directories),
+            None => is_excluded(&relative, DEFAULT_EXCLUDED_DIRECTORIES),
+        }
+    }
+
+    #[tracing::instrument(name = "project_detection", skip_all, fields(file = file_path.as_deref()))]
+    async fn detect_project(&self, file_path: Option<String>, config: &ZiitConfig) -> ProjectInfo {
+        let mut detection_settings = DetectionSettings::from_config(config);
+        {
+            let mut state = self.state();
+            detection_settings.workspace_folders = state.workspace_folders.clone();
+            state.nested_repository_policy = detection_settings.nested_repository_policy;
+        }
+
+        self.project_detector
+            .detect(file_path, detection_settings)
+            .await
+    }
+
+    // Heartbeats for the project containing `path` are tagged as debugging
+    // until the session ends.
+    pub async fn set_debug_session(&self, path: String, active: bool) {
+        let config = read_config_file().await.unwrap_or_default();
+        let path = normalize_activity_path(path, &config);
+        let Some(project) = self.detect_project(Some(path), &config).await.project else {
+            log::debug!("Ignoring debug session outside of a known project");
+            return;
+        };
+
+        log::info!("Debug session for {} active: {}", project, active);
+        let mut state = self.state();
+        if active {
+            state.debugging_projects.insert(project);
+        } else {
+            state.debugging_projects.remove(&project);
+        }
+    }
+
+    async fn check_branch_change(&self) {
+        let (last_file, policy, entity_type) = {
+            let state = self.state();
+            (
+                state.heartbeat_throttle.last_file().map(str::to_string),
+                state.nested_repository_policy,
+                state.last_entity_type,
+            )
+        };
+        let Some(file_path) = last_file else {
+            return;
+        };
+
+        let head_path = file_path.clone();
+        let git_head = tokio::task::spawn_blocking(move || read_git_head(&head_path, policy))
+            .await
+            .ok()
+            .flatten();
+        let branch_changed = self.state().heartbeat_throttle.git_head_changed(&git_head);
+
+        if branch_changed {
+            log::info!(
+                "Branch changed for focused file {}, sending heartbeat",
+                file_path
+            );
+            self.record_activity(ActivityEvent {
+                file_path: Some(file_path),
+                language_id: None,
+                force_send: true,
+                entity_type,
+                is_write: false,
+                cursor: None,
+            });
+        }
+    }
+
+    // Heartbeats are stored in the newest shape and only downgraded when sent,
+    // so queued ones still go out as v2 once the server supports it.
+    async fn heartbeat_schema(&self, base_url: &str, api_key: &str) -> HeartbeatSchema {
+        let configured = read_config_file()
+            .await
+            .ok()
+            .and_then(|config| config.heartbeat_schema)
+            .unwrap_or_default();
+        if configured != HeartbeatSchema::Auto {
+            return configured;
+        }
+
+        let cached = self.state().negotiated_schema.clone();
+        if let Some((url, schema)) = cached {
+            if url == base_url {
+                return schema;
+            }
+        }
+
+        let schema = match fetch_heartbeat_schema_request(base_url, api_key).await {
+            Ok(version) if version >= 2 => HeartbeatSchema::V2,
+            Ok(_) => HeartbeatSchema::V1,
+            // Only an answer from the server settles it, a network error is
+            // retried with the next heartbeat.
+            Err(e) if e.to_string().contains("HTTP") => HeartbeatSchema::V1,
+            Err(e) => {
+                log::debug!("Could not negotiate the heartbeat schema: {}", e);
+                return HeartbeatSchema::V1;
+            }
+        };
+        log::info!("Using heartbeat schema {:?} for {}", schema, base_url);
+        self.state().negotiated_schema = Some((base_url.to_string(), schema));
+        schema
+    }
+
+    async fn process_heartbeat(&self, heartbeat: Heartbeat) -> Result<()> {
+        if self.is_dry_run().await {
+            log::info!(
+                "Dry run: writing heartbeat to {:?} instead of sending it",
+                self.dry_run_path
+            );
+            return append_heartbeat_line(&self.dry_run_path, &heartbeat).await;
+        }
+
+        // Mirrors keep their own state, so a slow one cannot delay the
+        // primary server.
+        let targets = mirror_targets().await;
+        if !targets.is_empty() {
+            let mirrors = Arc::clone(&self.mirrors);
+            let heartbeat = heartbeat.clone();
+            tokio::spawn(async move { mirrors.send(&targets, &heartbeat).await });
+        }
+
+        let api_key_opt = get_api_key().await?;
+        let base_url = get_base_url().await?;
+
+        if api_key_opt.is_none() || base_url.is_empty() {
+            log::warn!("API key or base URL not set. Queuing heartbeat.");
+            self.queue_offline_heartbeat(heartbeat).await?;
+            self.set_api_key_status(false);
+            return Ok(());
+        }
+
+        let key = api_key_opt.unwrap();
+
+        if !self.state().is_online {
+            log::info!("Currently offline. Queuing heartbeat.");
+            self.queue_offline_heartbeat(heartbeat).await?;
+            return Ok(());
+        }
+
+        let _permit = self.heartbeat_in_flight.acquire().await?;
+        let schema = self.heartbeat_schema(&base_url, &key).await;
+        match send_heartbeat_request(&base_url, &key, heartbeat.clone().into_schema(schema)).await {
+            Ok(_) => {
+                log::info!("Heartbeat sent successfully.");
+                metrics::record_sent(1);
+                self.set_online_status(true);
+                self.set_api_key_status(true);
+            }
+            Err(e) => {
+                log::error!("Failed to send heartbeat: {}. Queuing offline.", e);
+                telemetry::record(ErrorKind::Sync, &format!("Failed to send heartbeat: {}", e))
+                    .await;
+                self.set_online_status(false);
+                if e.to_string().contains("401")
+                    || e.to_string().to_lowercase().contains("invalid api key")
+                {
+                    self.set_api_key_status(false);
+                }
+                self.queue_offline_heartbeat(heartbeat).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn queue_offline_heartbeat(&self, heartbeat: Heartbeat) -> Result<()> {
+        let appended = {
+            let _lock = self.lock_offline_queue().await;
+            let appended = append_heartbeat_line(&self.offline_queue_path, &heartbeat).await;
+            if let (Ok(()), Some(key)) = (&appended, queue_key(&heartbeat)) {
+                self.state().offline_on_disk.insert(key);
+            }
+            appended
+        };
+        let queued = {
+            let mut state = self.state();
+            state.offline_heartbeats.push_back(heartbeat);
+            metrics::set_queue_depth(state.offline_heartbeats.len() + state.spilled_heartbeats);
+            state.offline_heartbeats.len()
+        };
+        log::debug!("Heartbeat added to offline queue. Size: {}", queued);
+        metrics::record_queued();
+        if queued > OFFLINE_MEMORY_LIMIT {
+            self.spill_oldest_heartbeats().await;
+            let _ = self.save_offline_heartbeats().await;
+        } else if let Err(e) = appended {
+            log::warn!("Could not append to the offline queue: {}", e);
+            let _ = self.save_offline_heartbeats().await;
+        }
+        Ok(())
+    }
+
+    async fn spill_oldest_heartbeats(&self) {
+        let oldest: Vec<Heartbeat> = {
+            let mut state = self.state();
+            let count = OFFLINE_SPILL_BATCH.min(state.offline_heartbeats.len());
+            state.offline_heartbeats.drain(..count).collect()
+        };
+
+        match self.spill_store.spill(&oldest).await {
+            Ok(segment) => {
+                log::info!(
+                    "Spilled {} offline heartbeats to {:?}",
+                    segment.count,
+                    segment.path
+                );
+                self.state().spilled_heartbeats += segment.count;
+            }
+            Err(e) => {
+                log::warn!("Could not spill offline heartbeats to disk: {}", e);
+                self.state().requeue_front(oldest);
+            }
+        }
+    }
+
+    // Moves the oldest spilled segment back into memory once there is room,
+    // so syncing drains the backlog in order.
+    async fn load_spilled_heartbeats(&self) {
+        let Some(segment) = self.spill_store.segments().await.into_iter().next() else {
+            return;
+        };
+        if self.state().offline_heartbeats.len() + segment.count > OFFLINE_MEMORY_LIMIT {
+            return;
+        }
+
+        match self.spill_store.load::<Heartbeat>(&segment).await {
+            Ok(heartbeats) => {
+                log::info!(
+                    "Loaded {} spilled heartbeats from {:?}",
+                    heartbeats.len(),
+                    segment.path
+                );
+                self.state().requeue_front(heartbeats);
+            }
+            Err(e) => {
+                log::error!(
+                    "Could not read spilled heartbeats {:?}: {}",
+                    segment.path,
+                    e
+                );
+                return;
+            }
+        }
+
+        // The segment is only removed once its heartbeats are safely back in
+        // the JSONL queue file; until then a crash leaves them in the segment.
+        if let Err(e) = self.save_offline_heartbeats().await {
+            log::warn!("Could not save offline heartbeats: {}", e);
+            return;
+        }
+        match self.spill_store.remove(&segment).await {
+            Ok(()) => {
+                let mut state = self.state();
+                state.spilled_heartbeats = state.spilled_heartbeats.saturating_sub(segment.count);
+            }
+            Err(e) => log::warn!("Could not remove spilled segment {:?}: {}", segment.path, e),
+        }
+    }
+
+    // Also runs while offline: a successful batch is how the manager notices
+    // the server is reachable again.
+    pub async fn sync_offline_heartbeats(&self) -> Result<()> {
+        let Ok(_permit) = self.batch_in_flight.try_acquire() else {
+            log::debug!("Offline sync already in progress, skipping.");
+            return Ok(());
+        };
+        if self.is_dry_run().await {
+            log::debug!("Dry run: leaving the offline queue untouched.");
+            return Ok(());
+        }
+        self.load_spilled_heartbeats().await;
+        if self.expire_offline_heartbeats().await > 0 {
+            self.save_offline_heartbeats().await?;
+        }
+
+        // Only held to read the file, never across the request. The sent
+        // heartbeats stay in offline_on_disk until the queue is rewritten,
+        // so the merge afterwards does not pick them up again.
+        {
+            let _lock = self.lock_offline_queue().await;
+            self.merge_offline_queue_file().await;
+        }
+        {
+            let state = self.state();
+            if state.offline_heartbeats.is_empty() {
+                return Ok(());
+            }
+        }
+
+        let api_key_opt = get_api_key().await?;
+        let base_url = get_base_url().await?;
+
+        if api_key_opt.is_none() || base_url.is_empty() {
+            log::warn!("Cannot sync offline heartbeats: API key or base URL not set.");
+            self.set_api_key_status(false);
+            return Ok(());
+        }
+        let key = api_key_opt.unwrap();
+
+        let batch: Vec<Heartbeat> = self.state().offline_heartbeats.drain(..).collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        log::info!("Attempting to sync {} offline heartbeats.", batch.len());
+
+        let schema = self.heartbeat_schema(&base_url, &key).await;
+        let payload = batch
+            .iter()
+            .map(|heartbeat| heartbeat.clone().into_schema(schema))
+            .collect();
+        match send_batch_heartbeats_request(&base_url, &key, payload).await {
+            Ok(_) => {
+                log::info!("Successfully synced {} offline heartbeats.", batch.len());
+                metrics::record_sent(batch.len());
+                self.set_online_status(true);
+                self.set_api_key_status(true);
+                self.save_offline_heartbeats().await?;
+                self.fetch_daily_summary().await?;
+            }
+            Err(e) => {
+                log::error!("Error syncing offline heartbeats: {}. Re-queuing.", e);
+                metrics::record_sync_failure();
+                telemetry::record(
+                    ErrorKind::Sync,
+                    &format!("Error syncing offline heartbeats: {}", e),
+                )
+                .await;
+                self.state().requeue_front(batch);
+                self.set_online_status(false);
+                if e.to_string().contains("401")
+                    || e.to_string().to_lowercase().contains("invalid api key")
+                {
+                    self.set_api_key_status(false);
+                }
+                self.save_offline_heartbeats().await?;
+                self.write_status_snapshot().await;
+            }
+        }
+        Ok(())
+    }
+
+    // The last summary fetched from the server and when it was fetched, so
+    // status commands have something to show while offline.
+    pub fn cached_summary(&self) -> Option<(DateTime<Utc>, DailySummaryResponse)> {
+        self.state().last_summary.clone()
+    }
+
+    pub async fn fetch_daily_summary(&self) -> Result<()> {
+        // Reconnecting is left to the offline sync, which retries queued
+        // heartbeats and refreshes the summary once it gets through.
+        if self.is_dry_run().await {
+            log::debug!("Dry run: not fetching the daily summary.");
+            return Ok(());
+        }
+        if !self.state().is_online {
+            log::debug!("Offline, keeping the cached daily summary.");
+            self.write_status_snapshot().await;
+            return Ok(());
+        }
+
+        let api_key_opt = get_api_key().await?;
+        let base_url = get_base_url().await?;
+
+        if api_key_opt.is_none() || base_url.is_empty() {
+            log::warn!("Cannot fetch daily summary: API key or base URL not set.");
+            self.set_api_key_status(false);
+            self.write_status_snapshot().await;
+            return Ok(());
+        }
+        let api_key = api_key_opt.unwrap();
+
+        match fetch_daily_summary_request(&base_url, &api_key).await {
+            Ok(summary_response) => {
+                self.set_online_status(true);
+                self.set_api_key_status(true);
+                if let Some(today_summary) = summary_response.summaries.first() {
+                    log::info!(
+                        "Today's total coding time: {} seconds",
+                        today_summary.total_seconds
+                    );
+                    self.state().today_seconds = Some(today_summary.total_seconds);
+                } else {
+                    log::info!("No summary data for today.");
+                }
+                self.state().last_summary = Some((Utc::now(), summary_response));
+            }
+            Err(e) => {
+                log::error!("Error fetching daily summary: {}", e);
+                if e.to_string().contains("401")
+                    || e.to_string().to_lowercase().contains("invalid api key")
+                {
+                    self.set_api_key_status(false);
+                } else {
+                    self.set_online_status(false);
+                }
+            }
+        }
+        self.write_status_snapshot().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str) -> Option<String> {
+        Some(path.to_string())
+    }
+
+    #[test]
+    fn test_recover_heartbeats_from_truncated_queue() {
+        let heartbeats = vec![
+            Heartbeat::new(None, None, file("a {\"}"), None),
+            Heartbeat::new(None, None, file("b"), None),
+        ];
+        let data = serde_json::to_string_pretty(&heartbeats).unwrap();
+        let truncated = format!(
+            "{},\n  {{\n    \"timestamp\": \"2024",
+            &data[..data.len() - 2]
+        );
+
+        let recovered = recover_heartbeats(&truncated);
+
+        let files: Vec<_> = recovered
+            .iter()
+            .map(|hb| hb.file.clone().unwrap())
+            .collect();
+        assert_eq!(files, ["a {\"}", "b"]);
+        assert!(recover_heartbeats("not json at all").is_empty());
+    }
+
+    #[test]
+    fn test_offline_queue_round_trips_as_jsonl() {
+        let queue: VecDeque<_> = ["a", "b"]
+            .into_iter()
+            .map(|name| Heartbeat::new(None, None, file(name), None))
+            .collect();
+        let data = to_jsonl(&queue).unwrap();
+        assert_eq!(data.lines().count(), 2);
+
+        let truncated = format!("{data}{{\"timestamp\": \"2024\n\n");
+        let (heartbeats, unreadable) = parse_heartbeat_lines(&truncated);
+
+        let files: Vec<_> = heartbeats
+            .iter()
+            .map(|hb| hb.file.clone().unwrap())
+            .collect();
+        assert_eq!(files, ["a", "b"]);
+        assert_eq!(unreadable, 1);
+    }
+
+    #[test]
+    fn test_terminal_entity_type_is_serialized() {
+        let mut heartbeat = Heartbeat::new(Some("ziit".to_string()), None, None, None);
+        heartbeat.entity_type = Some(EntityType::Terminal);
+
+        let value = serde_json::to_value(&heartbeat).unwrap();
+        assert_eq!(value["entityType"], "terminal");
+
+        let legacy: Heartbeat = serde_json::from_value(serde_json::json!({
+            "timestamp": "2024-01-01T10:00:00Z",
+            "project": null,
+            "language": null,
+            "file": null,
+            "branch": null,
+            "editor": "Zed",
+            "os": "linux",
+        }))
+        .unwrap();
+        assert_eq!(legacy.entity_type, None);
+    }
+
+    #[test]
+    fn test_v1_schema_drops_v2_fields() {
+        let mut heartbeat = Heartbeat::new(None, None, file("main.rs"), None);
+        heartbeat.lines = Some(42);
+        heartbeat.line_number = Some(7);
+        heartbeat.cursor_position = Some(3);
+        heartbeat.is_write = Some(true);
+        heartbeat.intensity = Some(40);
+
+        let v2 = serde_json::to_value(heartbeat.clone().into_schema(HeartbeatSchema::V2)).unwrap();
+        assert_eq!(v2["lines"], 42);
+        assert_eq!(v2["lineNumber"], 7);
+        assert!(v2["userAgent"].as_str().unwrap().starts_with("ziit-zed/"));
+
+        let v1 = serde_json::to_value(heartbeat.into_schema(HeartbeatSchema::V1)).unwrap();
+        for field in [
+            "isWrite",
+            "lines",
+            "lineNumber",
+            "cursorPosition",
+            "userAgent",
+            "machine",
+            "intensity",
+        ] {
+            assert!(v1.get(field).is_none(), "{field}");
+        }
+        assert_eq!(v1["file"], "main.rs");
+    }
+
+    #[test]
+    fn test_intensity() {
+        assert_eq!(intensity(0, 120), 0);
+        assert_eq!(intensity(60, 120), 50);
+        assert_eq!(intensity(500, 120), 100);
+        // A single keypress after a long break is not continuous typing.
+        assert_eq!(intensity(1, 3600), 0);
+        assert_eq!(intensity(30, 3600), 25);
+        // Short intervals are measured over at least a minute.
+        assert_eq!(intensity(30, 5), 50);
+    }
+
+    #[test]
+    fn test_warmup_ends_with_the_first_edit() {
+        let mut state = State::new(Vec::new());
+        let now = Utc::now();
+        state.warmup_until = Some(now + TimeDelta::seconds(10));
+
+        assert!(state.warming_up(now, false));
+        assert!(!state.warming_up(now + TimeDelta::seconds(10), false));
+        assert!(!state.warming_up(now, true));
+        assert!(!state.warming_up(now, false));
+    }
+
+    #[test]
+    fn test_hold_back_keeps_the_latest_event_per_file() {
+        let event = |file_name: &str, is_write: bool| ActivityEvent {
+            file_path: file(file_name),
+            language_id: None,
+            force_send: false,
+            entity_type: EntityType::File,
+            is_write,
+            cursor: None,
+        };
+        let mut held_back = Vec::new();
+        hold_back(&mut held_back, event("a", false));
+        hold_back(&mut held_back, event("b", false));
+
+        // The warmup ran out with only opens and focus changes.
+        assert!(release_held_back(&mut held_back, None).is_empty());
+        assert!(held_back.is_empty());
+
+        hold_back(&mut held_back, event("a", false));
+        hold_back(&mut held_back, event("b", false));
+        let released = release_held_back(&mut held_back, Some(event("a", true)));
+        let released: Vec<_> = released
+            .iter()
+            .map(|event| (event.file_path.as_deref(), event.is_write))
+            .collect();
+        assert_eq!(released, [(Some("b"), false), (Some("a"), true)]);
+    }
+
+    #[test]
+    fn test_trim_history_lines() {
+        let now = Utc::now();
+        let heartbeat = |timestamp: DateTime<Utc>| {
+            let mut heartbeat = Heartbeat::new(None, None, file("a"), None);
+            heartbeat.timestamp = timestamp.to_rfc3339();
+            serde_json::to_string(&heartbeat).unwrap()
+        };
+        let old = heartbeat(now - TimeDelta::days(100));
+        let recent = heartbeat(now - TimeDelta::days(1));
+        let data = format!("{old}\nnot json\n{recent}\n");
+
+        let (kept, trimmed) = trim_history_lines(&data, now - TimeDelta::days(90));
+        assert_eq!(trimmed, 1);
+        assert_eq!(kept, format!("not json\n{recent}\n"));
+    }
+
+    #[test]
+    fn test_merge_offline_queue() {
+        let heartbeat = |file_name: &str| Heartbeat::new(None, None, file(file_name), None);
+        let (ours, synced, theirs) = (heartbeat("ours"), heartbeat("synced"), heartbeat("theirs"));
+        let known: HashSet<String> = [&ours, &synced].into_iter().filter_map(queue_key).collect();
+        let mut queue = VecDeque::from([ours.clone(), synced, heartbeat("unwritten")]);
+
+        // Another process synced `synced` and queued `theirs`.
+        let adopted = merge_offline_queue(&mut queue, &known, vec![ours, theirs]);
+
+        assert_eq!(adopted, 1);
+        let files: Vec<_> = queue
+            .iter()
+            .map(|heartbeat| heartbeat.file.as_deref())
+            .collect();
+        assert_eq!(files, [Some("ours"), Some("unwritten"), Some("theirs")]);
+    }
+
+    #[test]
+    fn test_merge_skips_a_batch_in_flight() {
+        let sent = Heartbeat::new(None, None, file("sent"), None);
+        let known: HashSet<String> = queue_key(&sent).into_iter().collect();
+        let mut queue = VecDeque::new();
+
+        // The batch was drained and sent, but the file still lists it.
+        assert_eq!(merge_offline_queue(&mut queue, &known, vec![sent]), 0);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_editor_name() {
+        let mut config = ZiitConfig::default();
+        assert_eq!(editor_name(&config, None), "Zed");
+        assert_eq!(editor_name(&config, Some("Zed Nightly")), "Zed Nightly");
+        config.editor_name = Some("Zed (work)".to_string());
+        assert_eq!(editor_name(&config, Some("Zed Nightly")), "Zed (work)");
+        config.editor_name = Some(" ".to_string());
+        assert_eq!(editor_name(&config, None), "Zed");
+    }
+
+    #[test]
+    fn test_retain_recent() {
+        let mut queue: VecDeque<Heartbeat> =
+            ["2024-01-01T00:00:00Z", "2024-03-01T00:00:00Z", "garbage"]
+                .into_iter()
+                .map(|timestamp| {
+                    let mut heartbeat = Heartbeat::new(None, None, None, None);
+                    heartbeat.timestamp = timestamp.to_string();
+                    heartbeat
+                })
+                .collect();
+        let cutoff = DateTime::parse_from_rfc3339("2024-02-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(retain_recent(&mut queue, cutoff), 1);
+        let kept: Vec<&str> = queue
+            .iter()
+            .map(|heartbeat| heartbeat.timestamp.as_str())
+            .collect();
+        assert_eq!(kept, ["2024-03-01T00:00:00Z", "garbage"]);
+    }
+
+    #[tokio::test]
+    async fn test_count_lines() {
+        let path = std::env::temp_dir().join(format!("ziit-lines-{}", std::process::id()));
+        let path_str = path.to_string_lossy().into_owned();
+
+        std::fs::write(&path, "a\nb\nc").unwrap();
+        assert_eq!(count_lines(&path_str).await, Some(3));
+        std::fs::write(&path, "a\nb\n").unwrap();
+        assert_eq!(count_lines(&path_str).await, Some(2));
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(count_lines(&path_str).await, Some(0));
+
+        std::fs::remove_file(&path).ok();
+        assert_eq!(count_lines(&path_str).await, None);
+    }
+
+    #[test]
+    fn test_requeue_front_keeps_order() {
+        let mut state = State::new(Vec::new());
+        let heartbeat = |file_name: &str| Heartbeat::new(None, None, file(file_name), None);
+        state.offline_heartbeats.push_back(heartbeat("c"));
+
+        state.requeue_front(vec![heartbeat("a"), heartbeat("b")]);
+
+        let files: Vec<_> = state
+            .offline_heartbeats
+            .iter()
+            .map(|hb| hb.file.clone().unwrap())
+            .collect();
+        assert_eq!(files, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_status_flag_transitions() {
+        let mut state = State::new(Vec::new());
+        assert!(state.is_online && state.has_valid_api_key);
+
+        state.set_online(false);
+        state.set_api_key_valid(false);
+        assert!(!state.is_online && !state.has_valid_api_key);
+
+        state.set_online(true);
+        assert!(state.is_online && !state.has_valid_api_key);
+    }
+}
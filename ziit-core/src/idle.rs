@@ -0,0 +1,149 @@
+// Seconds since the last keyboard or mouse input anywhere on the system.
+// Only compiled in with the os-idle feature; without it, or when the platform
+// does not expose the idle time, the editor events are all we go by.
+#[cfg(feature = "os-idle")]
+pub async fn idle_seconds() -> Option<u64> {
+    tokio::task::spawn_blocking(platform::idle_millis)
+        .await
+        .ok()
+        .flatten()
+        .map(|millis| millis / 1000)
+}
+
+#[cfg(not(feature = "os-idle"))]
+pub async fn idle_seconds() -> Option<u64> {
+    None
+}
+
+pub async fn is_away(threshold_seconds: u64) -> bool {
+    idle_seconds()
+        .await
+        .is_some_and(|seconds| seconds >= threshold_seconds)
+}
+
+#[cfg(all(feature = "os-idle", target_os = "macos"))]
+mod platform {
+    // IOKit's HID system keeps the idle time in nanoseconds.
+    pub fn idle_millis() -> Option<u64> {
+        let output = crate::vcs::run_command(
+            "ioreg",
+            &std::env::temp_dir(),
+            &["-c", "IOHIDSystem", "-d", "4"],
+        )?;
+        super::parse_ioreg(&output)
+    }
+}
+
+#[cfg(all(feature = "os-idle", windows))]
+mod platform {
+    #[repr(C)]
+    struct LastInputInfo {
+        size: u32,
+        time: u32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetLastInputInfo(info: *mut LastInputInfo) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    pub fn idle_millis() -> Option<u64> {
+        let mut info = LastInputInfo {
+            size: std::mem::size_of::<LastInputInfo>() as u32,
+            time: 0,
+        };
+        // SAFETY: info is a valid LASTINPUTINFO with its size set.
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // Both tick counts wrap after 49.7 days.
+        let now = unsafe { GetTickCount() };
+        Some(u64::from(now.wrapping_sub(info.time)))
+    }
+}
+
+#[cfg(all(feature = "os-idle", unix, not(target_os = "macos")))]
+mod platform {
+    // xprintidle covers X11, GNOME's idle monitor covers its Wayland session.
+    // Other Wayland compositors only expose idle notifications to clients
+    // with a surface, which a language server does not have.
+    pub fn idle_millis() -> Option<u64> {
+        let dir = std::env::temp_dir();
+        if std::env::var_os("DISPLAY").is_some() {
+            if let Some(millis) = crate::vcs::run_command("xprintidle", &dir, &[])
+                .and_then(|output| output.parse().ok())
+            {
+                return Some(millis);
+            }
+        }
+        let output = crate::vcs::run_command(
+            "gdbus",
+            &dir,
+            &[
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ],
+        )?;
+        super::parse_gdbus(&output)
+    }
+}
+
+#[cfg(all(feature = "os-idle", not(any(unix, windows))))]
+mod platform {
+    pub fn idle_millis() -> Option<u64> {
+        None
+    }
+}
+
+// `    |   "HIDIdleTime" = 1234567890`
+#[cfg(all(feature = "os-idle", any(target_os = "macos", test)))]
+fn parse_ioreg(output: &str) -> Option<u64> {
+    let nanos: u64 = output
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))?
+        .split('=')
+        .nth(1)?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(nanos / 1_000_000)
+}
+
+// `(uint64 12345,)`
+#[cfg(all(feature = "os-idle", any(all(unix, not(target_os = "macos")), test)))]
+fn parse_gdbus(output: &str) -> Option<u64> {
+    output
+        .trim_start_matches("(uint64 ")
+        .trim_end_matches(",)")
+        .parse()
+        .ok()
+}
+
+#[cfg(all(test, feature = "os-idle"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ioreg() {
+        let output = "    | |   \"HIDIdleTime\" = 2500000000\n    | |   \"HIDKeyboardModifierMappingPairs\" = ()";
+        assert_eq!(parse_ioreg(output), Some(2500));
+        assert_eq!(parse_ioreg("nothing here"), None);
+    }
+
+    #[test]
+    fn test_parse_gdbus() {
+        assert_eq!(parse_gdbus("(uint64 12345,)"), Some(12345));
+        assert_eq!(parse_gdbus("Error: no such name"), None);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::api::{fetch_heartbeat_schema_request, send_batch_heartbeats_request};
+use crate::config::{read_config_file, HeartbeatSchema};
+use crate::heartbeat::{EntityType, Heartbeat, HeartbeatCategory};
+use crate::language::extract_file_name;
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use serde::Deserialize;
+use tokio::time::{sleep, Duration};
+
+const IMPORT_BATCH_SIZE: usize = 500;
+// Pause between batches so a large import does not trip the rate limit.
+const IMPORT_BATCH_DELAY_MILLIS: u64 = 1000;
+const RATE_LIMIT_RETRIES: u32 = 5;
+const RATE_LIMIT_BACKOFF_SECONDS: u64 = 5;
+
+// The subset of WakaTime's heartbeat fields that maps onto Ziit.
+#[derive(Deserialize, Debug)]
+struct WakaTimeHeartbeat {
+    entity: String,
+    #[serde(rename = "type", default)]
+    entity_type: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    time: f64,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    branch: Option<String>,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    is_write: Option<bool>,
+    #[serde(default)]
+    lines: Option<usize>,
+    #[serde(default)]
+    lineno: Option<u32>,
+    #[serde(default)]
+    cursorpos: Option<u32>,
+    #[serde(default)]
+    user_agent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Day {
+    heartbeats: Vec<WakaTimeHeartbeat>,
+}
+
+// A plain list of heartbeats (our own export, the API's `data` page) or
+// WakaTime's full account dump grouped by day.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Dump {
+    Heartbeats(Vec<WakaTimeHeartbeat>),
+    Page { data: Vec<WakaTimeHeartbeat> },
+    Days { days: Vec<Day> },
+}
+
+fn os_from_user_agent(user_agent: &str) -> String {
+    let user_agent = user_agent.to_ascii_lowercase();
+    ["linux", "darwin", "windows"]
+        .into_iter()
+        .find(|os| user_agent.contains(&format!("({os}")))
+        .map_or_else(|| "unknown".to_string(), |os| os.replace("darwin", "macos"))
+}
+
+fn to_ziit(heartbeat: WakaTimeHeartbeat) -> Option<Heartbeat> {
+    let timestamp = DateTime::from_timestamp_millis((heartbeat.time * 1000.0) as i64)?;
+    let is_file = heartbeat
+        .entity_type
+        .as_deref()
+        .is_none_or(|kind| kind == "file");
+
+    let mut converted = Heartbeat::new(
+        heartbeat.project,
+        heartbeat.language,
+        is_file
+            .then(|| extract_file_name(Some(&heartbeat.entity)))
+            .flatten(),
+        heartbeat.branch,
+    );
+    converted.timestamp = timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    converted.editor = "WakaTime".to_string();
+    converted.os = heartbeat
+        .user_agent
+        .as_deref()
+        .map_or_else(|| "unknown".to_string(), os_from_user_agent);
+    converted.entity_type = Some(if is_file {
+        EntityType::File
+    } else {
+        EntityType::Terminal
+    });
+    converted.category = (heartbeat.category.as_deref() == Some("debugging"))
+        .then_some(HeartbeatCategory::Debugging);
+    converted.is_write = heartbeat.is_write;
+    converted.lines = heartbeat.lines;
+    converted.line_number = heartbeat.lineno;
+    converted.cursor_position = heartbeat.cursorpos;
+    converted.user_agent = heartbeat.user_agent;
+    converted.machine = None;
+    Some(converted)
+}
+
+// Oldest first, so the upload replays history in order.
+pub fn parse_wakatime_dump(data: &str) -> Result<Vec<Heartbeat>> {
+    let heartbeats = match serde_json::from_str(data)
+        .map_err(|e| anyhow!("Not a WakaTime heartbeat export: {}", e))?
+    {
+        Dump::Heartbeats(heartbeats) | Dump::Page { data: heartbeats } => heartbeats,
+        Dump::Days { days } => days.into_iter().flat_map(|day| day.heartbeats).collect(),
+    };
+
+    let mut converted: Vec<_> = heartbeats.into_iter().filter_map(to_ziit).collect();
+    converted.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(converted)
+}
+
+async fn send_with_backoff(base_url: &str, api_key: &str, batch: &[Heartbeat]) -> Result<()> {
+    let mut backoff = Duration::from_secs(RATE_LIMIT_BACKOFF_SECONDS);
+    for attempt in 0..=RATE_LIMIT_RETRIES {
+        match send_batch_heartbeats_request(base_url, api_key, batch.to_vec()).await {
+            Err(e) if e.to_string().contains("429") && attempt < RATE_LIMIT_RETRIES => {
+                log::warn!("Rate limited during import, retrying in {:?}", backoff);
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+    unreachable!("the last attempt always returns")
+}
+
+// Uploads in batches and reports the number sent so far after each one.
+// Stops at the first batch that still fails, so a rerun can resume from the
+// returned count.
+pub async fn upload(
+    base_url: &str,
+    api_key: &str,
+    heartbeats: &[Heartbeat],
+    mut progress: impl FnMut(usize),
+) -> Result<usize> {
+    let schema = match read_config_file()
+        .await?
+        .heartbeat_schema
+        .unwrap_or_default()
+    {
+        HeartbeatSchema::Auto => match fetch_heartbeat_schema_request(base_url, api_key).await {
+            Ok(version) if version >= 2 => HeartbeatSchema::V2,
+            _ => HeartbeatSchema::V1,
+        },
+        configured => configured,
+    };
+    let heartbeats: Vec<_> = heartbeats
+        .iter()
+        .map(|heartbeat| heartbeat.clone().into_schema(schema))
+        .collect();
+
+    let mut sent = 0;
+    for (index, batch) in heartbeats.chunks(IMPORT_BATCH_SIZE).enumerate() {
+        if index > 0 {
+            sleep(Duration::from_millis(IMPORT_BATCH_DELAY_MILLIS)).await;
+        }
+        send_with_backoff(base_url, api_key, batch)
+            .await
+            .map_err(|e| anyhow!("Import stopped after {} heartbeats: {}", sent, e))?;
+        sent += batch.len();
+        progress(sent);
+    }
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEARTBEAT: &str = r#"{
+        "entity": "/home/user/ziit/src/main.rs",
+        "type": "file",
+        "category": "debugging",
+        "time": 1704103200.5,
+        "project": "ziit",
+        "branch": "main",
+        "language": "Rust",
+        "is_write": true,
+        "lineno": 12,
+        "user_agent": "wakatime/v1.90.0 (linux-6.5) go1.21 vscode/1.85.0 vscode-wakatime/24.4.0"
+    }"#;
+
+    #[test]
+    fn test_parse_plain_list() {
+        let heartbeats = parse_wakatime_dump(&format!("[{HEARTBEAT}]")).unwrap();
+
+        assert_eq!(heartbeats.len(), 1);
+        let heartbeat = &heartbeats[0];
+        assert_eq!(heartbeat.timestamp, "2024-01-01T10:00:00Z");
+        assert_eq!(heartbeat.file.as_deref(), Some("main.rs"));
+        assert_eq!(heartbeat.project.as_deref(), Some("ziit"));
+        assert_eq!(heartbeat.os, "linux");
+        assert_eq!(heartbeat.category, Some(HeartbeatCategory::Debugging));
+        assert_eq!(heartbeat.is_write, Some(true));
+        assert_eq!(heartbeat.line_number, Some(12));
+    }
+
+    #[test]
+    fn test_parse_account_dump_sorted_by_time() {
+        let later = HEARTBEAT.replace("1704103200.5", "1704189600");
+        let dump = format!(
+            r#"{{"user": {{}}, "days": [{{"date": "2024-01-02", "heartbeats": [{later}]}}, {{"date": "2024-01-01", "heartbeats": [{HEARTBEAT}]}}]}}"#
+        );
+
+        let heartbeats = parse_wakatime_dump(&dump).unwrap();
+        let timestamps: Vec<_> = heartbeats.iter().map(|hb| hb.timestamp.as_str()).collect();
+        assert_eq!(timestamps, ["2024-01-01T10:00:00Z", "2024-01-02T10:00:00Z"]);
+
+        let page = format!(r#"{{"data": [{HEARTBEAT}]}}"#);
+        assert_eq!(parse_wakatime_dump(&page).unwrap().len(), 1);
+        assert!(parse_wakatime_dump(r#"{"something": "else"}"#).is_err());
+    }
+}
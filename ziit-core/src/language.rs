@@ -0,0 +1,1250 @@
+use crate::config::ZiitConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::LazyLock;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const SHEBANG_READ_LIMIT: usize = 256;
+const HEURISTIC_READ_LIMIT: usize = 1024;
+const MODELINE_READ_LIMIT: usize = 1024;
+const MODELINE_LINES: usize = 5;
+// Larger notebooks keep being reported as "Jupyter Notebook".
+const NOTEBOOK_PARSE_LIMIT: u64 = 5 * 1024 * 1024;
+const CONTENT_SAMPLE_SIZE: usize = 2048;
+// A sample must hit at least this many distinct signals of one language.
+const CONTENT_SAMPLE_MIN_SCORE: usize = 2;
+
+const LANGUAGES_JSON: &str = include_str!("languages.json");
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguageCategory {
+    Programming,
+    Markup,
+    Data,
+    Prose,
+    Config,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedLanguage {
+    pub name: String,
+    pub category: Option<LanguageCategory>,
+    pub embedded_language: Option<String>,
+}
+
+// One entry of the table generated from GitHub Linguist by
+// scripts/generate_languages.sh.
+#[derive(Deserialize, Debug)]
+struct LanguageEntry {
+    name: String,
+    #[serde(rename = "type")]
+    category: LanguageCategory,
+    #[serde(default)]
+    extensions: Vec<String>,
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+#[derive(Default)]
+struct LanguageDatabase {
+    by_extension: HashMap<String, String>,
+    by_file_name: HashMap<String, String>,
+    by_name: HashMap<String, String>,
+    categories: HashMap<String, LanguageCategory>,
+}
+
+impl LanguageDatabase {
+    fn load() -> Self {
+        let entries: Vec<LanguageEntry> = match serde_json::from_str(LANGUAGES_JSON) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::error!("Could not parse embedded language table: {}", e);
+                return Self::default();
+            }
+        };
+
+        let mut database = Self::default();
+        for entry in entries {
+            for extension in &entry.extensions {
+                database
+                    .by_extension
+                    .entry(extension.to_lowercase())
+                    .or_insert_with(|| entry.name.clone());
+            }
+            for file_name in &entry.filenames {
+                database
+                    .by_file_name
+                    .entry(file_name.to_lowercase())
+                    .or_insert_with(|| entry.name.clone());
+            }
+            for alias in &entry.aliases {
+                database
+                    .by_name
+                    .entry(alias.to_lowercase())
+                    .or_insert_with(|| entry.name.clone());
+            }
+            database
+                .categories
+                .insert(entry.name.clone(), entry.category);
+            database
+                .by_name
+                .insert(entry.name.to_lowercase(), entry.name);
+        }
+        database
+    }
+}
+
+static LANGUAGE_DATABASE: LazyLock<LanguageDatabase> = LazyLock::new(LanguageDatabase::load);
+
+pub fn load_language_database() {
+    let database = &*LANGUAGE_DATABASE;
+    log::info!(
+        "Loaded language table with {} extensions and {} file names",
+        database.by_extension.len(),
+        database.by_file_name.len()
+    );
+}
+
+fn language_for_file_name(file_name: &str) -> Option<&'static str> {
+    let database = &*LANGUAGE_DATABASE;
+    let file_name = file_name.to_lowercase();
+
+    if let Some(language) = database.by_file_name.get(&file_name) {
+        return Some(language);
+    }
+    if file_name.starts_with("docker-compose")
+        && (file_name.ends_with(".yml") || file_name.ends_with(".yaml"))
+    {
+        return Some("Docker Compose");
+    }
+
+    // Longest extension first, so `App.spec.tsx` tries `.spec.tsx` before `.tsx`
+    // and `archive.tar.gz` is not mistaken for anything.
+    file_name
+        .match_indices('.')
+        .filter(|(index, _)| *index > 0)
+        .find_map(|(index, _)| database.by_extension.get(&file_name[index..]))
+        .map(|language| language.as_str())
+}
+
+// Languages that only come from a user languageMap or the client have no
+// category unless their name matches the table.
+pub fn language_category(language: &str) -> Option<LanguageCategory> {
+    let database = &*LANGUAGE_DATABASE;
+    database.categories.get(language).copied().or_else(|| {
+        let name = database.by_name.get(&language.to_lowercase())?;
+        database.categories.get(name).copied()
+    })
+}
+
+pub fn detect_language(file_path: Option<&str>) -> Option<String> {
+    let path = Path::new(file_path?);
+    let file_name = path.file_name()?.to_str()?;
+    language_for_file_name(file_name).map(|language| language.to_string())
+}
+
+// Standard LSP language identifiers whose canonical Ziit name is not simply
+// a case-insensitive match against a name or alias in the language table.
+const LSP_LANGUAGE_IDS: &[(&str, &str)] = &[
+    ("javascriptreact", "JSX"),
+    ("typescriptreact", "TSX"),
+    ("shellscript", "Shell Script"),
+    ("bash", "Shell Script"),
+    ("sh", "Shell Script"),
+    ("csharp", "CSharp"),
+    ("cpp", "C++"),
+    ("fsharp", "FSharp"),
+    ("makefile", "Make"),
+    ("vue", "Vue.js"),
+    ("objective-c", "Objective-C"),
+    ("emacs-lisp", "Elisp"),
+    ("restructuredtext", "reST"),
+    ("rst", "reST"),
+    ("terraform", "Terraform"),
+    ("hcl", "HCL"),
+    ("vb", "Visual Basic"),
+    ("proto3", "Proto"),
+    ("protobuf", "Proto"),
+    ("dotenv", "env"),
+];
+
+// Zed language names (sent lowercased as the LSP languageId) paired with the
+// Ziit name they are reported as. Names that already match a language in the
+// table, like "Rust" or "Shell Script", need no entry.
+const ZED_LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("Apache Avro (IDL)", "Avro IDL"),
+    ("Cabal", "Cabal Config"),
+    ("Earthfile", "Earthly"),
+    ("GDShader", "Godot Shader"),
+    ("Glimmer (JavaScript)", "Glimmer JS"),
+    ("Glimmer (TypeScript)", "Glimmer TS"),
+    ("Go Mod", "Go"),
+    ("Go Sum", "Go"),
+    ("Go Work", "Go"),
+    ("HTML+ERB", "ERB"),
+    ("HTML-Jinja", "Jinja"),
+    ("JS+ERB", "ERB"),
+    ("JSON Lines", "JSONL"),
+    ("Jinja2", "Jinja"),
+    ("Lean 4", "Lean"),
+    ("Nu", "Nushell"),
+    ("OCaml Interface", "OCaml"),
+    ("OpenTofu Vars", "OpenTofu"),
+    ("Reason", "ReasonML"),
+    ("Reason Interface", "ReasonML"),
+    ("Terraform Vars", "Terraform"),
+    ("Textproto", "Proto"),
+    ("Tera (CSS)", "Tera"),
+    ("Tera (HTML)", "Tera"),
+    ("Tera (JSON)", "Tera"),
+    ("Tera (TOML)", "Tera"),
+    ("Tera (YAML)", "Tera"),
+    ("WGSL/WESL", "Wgsl"),
+    ("YAML+ERB", "ERB"),
+    ("php_only", "PHP"),
+];
+
+// Maps the `languageId` sent by the client to Ziit's canonical name. Plain
+// text returns None so the caller can fall back to extension detection.
+pub fn language_from_language_id(language_id: &str) -> Option<String> {
+    let language_id = language_id.trim();
+    let lowercase = language_id.to_lowercase();
+    if matches!(lowercase.as_str(), "" | "plaintext" | "plain text" | "text") {
+        return None;
+    }
+
+    if let Some(language) = known_language_name(&lowercase) {
+        return Some(language.to_string());
+    }
+    log::info!(
+        "No Ziit name known for language id {:?}, reporting it verbatim",
+        language_id
+    );
+    Some(language_id.to_string())
+}
+
+fn known_language_name(lowercase_id: &str) -> Option<&'static str> {
+    if let Some((_, language)) = ZED_LANGUAGE_NAMES
+        .iter()
+        .find(|(zed_name, _)| zed_name.to_lowercase() == lowercase_id)
+    {
+        return Some(language);
+    }
+    if let Some((_, language)) = LSP_LANGUAGE_IDS.iter().find(|(id, _)| *id == lowercase_id) {
+        return Some(language);
+    }
+    LANGUAGE_DATABASE
+        .by_name
+        .get(lowercase_id)
+        .map(|language| language.as_str())
+}
+
+// Vim `filetype` and Emacs `mode` names that differ from LSP identifiers.
+const MODELINE_ALIASES: &[(&str, &str)] = &[
+    ("js", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("py", "Python"),
+    ("rb", "Ruby"),
+    ("zsh", "Shell Script"),
+    ("shell-script", "Shell Script"),
+    ("conf", "ini"),
+    ("dosini", "ini"),
+    ("tex", "LaTeX"),
+    ("latex", "LaTeX"),
+    ("lisp-interaction", "Elisp"),
+    ("makefile-gmake", "Make"),
+    ("c++", "C++"),
+    ("cs", "CSharp"),
+    ("haskell", "Haskell"),
+    ("tuareg", "OCaml"),
+];
+
+fn language_from_modeline_name(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    let name = name.strip_suffix("-mode").unwrap_or(&name);
+    MODELINE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, language)| *language)
+        .or_else(|| known_language_name(name))
+}
+
+// `-*- mode: ruby -*-` or the short `-*- ruby -*-` form.
+fn parse_emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (inner, _) = rest.split_once("-*-")?;
+    if !inner.contains(':') {
+        return Some(inner.trim());
+    }
+    inner.split(';').find_map(|setting| {
+        let (key, value) = setting.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("mode")
+            .then_some(value.trim())
+    })
+}
+
+// `vim: ft=yaml`, `vim: set filetype=yaml :`, also `vi:` and `ex:` prefixes.
+fn parse_vim_modeline(line: &str) -> Option<&str> {
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(index, _)| *index == 0 || line[..*index].ends_with(|c: char| c.is_whitespace()))
+            .map(|(index, marker)| index + marker.len())
+    })?;
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syn" | "syntax").then_some(value)
+        })
+}
+
+pub fn language_from_modeline(content: &str) -> Option<&'static str> {
+    let lines: Vec<&str> = content.lines().collect();
+    // Emacs only looks at the first line, or the second after a shebang.
+    let emacs = lines
+        .iter()
+        .take(2)
+        .find_map(|line| parse_emacs_modeline(line))
+        .and_then(language_from_modeline_name);
+    if emacs.is_some() {
+        return emacs;
+    }
+
+    // Vim checks the first and last five lines.
+    let tail_start = lines.len().saturating_sub(MODELINE_LINES);
+    lines
+        .iter()
+        .take(MODELINE_LINES)
+        .chain(lines.iter().skip(tail_start))
+        .find_map(|line| parse_vim_modeline(line))
+        .and_then(language_from_modeline_name)
+}
+
+pub async fn detect_language_from_modeline(file_path: Option<&str>) -> Option<String> {
+    let path = Path::new(file_path?);
+    let mut file = tokio::fs::File::open(path).await.ok()?;
+    let length = file.metadata().await.ok()?.len();
+
+    let mut head = vec![0; MODELINE_READ_LIMIT];
+    let read = file.read(&mut head).await.ok()?;
+    let mut content = String::from_utf8_lossy(&head[..read]).into_owned();
+
+    if length > MODELINE_READ_LIMIT as u64 {
+        let tail_offset = length.saturating_sub(MODELINE_READ_LIMIT as u64);
+        file.seek(SeekFrom::Start(tail_offset)).await.ok()?;
+        let mut tail = vec![0; MODELINE_READ_LIMIT];
+        let read = file.read(&mut tail).await.ok()?;
+        content.push('\n');
+        content.push_str(&String::from_utf8_lossy(&tail[..read]));
+    }
+
+    language_from_modeline(&content).map(|language| language.to_string())
+}
+
+// User `languageMap` entries take precedence over everything built in. Keys
+// are either exact file names (`Tiltfile`) or extensions (`.proto`, `proto`,
+// `*.gen.go`); the longest matching extension wins.
+pub fn language_from_map(
+    file_path: Option<&str>,
+    language_map: &HashMap<String, String>,
+) -> Option<String> {
+    let file_name = Path::new(file_path?).file_name()?.to_str()?.to_lowercase();
+
+    if let Some((_, language)) = language_map
+        .iter()
+        .find(|(key, _)| key.to_lowercase() == file_name)
+    {
+        return Some(language.clone());
+    }
+
+    language_map
+        .iter()
+        .filter_map(|(key, language)| {
+            let extension = key.trim_start_matches('*').trim_start_matches('.');
+            if extension.is_empty() {
+                return None;
+            }
+            let suffix = format!(".{}", extension.to_lowercase());
+            file_name
+                .ends_with(&suffix)
+                .then_some((suffix.len(), language))
+        })
+        .max_by_key(|(length, _)| *length)
+        .map(|(_, language)| language.clone())
+}
+
+pub fn language_from_shebang(first_line: &str) -> Option<&'static str> {
+    let command = first_line.strip_prefix("#!")?.trim();
+    let mut parts = command.split_whitespace();
+    let mut program = parts.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = parts.find(|arg| !arg.starts_with('-') && !arg.contains('='))?;
+    }
+    // `python3.12` and `lua5.4` share an interpreter with `python` and `lua`
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    let language = match program {
+        "python" | "pypy" => "Python",
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "ash" => "Shell Script",
+        "fish" => "Fish",
+        "node" | "nodejs" | "bun" => "JavaScript",
+        "deno" | "ts-node" | "tsx" => "TypeScript",
+        "ruby" => "Ruby",
+        "perl" => "Perl",
+        "php" => "PHP",
+        "lua" | "luajit" => "Lua",
+        "Rscript" => "R",
+        "julia" => "Julia",
+        "awk" | "gawk" | "mawk" => "AWK",
+        "sed" => "sed",
+        "jq" => "jq",
+        "elixir" => "Elixir",
+        "escript" => "Erlang",
+        "pwsh" | "powershell" => "PowerShell",
+        "make" => "Make",
+        "nix-shell" => "Nix",
+        "runhaskell" | "runghc" => "Haskell",
+        "scala" => "Scala",
+        "groovy" => "Groovy",
+        "swift" => "Swift",
+        "racket" => "Racket",
+        "guile" => "Scheme",
+        "ocaml" => "OCaml",
+        "crystal" => "Crystal",
+        _ => return None,
+    };
+    Some(language)
+}
+
+// Only consulted for files without an extension, and only reads the first
+// few hundred bytes so large files never stall the activity path.
+pub async fn detect_language_from_shebang(file_path: Option<&str>) -> Option<String> {
+    let path = Path::new(file_path?);
+    if path.extension().is_some() {
+        return None;
+    }
+
+    let head = read_file_head(path, SHEBANG_READ_LIMIT).await?;
+    let first_line = head.lines().next()?;
+    language_from_shebang(first_line).map(|language| language.to_string())
+}
+
+async fn read_file_head(path: &Path, limit: usize) -> Option<String> {
+    let mut file = tokio::fs::File::open(path).await.ok()?;
+    let mut buffer = vec![0; limit];
+    let read = file.read(&mut buffer).await.ok()?;
+    Some(String::from_utf8_lossy(&buffer[..read]).into_owned())
+}
+
+fn is_ambiguous_extension(extension: &str) -> bool {
+    matches!(extension, "m" | "v" | "pl" | "sql" | "h")
+}
+
+// Keyword sniffing for extensions shared by several languages. Returns None
+// when nothing conclusive is found so the extension default still applies.
+pub fn language_from_heuristics(extension: &str, head: &str) -> Option<&'static str> {
+    let has_line_starting = |prefixes: &[&str]| {
+        head.lines()
+            .map(str::trim_start)
+            .any(|line| prefixes.iter().any(|prefix| line.starts_with(prefix)))
+    };
+    let contains_any = |needles: &[&str]| needles.iter().any(|needle| head.contains(needle));
+
+    match extension {
+        "m" => {
+            if has_line_starting(&["#import", "#include", "@interface", "@implementation"]) {
+                Some("Objective-C")
+            } else if has_line_starting(&["function ", "%", "end"]) || head.contains("disp(") {
+                Some("MATLAB")
+            } else {
+                None
+            }
+        }
+        "v" => {
+            if contains_any(&["endmodule", "always @", "posedge", "input wire"]) {
+                Some("Verilog")
+            } else if has_line_starting(&["Theorem ", "Lemma ", "Proof.", "Qed.", "Require Import"])
+            {
+                Some("Coq")
+            } else if has_line_starting(&["fn ", "module ", "import ", "pub fn "]) {
+                Some("V")
+            } else {
+                None
+            }
+        }
+        "pl" => {
+            if contains_any(&["use strict", "use warnings", "my $", "print \""])
+                || has_line_starting(&["sub "])
+            {
+                Some("Perl")
+            } else if head.contains(":-") {
+                Some("Prolog")
+            } else {
+                None
+            }
+        }
+        "sql" => {
+            let upper = head.to_uppercase();
+            if upper.contains("PLPGSQL") || upper.contains("CREATE EXTENSION") {
+                Some("PLpgSQL")
+            } else if upper.contains("VARCHAR2") || upper.contains("CREATE OR REPLACE PACKAGE") {
+                Some("PLSQL")
+            } else if upper.contains("NVARCHAR")
+                || upper.contains("[DBO]")
+                || upper.lines().any(|line| line.trim() == "GO")
+            {
+                Some("TSQL")
+            } else {
+                None
+            }
+        }
+        "h" => {
+            if has_line_starting(&["@interface", "#import"]) {
+                Some("Objective-C")
+            } else if has_line_starting(&[
+                "class ",
+                "namespace ",
+                "template",
+                "#include <iostream>",
+            ]) {
+                Some("C++")
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// Reads the first KB of files with an ambiguous extension and picks the
+// language from its contents.
+pub async fn disambiguate_language(file_path: Option<&str>) -> Option<String> {
+    let path = Path::new(file_path?);
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    if !is_ambiguous_extension(&extension) {
+        return None;
+    }
+
+    let head = read_file_head(path, HEURISTIC_READ_LIMIT).await?;
+    language_from_heuristics(&extension, &head).map(|language| language.to_string())
+}
+
+#[derive(Deserialize, Default)]
+struct Notebook {
+    #[serde(default)]
+    metadata: NotebookMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct NotebookMetadata {
+    kernelspec: Option<NotebookKernelSpec>,
+    language_info: Option<NotebookLanguageInfo>,
+}
+
+#[derive(Deserialize)]
+struct NotebookKernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NotebookLanguageInfo {
+    name: Option<String>,
+}
+
+pub fn language_from_notebook(content: &str) -> Option<String> {
+    let notebook: Notebook = serde_json::from_str(content).ok()?;
+    let metadata = notebook.metadata;
+    let language = metadata
+        .language_info
+        .and_then(|info| info.name)
+        .or_else(|| metadata.kernelspec.and_then(|spec| spec.language))?;
+    language_from_language_id(&language)
+}
+
+// Reports the kernel language of `.ipynb` files instead of JSON.
+pub async fn detect_notebook_language(file_path: Option<&str>) -> Option<String> {
+    let path = Path::new(file_path?);
+    let extension = path.extension()?.to_str()?;
+    if !extension.eq_ignore_ascii_case("ipynb") {
+        return None;
+    }
+
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    if metadata.len() > NOTEBOOK_PARSE_LIMIT {
+        return None;
+    }
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    language_from_notebook(&content)
+}
+
+// Signals for the content classifier, consulted only when every name based
+// rule came up empty. Each distinct signal present adds one point.
+const CONTENT_SIGNALS: &[(&str, &[&str])] = &[
+    (
+        "Python",
+        &["def ", "import ", "self.", "elif ", "__name__", "print("],
+    ),
+    (
+        "JavaScript",
+        &[
+            "function ",
+            "const ",
+            "=> ",
+            "console.log",
+            "require(",
+            "module.exports",
+        ],
+    ),
+    (
+        "TypeScript",
+        &[
+            "interface ",
+            ": string",
+            ": number",
+            "export type ",
+            "as const",
+        ],
+    ),
+    (
+        "Rust",
+        &[
+            "fn ",
+            "let mut ",
+            "impl ",
+            "pub fn ",
+            "use std::",
+            "-> Result<",
+        ],
+    ),
+    ("Go", &["package ", "func ", ":= ", "fmt.", "import ("]),
+    (
+        "Java",
+        &[
+            "public class ",
+            "System.out",
+            "import java.",
+            "private final ",
+            "@Override",
+        ],
+    ),
+    (
+        "C",
+        &[
+            "#include <stdio.h>",
+            "int main(",
+            "printf(",
+            "malloc(",
+            "typedef struct",
+        ],
+    ),
+    (
+        "C++",
+        &[
+            "std::",
+            "#include <iostream>",
+            "namespace ",
+            "template<",
+            "cout <<",
+        ],
+    ),
+    (
+        "Ruby",
+        &["puts ", "require '", "do |", "attr_accessor", "\nend"],
+    ),
+    (
+        "Shell Script",
+        &["echo ", "\nfi", "then\n", "$(", "esac", "export "],
+    ),
+    (
+        "PHP",
+        &["<?php", "$this->", "echo $", "function __construct"],
+    ),
+    (
+        "HTML",
+        &["<!doctype html", "<html", "<div", "<body", "</head>"],
+    ),
+    ("XML", &["<?xml", "xmlns", "</"]),
+    (
+        "SQL",
+        &["select ", " from ", "create table", "insert into", "where "],
+    ),
+    ("Markdown", &["\n# ", "\n## ", "```", "](", "\n- "]),
+    ("Lua", &["local ", "function ", " then", "\nend", "~="]),
+    ("Perl", &["my $", "use strict", "use warnings", "sub "]),
+    ("ini", &["\n[", "]\n", "="]),
+    (
+        "Dockerfile",
+        &["from ", "\nrun ", "\ncopy ", "\nworkdir ", "\ncmd "],
+    ),
+];
+
+// Keyword classifier for files nothing else recognizes. Matching is case
+// insensitive; ties are treated as inconclusive.
+pub fn classify_content(sample: &str) -> Option<&'static str> {
+    let sample = format!("\n{}", sample.to_lowercase());
+    let mut scores: Vec<(&'static str, usize)> = CONTENT_SIGNALS
+        .iter()
+        .map(|(language, signals)| {
+            let score = signals
+                .iter()
+                .filter(|signal| sample.contains(&signal.to_lowercase()))
+                .count();
+            (*language, score)
+        })
+        .collect();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    match scores.as_slice() {
+        [(language, best), (_, second), ..]
+            if *best >= CONTENT_SAMPLE_MIN_SCORE && best > second =>
+        {
+            Some(language)
+        }
+        _ => None,
+    }
+}
+
+pub async fn detect_language_from_content(file_path: Option<&str>) -> Option<String> {
+    let head = read_file_head(Path::new(file_path?), CONTENT_SAMPLE_SIZE).await?;
+    classify_content(&head).map(|language| language.to_string())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LanguageSettings {
+    pub language_map: HashMap<String, String>,
+    pub content_sampling: bool,
+}
+
+impl LanguageSettings {
+    pub fn from_config(config: &ZiitConfig) -> Self {
+        Self {
+            language_map: config.language_map.clone().unwrap_or_default(),
+            content_sampling: config.content_sampling.unwrap_or(false),
+        }
+    }
+}
+
+// Template languages wrap a host language named by an inner extension
+// (`show.html.erb`); component languages embed script blocks.
+const TEMPLATE_LANGUAGES: &[&str] = &[
+    "Blade",
+    "EJS",
+    "ERB",
+    "Handlebars",
+    "Jinja",
+    "Liquid",
+    "Mako",
+    "Smarty",
+    "Twig",
+];
+const COMPONENT_LANGUAGES: &[&str] = &["Astro", "Svelte", "Vue.js"];
+
+fn is_composite_language(language: &str) -> bool {
+    TEMPLATE_LANGUAGES.contains(&language) || COMPONENT_LANGUAGES.contains(&language)
+}
+
+pub fn template_embedded_language(file_name: &str, language: &str) -> Option<&'static str> {
+    let file_name = file_name.to_lowercase();
+    let parts: Vec<&str> = file_name.split('.').collect();
+    let inner = parts
+        .iter()
+        .take(parts.len().saturating_sub(1))
+        .skip(1)
+        .rev()
+        .filter_map(|part| LANGUAGE_DATABASE.by_extension.get(&format!(".{}", part)))
+        .map(|language| language.as_str())
+        .find(|inner| !is_composite_language(inner));
+    // Blade templates are always HTML even though they end in `.php`.
+    inner.or((language == "Blade").then_some("HTML"))
+}
+
+pub fn component_script_language(head: &str) -> Option<&'static str> {
+    let head = head.to_lowercase();
+    let script_start = head.find("<script")?;
+    let script_tag = &head[script_start..];
+    let script_tag = &script_tag[..script_tag.find('>').unwrap_or(script_tag.len())];
+    if script_tag.contains("lang=\"ts\"")
+        || script_tag.contains("lang='ts'")
+        || script_tag.contains("lang=\"typescript\"")
+    {
+        Some("TypeScript")
+    } else {
+        Some("JavaScript")
+    }
+}
+
+// Only computed for composite languages: template hosts come from the file
+// name, component scripts from a bounded read of the file header.
+pub async fn detect_embedded_language(file_path: Option<&str>, language: &str) -> Option<String> {
+    let path = Path::new(file_path?);
+    if TEMPLATE_LANGUAGES.contains(&language) {
+        let file_name = path.file_name()?.to_str()?;
+        return template_embedded_language(file_name, language).map(|inner| inner.to_string());
+    }
+    if COMPONENT_LANGUAGES.contains(&language) {
+        let head = read_file_head(path, HEURISTIC_READ_LIMIT).await?;
+        return component_script_language(&head).map(|inner| inner.to_string());
+    }
+    None
+}
+
+// Detection order: user languageMap, notebook kernels, modelines, composite
+// template and component files, the client's languageId, content heuristics
+// for ambiguous extensions, the built-in tables, shebangs, then (if enabled)
+// content sampling.
+pub async fn resolve_language(
+    file_path: Option<&str>,
+    language_id: Option<&str>,
+    settings: &LanguageSettings,
+) -> Option<DetectedLanguage> {
+    let name = resolve_language_name(file_path, language_id, settings).await?;
+    let category = language_category(&name);
+    let embedded_language = detect_embedded_language(file_path, &name).await;
+    Some(DetectedLanguage {
+        name,
+        category,
+        embedded_language,
+    })
+}
+
+async fn resolve_language_name(
+    file_path: Option<&str>,
+    language_id: Option<&str>,
+    settings: &LanguageSettings,
+) -> Option<String> {
+    if let Some(language) = language_from_map(file_path, &settings.language_map) {
+        return Some(language);
+    }
+    if let Some(language) = detect_notebook_language(file_path).await {
+        return Some(language);
+    }
+    if let Some(language) = detect_language_from_modeline(file_path).await {
+        return Some(language);
+    }
+    // Clients often report the host language (`php` for `.blade.php`).
+    if let Some(language) = detect_language(file_path).filter(|name| is_composite_language(name)) {
+        return Some(language);
+    }
+    if let Some(language) = language_id.and_then(language_from_language_id) {
+        return Some(language);
+    }
+    if let Some(language) = disambiguate_language(file_path).await {
+        return Some(language);
+    }
+    if let Some(language) = detect_language(file_path) {
+        return Some(language);
+    }
+    if let Some(language) = detect_language_from_shebang(file_path).await {
+        return Some(language);
+    }
+    if settings.content_sampling {
+        return detect_language_from_content(file_path).await;
+    }
+    None
+}
+
+pub fn extract_file_name(file_path: Option<&str>) -> Option<String> {
+    let path = file_path?;
+    let path = Path::new(path);
+    path.file_name()?.to_str().map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language(Some("test.rs")), Some("Rust".to_string()));
+        assert_eq!(
+            detect_language(Some("test.js")),
+            Some("JavaScript".to_string())
+        );
+        assert_eq!(detect_language(Some("test.py")), Some("Python".to_string()));
+        assert_eq!(
+            detect_language(Some("/path/to/file.go")),
+            Some("Go".to_string())
+        );
+        assert_eq!(detect_language(Some("unknown.xyz")), None);
+    }
+
+    #[test]
+    fn test_language_table_extensions_are_unambiguous() {
+        let entries: Vec<LanguageEntry> = serde_json::from_str(LANGUAGES_JSON).unwrap();
+        assert!(entries.len() > 200);
+
+        let mut owners: HashMap<String, &str> = HashMap::new();
+        for entry in &entries {
+            for extension in &entry.extensions {
+                if let Some(owner) = owners.insert(extension.to_lowercase(), &entry.name) {
+                    panic!(
+                        "{} is listed by both {} and {}",
+                        extension, owner, entry.name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_language_category() {
+        assert_eq!(
+            language_category("Rust"),
+            Some(LanguageCategory::Programming)
+        );
+        assert_eq!(language_category("yaml"), Some(LanguageCategory::Config));
+        assert_eq!(language_category("Markdown"), Some(LanguageCategory::Prose));
+        assert_eq!(language_category("CSV"), Some(LanguageCategory::Data));
+        assert_eq!(language_category("AcmeDSL"), None);
+    }
+
+    #[test]
+    fn test_detect_language_from_file_name() {
+        assert_eq!(
+            detect_language(Some("/repo/Dockerfile")),
+            Some("Dockerfile".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("/repo/CMakeLists.txt")),
+            Some("CMake".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("/home/user/.bashrc")),
+            Some("Shell Script".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("/repo/go.sum")),
+            Some("Go".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("/repo/Gemfile")),
+            Some("Ruby".to_string())
+        );
+        assert_eq!(detect_language(Some("/repo/LICENSE")), None);
+    }
+
+    #[test]
+    fn test_language_from_language_id() {
+        assert_eq!(language_from_language_id("rust"), Some("Rust".to_string()));
+        assert_eq!(
+            language_from_language_id("typescriptreact"),
+            Some("TSX".to_string())
+        );
+        assert_eq!(
+            language_from_language_id("shell script"),
+            Some("Shell Script".to_string())
+        );
+        assert_eq!(
+            language_from_language_id("Unison"),
+            Some("Unison".to_string())
+        );
+        assert_eq!(language_from_language_id("plaintext"), None);
+        assert_eq!(language_from_language_id("go sum"), Some("Go".to_string()));
+        assert_eq!(
+            language_from_language_id("html+erb"),
+            Some("ERB".to_string())
+        );
+        assert_eq!(
+            language_from_language_id("jsonc"),
+            Some("JSONC".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_language_compound_extensions() {
+        assert_eq!(
+            detect_language(Some("types/index.d.ts")),
+            Some("TypeScript".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("src/App.spec.tsx")),
+            Some("TSX".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("config.schema.json")),
+            Some("JSON".to_string())
+        );
+        assert_eq!(
+            detect_language(Some("main.tf.json")),
+            Some("Terraform".to_string())
+        );
+        assert_eq!(detect_language(Some("release.tar.gz")), None);
+    }
+
+    #[test]
+    fn test_language_from_map() {
+        let mut language_map = HashMap::new();
+        language_map.insert("acme".to_string(), "AcmeDSL".to_string());
+        language_map.insert("*.gen.go".to_string(), "Generated Go".to_string());
+        language_map.insert(".go".to_string(), "Golang".to_string());
+        language_map.insert("Tiltfile".to_string(), "Tilt".to_string());
+
+        assert_eq!(
+            language_from_map(Some("/src/rules.acme"), &language_map),
+            Some("AcmeDSL".to_string())
+        );
+        assert_eq!(
+            language_from_map(Some("/src/api.gen.go"), &language_map),
+            Some("Generated Go".to_string())
+        );
+        assert_eq!(
+            language_from_map(Some("/src/main.go"), &language_map),
+            Some("Golang".to_string())
+        );
+        assert_eq!(
+            language_from_map(Some("/src/Tiltfile"), &language_map),
+            Some("Tilt".to_string())
+        );
+        assert_eq!(language_from_map(Some("/src/main.rs"), &language_map), None);
+    }
+
+    #[test]
+    fn test_language_from_heuristics() {
+        assert_eq!(
+            language_from_heuristics("m", "#import <Foundation/Foundation.h>\n"),
+            Some("Objective-C")
+        );
+        assert_eq!(
+            language_from_heuristics("m", "function y = square(x)\n  y = x.^2;\nend\n"),
+            Some("MATLAB")
+        );
+        assert_eq!(
+            language_from_heuristics("v", "module counter(input wire clk);\nendmodule\n"),
+            Some("Verilog")
+        );
+        assert_eq!(
+            language_from_heuristics("v", "Theorem plus_O_n : forall n, 0 + n = n.\nProof.\n"),
+            Some("Coq")
+        );
+        assert_eq!(
+            language_from_heuristics("pl", "parent(tom, bob).\nancestor(X, Y) :- parent(X, Y).\n"),
+            Some("Prolog")
+        );
+        assert_eq!(
+            language_from_heuristics("pl", "use strict;\nmy $name = 'x';\n"),
+            Some("Perl")
+        );
+        assert_eq!(
+            language_from_heuristics(
+                "sql",
+                "CREATE FUNCTION f() RETURNS void AS $$ $$ LANGUAGE plpgsql;"
+            ),
+            Some("PLpgSQL")
+        );
+        assert_eq!(
+            language_from_heuristics("sql", "SELECT 1;\nGO\n"),
+            Some("TSQL")
+        );
+        assert_eq!(language_from_heuristics("sql", "SELECT 1;"), None);
+    }
+
+    #[tokio::test]
+    async fn test_disambiguate_language() {
+        let dir = std::env::temp_dir().join(format!("ziit-heuristics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("solve.m");
+        std::fs::write(&file, "% solver\nfunction x = solve(a)\n").unwrap();
+
+        assert_eq!(
+            disambiguate_language(file.to_str()).await,
+            Some("MATLAB".to_string())
+        );
+        assert_eq!(
+            disambiguate_language(Some("/nonexistent/main.rs")).await,
+            None
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_language_from_modeline() {
+        assert_eq!(
+            language_from_modeline("# vim: ft=yaml\nkey: value\n"),
+            Some("YAML")
+        );
+        assert_eq!(
+            language_from_modeline("x = 1\n/* vim: set filetype=javascript : */\n"),
+            Some("JavaScript")
+        );
+        assert_eq!(
+            language_from_modeline("# -*- mode: ruby -*-\nputs 1\n"),
+            Some("Ruby")
+        );
+        assert_eq!(
+            language_from_modeline("#!/bin/sh\n# -*- shell-script -*-\n"),
+            Some("Shell Script")
+        );
+        assert_eq!(language_from_modeline("# -*- coding: utf-8 -*-\n"), None);
+        assert_eq!(language_from_modeline("let env: ft=yaml"), None);
+    }
+
+    #[tokio::test]
+    async fn test_detect_language_from_modeline_tail() {
+        let dir = std::env::temp_dir().join(format!("ziit-modeline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("settings.conf");
+        let mut content = "x\n".repeat(2000);
+        content.push_str("# vim: ft=toml\n");
+        std::fs::write(&file, content).unwrap();
+
+        assert_eq!(
+            detect_language_from_modeline(file.to_str()).await,
+            Some("TOML".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_language_from_notebook() {
+        let python = r#"{"cells": [], "metadata": {"kernelspec": {"language": "python", "name": "python3"}, "language_info": {"name": "python", "version": "3.12.1"}}, "nbformat": 4}"#;
+        let julia = r#"{"cells": [], "metadata": {"kernelspec": {"language": "julia", "name": "julia-1.10"}}}"#;
+        let r = r#"{"metadata": {"language_info": {"name": "R"}}}"#;
+
+        assert_eq!(language_from_notebook(python), Some("Python".to_string()));
+        assert_eq!(language_from_notebook(julia), Some("Julia".to_string()));
+        assert_eq!(language_from_notebook(r), Some("R".to_string()));
+        assert_eq!(language_from_notebook(r#"{"cells": []}"#), None);
+        assert_eq!(language_from_notebook("not json"), None);
+    }
+
+    #[test]
+    fn test_template_embedded_language() {
+        assert_eq!(
+            detect_language(Some("app/views/show.html.erb")),
+            Some("ERB".to_string())
+        );
+        assert_eq!(
+            template_embedded_language("show.html.erb", "ERB"),
+            Some("HTML")
+        );
+        assert_eq!(
+            template_embedded_language("index.js.erb", "ERB"),
+            Some("JavaScript")
+        );
+        assert_eq!(
+            template_embedded_language("welcome.blade.php", "Blade"),
+            Some("HTML")
+        );
+        assert_eq!(template_embedded_language("layout.erb", "ERB"), None);
+    }
+
+    #[test]
+    fn test_component_script_language() {
+        assert_eq!(
+            component_script_language("<script lang=\"ts\">\nlet x: number = 1;\n</script>"),
+            Some("TypeScript")
+        );
+        assert_eq!(
+            component_script_language("<template></template>\n<script setup>\n</script>"),
+            Some("JavaScript")
+        );
+        assert_eq!(component_script_language("<h1>Hello</h1>"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_language_prefers_composite_over_language_id() {
+        let settings = LanguageSettings::default();
+        let language = resolve_language(Some("/app/welcome.blade.php"), Some("php"), &settings)
+            .await
+            .unwrap();
+        assert_eq!(language.name, "Blade");
+        assert_eq!(language.embedded_language, Some("HTML".to_string()));
+    }
+
+    #[test]
+    fn test_classify_content() {
+        assert_eq!(
+            classify_content("import os\n\ndef main():\n    print(os.getcwd())\n"),
+            Some("Python")
+        );
+        assert_eq!(
+            classify_content(
+                "package main\n\nimport (\n\t\"fmt\"\n)\n\nfunc main() {\n\tfmt.Println(1)\n}\n"
+            ),
+            Some("Go")
+        );
+        assert_eq!(
+            classify_content("SELECT id FROM users WHERE active = 1;"),
+            Some("SQL")
+        );
+        assert_eq!(classify_content("hello world"), None);
+    }
+
+    #[tokio::test]
+    async fn test_content_sampling_is_opt_in() {
+        let dir = std::env::temp_dir().join(format!("ziit-sampling-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("task");
+        std::fs::write(&file, "use strict;\nuse warnings;\nmy $x = 1;\n").unwrap();
+
+        let mut settings = LanguageSettings::default();
+        assert_eq!(resolve_language(file.to_str(), None, &settings).await, None);
+        settings.content_sampling = true;
+        assert_eq!(
+            resolve_language(file.to_str(), None, &settings)
+                .await
+                .map(|language| language.name),
+            Some("Perl".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_language_from_shebang() {
+        assert_eq!(
+            language_from_shebang("#!/usr/bin/env python3"),
+            Some("Python")
+        );
+        assert_eq!(
+            language_from_shebang("#!/bin/bash -e"),
+            Some("Shell Script")
+        );
+        assert_eq!(
+            language_from_shebang("#!/usr/bin/env -S deno run --allow-net"),
+            Some("TypeScript")
+        );
+        assert_eq!(
+            language_from_shebang("#!/usr/bin/python3.12"),
+            Some("Python")
+        );
+        assert_eq!(language_from_shebang("# just a comment"), None);
+        assert_eq!(language_from_shebang("#!/usr/bin/unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn test_detect_language_from_shebang() {
+        let dir = std::env::temp_dir().join(format!("ziit-shebang-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("deploy");
+        std::fs::write(&script, "#!/usr/bin/env ruby\nputs 'hi'\n").unwrap();
+
+        assert_eq!(
+            detect_language_from_shebang(script.to_str()).await,
+            Some("Ruby".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_extract_file_name() {
+        assert_eq!(
+            extract_file_name(Some("/path/to/file.rs")),
+            Some("file.rs".to_string())
+        );
+        assert_eq!(
+            extract_file_name(Some("file.rs")),
+            Some("file.rs".to_string())
+        );
+        assert_eq!(extract_file_name(None), None);
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::fs;
+
+// A lease that has not been renewed for this long is treated as released, so
+// a closed or crashed editor does not silence the others.
+const LEASE_TTL_SECONDS: i64 = 5 * 60;
+
+// Shared with the other Ziit editor plugins: whichever editor the user works
+// in writes itself into `active_editor.json` in the Ziit config directory,
+// and the others stop reporting while that lease is fresh.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub editor: String,
+    pub pid: u32,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EditorLease {
+    path: PathBuf,
+    editor: String,
+    pid: u32,
+}
+
+impl EditorLease {
+    pub fn new(path: PathBuf, editor: &str, pid: u32) -> Self {
+        Self {
+            path,
+            editor: editor.to_string(),
+            pid,
+        }
+    }
+
+    pub async fn claim(&self, now: DateTime<Utc>) -> Result<()> {
+        let lease = Lease {
+            editor: self.editor.clone(),
+            pid: self.pid,
+            updated_at: now,
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let partial = self.path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string(&lease)?).await?;
+        fs::rename(&partial, &self.path).await?;
+        Ok(())
+    }
+
+    // The lease of another editor that is still active, if any. A missing or
+    // unreadable file means nobody holds it.
+    pub async fn held_elsewhere(&self, now: DateTime<Utc>) -> Option<Lease> {
+        let data = fs::read_to_string(&self.path).await.ok()?;
+        let lease: Lease = serde_json::from_str(&data).ok()?;
+        let ours = lease.editor == self.editor && lease.pid == self.pid;
+        let fresh = now - lease.updated_at < TimeDelta::seconds(LEASE_TTL_SECONDS);
+        (!ours && fresh).then_some(lease)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lease_is_held_by_the_last_claimant() {
+        let path = std::env::temp_dir().join(format!(
+            "ziit-lease-{}/active_editor.json",
+            std::process::id()
+        ));
+        std::fs::remove_file(&path).ok();
+        let zed = EditorLease::new(path.clone(), "Zed", 1);
+        let vscode = EditorLease::new(path, "VS Code", 2);
+        let now = Utc::now();
+
+        assert_eq!(zed.held_elsewhere(now).await, None);
+        vscode.claim(now).await.unwrap();
+        assert_eq!(
+            zed.held_elsewhere(now).await.map(|lease| lease.editor),
+            Some("VS Code".to_string())
+        );
+        assert_eq!(vscode.held_elsewhere(now).await, None);
+
+        let later = now + TimeDelta::seconds(LEASE_TTL_SECONDS);
+        assert_eq!(zed.held_elsewhere(later).await, None);
+    }
+}
@@ -0,0 +1,26 @@
+pub mod api;
+pub mod clock;
+pub mod commands;
+pub mod config;
+pub mod export;
+pub mod file_classification;
+pub mod file_lock;
+pub mod heartbeat;
+pub mod idle;
+pub mod import;
+pub mod language;
+pub mod lease;
+pub mod metrics;
+pub mod mirror;
+pub mod project;
+pub mod project_map;
+pub mod recap;
+pub mod redact;
+pub mod report;
+pub mod request_log;
+pub mod spill;
+pub mod stats;
+pub mod telemetry;
+pub mod throttle;
+pub mod timezone;
+pub mod vcs;
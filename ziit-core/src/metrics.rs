@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Upper bounds in seconds, Prometheus' defaults minus the sub-50ms buckets a
+// request over the internet never lands in.
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+static HEARTBEATS_SENT: AtomicU64 = AtomicU64::new(0);
+static HEARTBEATS_QUEUED: AtomicU64 = AtomicU64::new(0);
+static HEARTBEATS_DROPPED: AtomicU64 = AtomicU64::new(0);
+static ACTIVITY_DROPPED: AtomicU64 = AtomicU64::new(0);
+static SYNC_FAILURES: AtomicU64 = AtomicU64::new(0);
+static QUEUE_DEPTH: AtomicU64 = AtomicU64::new(0);
+static API_LATENCY: Mutex<BTreeMap<&'static str, Histogram>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+pub fn record_sent(count: usize) {
+    HEARTBEATS_SENT.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+pub fn record_queued() {
+    HEARTBEATS_QUEUED.fetch_add(1, Ordering::Relaxed);
+}
+
+// Heartbeats that are lost for good: over the rate limit, expired or
+// unreadable in the offline queue.
+pub fn record_dropped(count: usize) {
+    HEARTBEATS_DROPPED.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+pub fn record_activity_dropped() {
+    ACTIVITY_DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_sync_failure() {
+    SYNC_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn set_queue_depth(depth: usize) {
+    QUEUE_DEPTH.store(depth as u64, Ordering::Relaxed);
+}
+
+pub fn observe_api_latency(endpoint: &'static str, elapsed: Duration) {
+    API_LATENCY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(endpoint)
+        .or_default()
+        .observe(elapsed.as_secs_f64());
+}
+
+// Prometheus text exposition format, version 0.0.4.
+pub fn render() -> String {
+    let mut output = String::new();
+    let counters = [
+        (
+            "ziit_heartbeats_sent_total",
+            "Heartbeats accepted by the server.",
+            &HEARTBEATS_SENT,
+        ),
+        (
+            "ziit_heartbeats_queued_total",
+            "Heartbeats put in the offline queue.",
+            &HEARTBEATS_QUEUED,
+        ),
+        (
+            "ziit_heartbeats_dropped_total",
+            "Heartbeats dropped by the rate limiter or lost from the offline queue.",
+            &HEARTBEATS_DROPPED,
+        ),
+        (
+            "ziit_activity_events_dropped_total",
+            "Editor events dropped because the activity queue was full.",
+            &ACTIVITY_DROPPED,
+        ),
+        (
+            "ziit_sync_failures_total",
+            "Failed attempts to sync the offline queue.",
+            &SYNC_FAILURES,
+        ),
+    ];
+    for (name, help, counter) in counters {
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        let _ = writeln!(output, "# TYPE {} counter", name);
+        let _ = writeln!(output, "{} {}", name, counter.load(Ordering::Relaxed));
+    }
+
+    let _ = writeln!(
+        output,
+        "# HELP ziit_offline_queue_depth Heartbeats waiting in the offline queue."
+    );
+    let _ = writeln!(output, "# TYPE ziit_offline_queue_depth gauge");
+    let _ = writeln!(
+        output,
+        "ziit_offline_queue_depth {}",
+        QUEUE_DEPTH.load(Ordering::Relaxed)
+    );
+
+    let name = "ziit_api_request_duration_seconds";
+    let _ = writeln!(
+        output,
+        "# HELP {} Latency of requests to the Ziit API.",
+        name
+    );
+    let _ = writeln!(output, "# TYPE {} histogram", name);
+    let histograms = API_LATENCY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    for (endpoint, histogram) in histograms {
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+            let _ = writeln!(
+                output,
+                "{}_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                name, endpoint, bound, count
+            );
+        }
+        let _ = writeln!(
+            output,
+            "{}_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}",
+            name, endpoint, histogram.count
+        );
+        let _ = writeln!(
+            output,
+            "{}_sum{{endpoint=\"{}\"}} {}",
+            name, endpoint, histogram.sum
+        );
+        let _ = writeln!(
+            output,
+            "{}_count{{endpoint=\"{}\"}} {}",
+            name, endpoint, histogram.count
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(0.2);
+        histogram.observe(3.0);
+        assert_eq!(histogram.buckets, [0, 0, 1, 1, 1, 1, 2, 2]);
+        assert_eq!(histogram.count, 2);
+    }
+
+    #[test]
+    fn test_render_exposes_latency_per_endpoint() {
+        observe_api_latency("test", Duration::from_millis(80));
+        let output = render();
+        assert!(output.contains("# TYPE ziit_heartbeats_sent_total counter"));
+        assert!(output
+            .contains("ziit_api_request_duration_seconds_bucket{endpoint=\"test\",le=\"0.1\"} 1"));
+        assert!(output.contains("ziit_api_request_duration_seconds_count{endpoint=\"test\"} 1"));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::api::{
+    fetch_heartbeat_schema_request, send_batch_heartbeats_request, send_heartbeat_request,
+};
+use crate::config::HeartbeatSchema;
+use crate::heartbeat::{append_heartbeat_line, parse_heartbeat_lines, to_jsonl, Heartbeat};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::sync::Mutex;
+
+const MIRROR_QUEUE_FILE_NAME: &str = "offline_heartbeats.jsonl";
+
+// An additional server that receives a copy of every heartbeat, for example
+// a self-hosted instance while migrating away from ziit.app.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MirrorTarget {
+    #[serde(rename = "baseUrl")]
+    pub base_url: String,
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MirrorStatus {
+    #[serde(rename = "baseUrl")]
+    pub base_url: String,
+    pub online: bool,
+    pub queued: usize,
+}
+
+// Each mirror has its own online flag, schema and offline queue, so one
+// unreachable server never holds back the others or the primary one.
+#[derive(Debug)]
+struct Mirror {
+    target: MirrorTarget,
+    queue_path: PathBuf,
+    queue: VecDeque<Heartbeat>,
+    online: bool,
+    schema: Option<HeartbeatSchema>,
+}
+
+impl Mirror {
+    async fn load(dir: &std::path::Path, target: MirrorTarget) -> Self {
+        let queue_path = dir
+            .join(directory_name(&target.base_url))
+            .join(MIRROR_QUEUE_FILE_NAME);
+        let data = fs::read_to_string(&queue_path).await.unwrap_or_default();
+        let (queue, unreadable) = parse_heartbeat_lines(&data);
+        if unreadable > 0 {
+            log::warn!(
+                "Dropped {} unreadable queued heartbeats for {}",
+                unreadable,
+                target.base_url
+            );
+        }
+
+        Self {
+            target,
+            queue_path,
+            queue: queue.into(),
+            online: true,
+            schema: None,
+        }
+    }
+
+    async fn schema(&mut self) -> HeartbeatSchema {
+        if let Some(schema) = self.schema {
+            return schema;
+        }
+        let schema =
+            match fetch_heartbeat_schema_request(&self.target.base_url, &self.target.api_key).await
+            {
+                Ok(version) if version >= 2 => HeartbeatSchema::V2,
+                Ok(_) => HeartbeatSchema::V1,
+                Err(e) if e.to_string().contains("HTTP") => HeartbeatSchema::V1,
+                Err(_) => return HeartbeatSchema::V1,
+            };
+        self.schema = Some(schema);
+        schema
+    }
+
+    async fn send(&mut self, heartbeat: Heartbeat) {
+        if self.online {
+            let schema = self.schema().await;
+            let payload = heartbeat.clone().into_schema(schema);
+            match send_heartbeat_request(&self.target.base_url, &self.target.api_key, payload).await
+            {
+                Ok(()) => return,
+                Err(e) => {
+                    log::warn!("Mirror {} went offline: {}", self.target.base_url, e);
+                    self.online = false;
+                }
+            }
+        }
+
+        if let Some(dir) = self.queue_path.parent() {
+            fs::create_dir_all(dir).await.ok();
+        }
+        if let Err(e) = append_heartbeat_line(&self.queue_path, &heartbeat).await {
+            log::warn!(
+                "Could not queue heartbeat for {}: {}",
+                self.target.base_url,
+                e
+            );
+        }
+        self.queue.push_back(heartbeat);
+    }
+
+    // Doubles as the reconnection probe, like the primary offline sync.
+    async fn sync(&mut self) -> Result<()> {
+        if self.queue.is_empty() {
+            return Ok(());
+        }
+        let schema = self.schema().await;
+        let batch: Vec<_> = self
+            .queue
+            .iter()
+            .map(|heartbeat| heartbeat.clone().into_schema(schema))
+            .collect();
+
+        match send_batch_heartbeats_request(&self.target.base_url, &self.target.api_key, batch)
+            .await
+        {
+            Ok(()) => {
+                log::info!(
+                    "Synced {} queued heartbeats to {}",
+                    self.queue.len(),
+                    self.target.base_url
+                );
+                self.queue.clear();
+                self.online = true;
+                fs::write(&self.queue_path, to_jsonl(&self.queue)?).await?;
+                Ok(())
+            }
+            Err(e) => {
+                self.online = false;
+                Err(e)
+            }
+        }
+    }
+
+    fn status(&self) -> MirrorStatus {
+        MirrorStatus {
+            base_url: self.target.base_url.clone(),
+            online: self.online,
+            queued: self.queue.len(),
+        }
+    }
+}
+
+fn directory_name(base_url: &str) -> String {
+    base_url
+        .trim_end_matches('/')
+        .split("://")
+        .last()
+        .unwrap_or(base_url)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct Mirrors {
+    dir: PathBuf,
+    mirrors: Mutex<Vec<Mirror>>,
+}
+
+impl Mirrors {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            mirrors: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Follows the config: new targets are loaded with their queue, removed
+    // ones are dropped while their queue file stays on disk.
+    async fn refresh(&self, mirrors: &mut Vec<Mirror>, targets: &[MirrorTarget]) {
+        mirrors.retain(|mirror| targets.contains(&mirror.target));
+        for target in targets {
+            if !mirrors.iter().any(|mirror| &mirror.target == target) {
+                mirrors.push(Mirror::load(&self.dir, target.clone()).await);
+            }
+        }
+    }
+
+    pub async fn send(&self, targets: &[MirrorTarget], heartbeat: &Heartbeat) {
+        let mut mirrors = self.mirrors.lock().await;
+        self.refresh(&mut mirrors, targets).await;
+        for mirror in mirrors.iter_mut() {
+            mirror.send(heartbeat.clone()).await;
+        }
+    }
+
+    pub async fn sync(&self, targets: &[MirrorTarget]) {
+        let mut mirrors = self.mirrors.lock().await;
+        self.refresh(&mut mirrors, targets).await;
+        for mirror in mirrors.iter_mut() {
+            if let Err(e) = mirror.sync().await {
+                log::debug!("Could not sync mirror {}: {}", mirror.target.base_url, e);
+            }
+        }
+    }
+
+    pub async fn statuses(&self) -> Vec<MirrorStatus> {
+        self.mirrors
+            .lock()
+            .await
+            .iter()
+            .map(Mirror::status)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_name() {
+        assert_eq!(
+            directory_name("https://ziit.example.com:8443/"),
+            "ziit_example_com_8443"
+        );
+        assert_eq!(directory_name("http://localhost"), "localhost");
+    }
+}
@@ -0,0 +1,212 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROJECT_MAP_FILE_NAME: &str = "project-map.json";
+
+// `paths` maps directory prefixes to project names (longest prefix wins),
+// `remotes` maps remote URL patterns (`*` wildcards) to project names and
+// `tags` maps project names to the tags attached to their heartbeats.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct ProjectMap {
+    #[serde(default)]
+    pub paths: HashMap<String, String>,
+    #[serde(default)]
+    pub remotes: HashMap<String, String>,
+    #[serde(default)]
+    pub tags: HashMap<String, Vec<String>>,
+}
+
+impl ProjectMap {
+    pub fn project_for_path(&self, file_path: &str) -> Option<String> {
+        let path = Path::new(file_path);
+        self.paths
+            .iter()
+            .map(|(prefix, project)| (expand_home(prefix), project))
+            .filter(|(prefix, _)| path.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.components().count())
+            .map(|(_, project)| project.clone())
+    }
+
+    pub fn tags_for(&self, project: &str) -> Vec<String> {
+        self.tags.get(project).cloned().unwrap_or_default()
+    }
+
+    pub fn project_for_remote(&self, remote_url: &str) -> Option<String> {
+        let normalized = normalize_remote_url(remote_url);
+        self.remotes
+            .iter()
+            .filter(|(pattern, _)| {
+                glob_match(pattern, remote_url.trim()) || glob_match(pattern, &normalized)
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, project)| project.clone())
+    }
+}
+
+fn get_project_map_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(xdg_config_home) = std::env::var("XDG_CONFIG_HOME") {
+        if !xdg_config_home.is_empty() {
+            paths.push(
+                PathBuf::from(xdg_config_home)
+                    .join("ziit")
+                    .join(PROJECT_MAP_FILE_NAME),
+            );
+        }
+    }
+    if let Some(home_dir) = dirs::home_dir() {
+        paths.push(
+            home_dir
+                .join(".config")
+                .join("ziit")
+                .join(PROJECT_MAP_FILE_NAME),
+        );
+        paths.push(home_dir.join(".ziit").join(PROJECT_MAP_FILE_NAME));
+    }
+    paths
+}
+
+pub fn load_project_map() -> ProjectMap {
+    for path in get_project_map_paths() {
+        if !path.exists() {
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<ProjectMap>(&content) {
+                Ok(map) => {
+                    log::debug!("Loaded project map from {:?}", path);
+                    return map;
+                }
+                Err(e) => log::warn!("Could not parse project map {:?}: {}", path, e),
+            },
+            Err(e) => log::warn!("Could not read project map {:?}: {}", path, e),
+        }
+    }
+    ProjectMap::default()
+}
+
+// Turns `git@host:owner/repo.git` and `ssh://git@host:22/owner/repo.git`
+// into `host/owner/repo`.
+pub fn normalize_remote_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let (without_scheme, had_scheme) = match url.split_once("://") {
+        Some((_, rest)) => (rest, true),
+        None => (url, false),
+    };
+    let without_user = match without_scheme.split_once('@') {
+        Some((_, rest)) => rest,
+        None => without_scheme,
+    };
+    let normalized = if had_scheme {
+        match without_user.split_once('/') {
+            Some((host, path)) => {
+                let host = host.split(':').next().unwrap_or(host);
+                format!("{}/{}", host, path)
+            }
+            None => without_user.to_string(),
+        }
+    } else {
+        without_user.replacen(':', "/", 1)
+    };
+    normalized.trim_end_matches('/').to_string()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home_dir) = dirs::home_dir() {
+            return home_dir.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let mut remaining = text;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            match remaining.strip_prefix(part) {
+                Some(rest) => remaining = rest,
+                None => return false,
+            }
+        } else if i == parts.len() - 1 {
+            return remaining.ends_with(part);
+        } else {
+            match remaining.find(part) {
+                Some(index) => remaining = &remaining[index + part.len()..],
+                None => return false,
+            }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_remote_url() {
+        assert_eq!(
+            normalize_remote_url("git@github.com:acme/api.git"),
+            "github.com/acme/api"
+        );
+        assert_eq!(
+            normalize_remote_url("https://github.com/acme/api"),
+            "github.com/acme/api"
+        );
+        assert_eq!(
+            normalize_remote_url("ssh://git@gitlab.example.com:2222/group/sub/api.git"),
+            "gitlab.example.com/group/sub/api"
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("github.com/acme/*", "github.com/acme/api"));
+        assert!(glob_match("*/api", "github.com/acme/api"));
+        assert!(glob_match("github.com/*/api", "github.com/acme/api"));
+        assert!(!glob_match("gitlab.com/*", "github.com/acme/api"));
+        assert!(glob_match("exact", "exact"));
+    }
+
+    #[test]
+    fn test_project_map_lookup() {
+        let mut map = ProjectMap::default();
+        map.paths.insert("/work".to_string(), "work".to_string());
+        map.paths
+            .insert("/work/scratch".to_string(), "scratch".to_string());
+        map.remotes
+            .insert("github.com/*/api".to_string(), "api".to_string());
+        map.remotes.insert(
+            "github.com/fork/api".to_string(),
+            "upstream-api".to_string(),
+        );
+
+        assert_eq!(
+            map.project_for_path("/work/scratch/main.rs"),
+            Some("scratch".to_string())
+        );
+        assert_eq!(
+            map.project_for_path("/work/other/main.rs"),
+            Some("work".to_string())
+        );
+        assert_eq!(map.project_for_path("/elsewhere/main.rs"), None);
+        assert_eq!(
+            map.project_for_remote("git@github.com:fork/api.git"),
+            Some("upstream-api".to_string())
+        );
+        assert_eq!(
+            map.project_for_remote("https://github.com/acme/api.git"),
+            Some("api".to_string())
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::stats::{format_duration, top, LocalStats};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+
+// When to show the local end-of-day recap, from the `dailySummary` setting:
+// "off", "firstActivity" (yesterday, on the first heartbeat of a new day) or
+// a local "HH:MM" (today, once that time has passed).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecapSchedule {
+    #[default]
+    Off,
+    FirstActivity,
+    At(NaiveTime),
+}
+
+impl RecapSchedule {
+    pub fn parse(value: Option<&str>) -> Self {
+        match value {
+            None | Some("off") => Self::Off,
+            Some("firstActivity") => Self::FirstActivity,
+            Some(time) => NaiveTime::parse_from_str(time, "%H:%M").map_or_else(
+                |_| {
+                    log::warn!("Ignoring invalid dailySummary setting {:?}", time);
+                    Self::Off
+                },
+                Self::At,
+            ),
+        }
+    }
+
+    // The day to recap, if one is due. `on_activity` tells whether this is
+    // called for a heartbeat or from the periodic check.
+    // `now` is the wall clock time in the configured timezone.
+    pub fn due_day(self, now: NaiveDateTime, on_activity: bool) -> Option<NaiveDate> {
+        match self {
+            Self::FirstActivity if on_activity => Some(now.date() - TimeDelta::days(1)),
+            Self::At(time) if !on_activity && now.time() >= time => Some(now.date()),
+            _ => None,
+        }
+    }
+}
+
+pub fn message(day: NaiveDate, today: NaiveDate, stats: &LocalStats) -> Option<String> {
+    if stats.total_seconds == 0 {
+        return None;
+    }
+
+    let label = if day == today { "Today" } else { "Yesterday" };
+    let mut message = format!(
+        "Ziit: {} you coded {}",
+        label,
+        format_duration(stats.total_seconds)
+    );
+    if let Some((project, seconds)) = top(&stats.projects) {
+        message.push_str(&format!(
+            ", mostly on {} ({})",
+            project,
+            format_duration(seconds)
+        ));
+    }
+    if let Some((language, _)) = top(&stats.languages) {
+        message.push_str(&format!(", top language {}", language));
+    }
+    message.push('.');
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!(RecapSchedule::parse(None), RecapSchedule::Off);
+        assert_eq!(
+            RecapSchedule::parse(Some("firstActivity")),
+            RecapSchedule::FirstActivity
+        );
+        assert_eq!(
+            RecapSchedule::parse(Some("18:30")),
+            RecapSchedule::At(NaiveTime::from_hms_opt(18, 30, 0).unwrap())
+        );
+        assert_eq!(RecapSchedule::parse(Some("soon")), RecapSchedule::Off);
+    }
+
+    #[test]
+    fn test_due_day() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let now = today.and_hms_opt(18, 45, 0).unwrap();
+        let at = |time: &str| RecapSchedule::parse(Some(time));
+
+        assert_eq!(
+            RecapSchedule::FirstActivity.due_day(now, true),
+            Some(today - TimeDelta::days(1))
+        );
+        assert_eq!(RecapSchedule::FirstActivity.due_day(now, false), None);
+        assert_eq!(at("18:30").due_day(now, false), Some(today));
+        assert_eq!(at("19:00").due_day(now, false), None);
+        assert_eq!(at("18:30").due_day(now, true), None);
+        assert_eq!(RecapSchedule::Off.due_day(now, true), None);
+    }
+
+    #[test]
+    fn test_message() {
+        let mut stats = LocalStats {
+            total_seconds: 5400,
+            ..LocalStats::default()
+        };
+        stats.projects.insert("ziit".to_string(), 3600);
+        stats.projects.insert("zed".to_string(), 1800);
+        stats.languages.insert("Rust".to_string(), 5400);
+        let today = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+
+        assert_eq!(
+            message(today - TimeDelta::days(1), today, &stats).unwrap(),
+            "Ziit: Yesterday you coded 1h 30m, mostly on ziit (1h 0m), top language Rust."
+        );
+        assert_eq!(message(today, today, &LocalStats::default()), None);
+    }
+}
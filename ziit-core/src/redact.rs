@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+const MASK: &str = "<redacted>";
+const LOG_PAYLOADS_ENV_VAR: &str = "ZIIT_LOG_PAYLOADS";
+const MIN_SECRET_LENGTH: usize = 6;
+// Where credentials show up in headers, JSON, query strings and the Debug
+// output of the config.
+const SECRET_MARKERS: &[&str] = &[
+    "Bearer ",
+    "\"apiKey\":",
+    "\"api_key\":",
+    "api_key: Some(",
+    "apiKey=",
+    "api_key=",
+];
+
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+static LOG_PAYLOADS: AtomicBool = AtomicBool::new(false);
+
+// Known credentials are masked wherever they appear, not only after one of
+// the markers.
+pub fn register_secret(secret: &str) {
+    let secret = secret.trim();
+    if secret.len() < MIN_SECRET_LENGTH {
+        return;
+    }
+    if let Ok(mut secrets) = SECRETS.write() {
+        if !secrets.iter().any(|known| known == secret) {
+            secrets.push(secret.to_string());
+        }
+    }
+}
+
+// Heartbeat bodies and the config file are only logged when asked for,
+// since the logs end up in Zed's shared LSP log.
+pub fn set_log_payloads(enabled: bool) {
+    LOG_PAYLOADS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn log_payloads() -> bool {
+    LOG_PAYLOADS.load(Ordering::Relaxed)
+        || std::env::var(LOG_PAYLOADS_ENV_VAR).is_ok_and(|value| value == "1" || value == "true")
+}
+
+pub fn redact(text: &str) -> String {
+    let mut text = text.to_string();
+    if let Ok(secrets) = SECRETS.read() {
+        for secret in secrets.iter() {
+            text = text.replace(secret.as_str(), MASK);
+        }
+    }
+    for marker in SECRET_MARKERS {
+        text = mask_after(&text, marker);
+    }
+    text
+}
+
+fn mask_after(text: &str, marker: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(marker) {
+        let (before, after) = rest.split_at(index + marker.len());
+        result.push_str(before);
+        let value_start = after.len() - after.trim_start_matches([' ', '"', '\'']).len();
+        result.push_str(&after[..value_start]);
+        let value = &after[value_start..];
+        let value_len = value
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | ',' | ')' | '}' | '&'))
+            .unwrap_or(value.len());
+        if value_len > 0 {
+            result.push_str(MASK);
+        }
+        rest = &value[value_len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_masks_credentials_after_markers() {
+        assert_eq!(
+            redact("Authorization: Bearer abc123def"),
+            "Authorization: Bearer <redacted>"
+        );
+        assert_eq!(
+            redact(r#"{"apiKey": "abc123def", "baseUrl": "https://ziit.app"}"#),
+            r#"{"apiKey": "<redacted>", "baseUrl": "https://ziit.app"}"#
+        );
+        assert_eq!(
+            redact(r#"ZiitConfig { api_key: Some("abc123def"), base_url: None }"#),
+            r#"ZiitConfig { api_key: Some("<redacted>"), base_url: None }"#
+        );
+        assert_eq!(redact("no secrets here"), "no secrets here");
+    }
+
+    #[test]
+    fn test_redact_masks_registered_secrets_anywhere() {
+        register_secret("zk_registered_secret");
+        register_secret("abc");
+        assert_eq!(
+            redact("key zk_registered_secret leaked, abc stays"),
+            "key <redacted> leaked, abc stays"
+        );
+    }
+}
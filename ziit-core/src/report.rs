@@ -0,0 +1,254 @@
+use crate::api::DailySummaryResponse;
+use crate::heartbeat::Heartbeat;
+use crate::stats::{self, format_duration, IDLE_GAP_SECONDS};
+use crate::timezone::Timezone;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use std::collections::BTreeMap;
+
+const REPORT_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportSource {
+    Server,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Session {
+    fn seconds(&self) -> u64 {
+        (self.end - self.start).num_seconds().max(0) as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeeklyReport {
+    pub source: ReportSource,
+    pub days: BTreeMap<NaiveDate, u64>,
+    pub projects: BTreeMap<String, u64>,
+    pub longest_session: Option<Session>,
+    pub timezone: Timezone,
+}
+
+fn report_days(today: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    (0..REPORT_DAYS)
+        .rev()
+        .map(move |offset| today - TimeDelta::days(offset))
+}
+
+fn week_start(today: NaiveDate, timezone: &Timezone) -> DateTime<Utc> {
+    timezone.start_of_day(today - TimeDelta::days(REPORT_DAYS - 1))
+}
+
+// Sessions only exist in the local history, the server reports totals.
+pub fn longest_session(heartbeats: &[Heartbeat]) -> Option<Session> {
+    let mut timestamps: Vec<DateTime<Utc>> = heartbeats
+        .iter()
+        .filter_map(|heartbeat| DateTime::parse_from_rfc3339(&heartbeat.timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .collect();
+    timestamps.sort();
+
+    let mut longest: Option<Session> = None;
+    let mut current: Option<Session> = None;
+    for timestamp in timestamps {
+        current = match current {
+            Some(session) if (timestamp - session.end).num_seconds() <= IDLE_GAP_SECONDS => {
+                Some(Session {
+                    end: timestamp,
+                    ..session
+                })
+            }
+            _ => Some(Session {
+                start: timestamp,
+                end: timestamp,
+            }),
+        };
+        if current.is_some_and(|session| {
+            longest.is_none_or(|longest| session.seconds() > longest.seconds())
+        }) {
+            longest = current;
+        }
+    }
+    longest.filter(|session| session.seconds() > 0)
+}
+
+pub fn from_summary(
+    summary: &DailySummaryResponse,
+    history: &[Heartbeat],
+    today: NaiveDate,
+    timezone: Timezone,
+) -> WeeklyReport {
+    let mut days: BTreeMap<NaiveDate, u64> = report_days(today).map(|day| (day, 0)).collect();
+    let mut projects = BTreeMap::new();
+    for entry in &summary.summaries {
+        if let Some(total) = entry
+            .date
+            .get(..10)
+            .and_then(|date| date.parse().ok())
+            .and_then(|date| days.get_mut(&date))
+        {
+            *total += entry.total_seconds;
+        }
+        for (project, seconds) in entry.projects.iter().flatten() {
+            *projects.entry(project.clone()).or_default() += seconds;
+        }
+    }
+
+    WeeklyReport {
+        source: ReportSource::Server,
+        days,
+        projects,
+        longest_session: longest_session(&stats::since(
+            history.to_vec(),
+            week_start(today, &timezone),
+        )),
+        timezone,
+    }
+}
+
+pub fn from_history(history: Vec<Heartbeat>, today: NaiveDate, timezone: Timezone) -> WeeklyReport {
+    let week = stats::since(history, week_start(today, &timezone));
+
+    let days = report_days(today)
+        .map(|day| {
+            let day_heartbeats = stats::between(
+                week.clone(),
+                timezone.start_of_day(day),
+                timezone.start_of_day(day + TimeDelta::days(1)),
+            );
+            (day, stats::aggregate(&day_heartbeats).total_seconds)
+        })
+        .collect();
+
+    WeeklyReport {
+        source: ReportSource::Local,
+        days,
+        projects: stats::aggregate(&week).projects,
+        longest_session: longest_session(&week),
+        timezone,
+    }
+}
+
+impl WeeklyReport {
+    pub fn to_markdown(&self) -> String {
+        let total: u64 = self.days.values().sum();
+        let mut markdown = String::from("# Weekly coding report\n\n");
+        if let (Some(first), Some(last)) = (self.days.keys().next(), self.days.keys().last()) {
+            markdown.push_str(&format!(
+                "{} to {}, {} in total.\n",
+                first,
+                last,
+                format_duration(total)
+            ));
+        }
+
+        markdown.push_str("\n## Per day\n\n| Day | Time |\n| --- | --- |\n");
+        for (day, seconds) in &self.days {
+            markdown.push_str(&format!(
+                "| {} | {} |\n",
+                day.format("%a %Y-%m-%d"),
+                format_duration(*seconds)
+            ));
+        }
+
+        markdown.push_str("\n## Per project\n\n");
+        if self.projects.is_empty() {
+            markdown.push_str("No project time tracked.\n");
+        } else {
+            let mut projects: Vec<_> = self.projects.iter().collect();
+            projects.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            markdown.push_str("| Project | Time |\n| --- | --- |\n");
+            for (project, seconds) in projects {
+                markdown.push_str(&format!(
+                    "| {} | {} |\n",
+                    project,
+                    format_duration(*seconds)
+                ));
+            }
+        }
+
+        if let Some(session) = self.longest_session {
+            let start = self.timezone.local_time(session.start);
+            let end = self.timezone.local_time(session.end);
+            markdown.push_str(&format!(
+                "\nLongest session: {} on {}, {} to {}.\n",
+                format_duration(session.seconds()),
+                start.format("%a %Y-%m-%d"),
+                start.format("%H:%M"),
+                end.format("%H:%M")
+            ));
+        }
+        if self.source == ReportSource::Local {
+            markdown.push_str("\n_Computed from local history, the server was not reachable._\n");
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::SummaryEntry;
+
+    fn heartbeat(timestamp: &str) -> Heartbeat {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "project": "ziit",
+            "language": "Rust",
+            "file": null,
+            "branch": "main",
+            "editor": "Zed",
+            "os": "linux",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_longest_session_splits_on_idle_gaps() {
+        let history = [
+            heartbeat("2024-01-01T10:00:00Z"),
+            heartbeat("2024-01-01T10:04:00Z"),
+            heartbeat("2024-01-01T11:00:00Z"),
+            heartbeat("2024-01-01T11:05:00Z"),
+            heartbeat("2024-01-01T11:10:00Z"),
+        ];
+
+        let session = longest_session(&history).unwrap();
+        assert_eq!(session.start.to_rfc3339(), "2024-01-01T11:00:00+00:00");
+        assert_eq!(session.seconds(), 600);
+        assert_eq!(longest_session(&history[..1]), None);
+    }
+
+    #[test]
+    fn test_from_summary_fills_missing_days() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let summary = DailySummaryResponse {
+            summaries: vec![SummaryEntry {
+                date: "2024-01-05".to_string(),
+                total_seconds: 5400,
+                hourly_data: None,
+                projects: Some([("ziit".to_string(), 5400)].into_iter().collect()),
+            }],
+            timezone: "UTC".to_string(),
+        };
+
+        let report = from_summary(&summary, &[], today, Timezone::Local);
+        assert_eq!(report.days.len(), 7);
+        assert_eq!(
+            report.days[&NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()],
+            5400
+        );
+        assert_eq!(report.days.values().sum::<u64>(), 5400);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("2024-01-01 to 2024-01-07, 1h 30m in total."));
+        assert!(markdown.contains("| Fri 2024-01-05 | 1h 30m |"));
+        assert!(markdown.contains("| ziit | 1h 30m |"));
+        assert!(!markdown.contains("local history"));
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const MAX_REQUESTS: usize = 50;
+const MAX_ERROR_CHARS: usize = 200;
+
+static REQUESTS: Mutex<VecDeque<RequestRecord>> = Mutex::new(VecDeque::new());
+
+// One API request as shown by `ziit.recentRequests`. `status` is missing when
+// the request never got a response.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RequestRecord {
+    pub at: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn record(mut request: RequestRecord) {
+    request.error = request.error.map(|error| truncate(error.trim()));
+    let mut requests = REQUESTS.lock().unwrap_or_else(|e| e.into_inner());
+    if requests.len() == MAX_REQUESTS {
+        requests.pop_front();
+    }
+    requests.push_back(request);
+}
+
+// Newest first.
+pub fn recent() -> Vec<RequestRecord> {
+    REQUESTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .rev()
+        .cloned()
+        .collect()
+}
+
+fn truncate(error: &str) -> String {
+    match error.char_indices().nth(MAX_ERROR_CHARS) {
+        Some((index, _)) => format!("{}…", &error[..index]),
+        None => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_keeps_short_errors() {
+        assert_eq!(truncate("HTTP 500"), "HTTP 500");
+        let long = "é".repeat(MAX_ERROR_CHARS + 10);
+        assert_eq!(truncate(&long).chars().count(), MAX_ERROR_CHARS + 1);
+    }
+}
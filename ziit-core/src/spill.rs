@@ -0,0 +1,133 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+// Older offline entries moved out of memory during long offline periods.
+// Each spill becomes one immutable segment file named after a sequence number
+// and its entry count, so the backlog size is known without reading them and
+// sorting by name yields the oldest segment first.
+#[derive(Debug, Clone)]
+pub struct SpillStore {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub path: PathBuf,
+    pub sequence: u64,
+    pub count: usize,
+}
+
+fn parse_segment_name(path: &Path) -> Option<Segment> {
+    let name = path.file_name()?.to_str()?;
+    let rest = name.strip_prefix("segment-")?.strip_suffix(".json")?;
+    let (sequence, count) = rest.split_once('-')?;
+    Some(Segment {
+        path: path.to_path_buf(),
+        sequence: sequence.parse().ok()?,
+        count: count.parse().ok()?,
+    })
+}
+
+impl SpillStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    // Oldest first.
+    pub async fn segments(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        if let Ok(mut entries) = fs::read_dir(&self.dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                if let Some(segment) = parse_segment_name(&entry.path()) {
+                    segments.push(segment);
+                }
+            }
+        }
+        segments.sort_by_key(|segment| segment.sequence);
+        segments
+    }
+
+    pub async fn spilled_count(&self) -> usize {
+        self.segments()
+            .await
+            .iter()
+            .map(|segment| segment.count)
+            .sum()
+    }
+
+    pub async fn spill<T: Serialize>(&self, entries: &[T]) -> Result<Segment> {
+        fs::create_dir_all(&self.dir).await?;
+        let sequence = self
+            .segments()
+            .await
+            .last()
+            .map_or(0, |segment| segment.sequence + 1);
+        let path = self
+            .dir
+            .join(format!("segment-{sequence:012}-{}.json", entries.len()));
+
+        // Write to a temporary name first so a crash never leaves a truncated
+        // segment that looks complete.
+        let partial = path.with_extension("partial");
+        fs::write(&partial, serde_json::to_vec(entries)?).await?;
+        fs::rename(&partial, &path).await?;
+
+        Ok(Segment {
+            path,
+            sequence,
+            count: entries.len(),
+        })
+    }
+
+    pub async fn load<T: DeserializeOwned>(&self, segment: &Segment) -> Result<Vec<T>> {
+        let data = fs::read(&segment.path).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub async fn remove(&self, segment: &Segment) -> Result<()> {
+        fs::remove_file(&segment.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> SpillStore {
+        let dir = std::env::temp_dir().join(format!("ziit-spill-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        SpillStore::new(dir)
+    }
+
+    #[test]
+    fn test_parse_segment_name() {
+        let segment = parse_segment_name(Path::new("/x/segment-000000000007-250.json")).unwrap();
+        assert_eq!(segment.sequence, 7);
+        assert_eq!(segment.count, 250);
+        assert!(parse_segment_name(Path::new("/x/segment-7-250.partial")).is_none());
+        assert!(parse_segment_name(Path::new("/x/offline_heartbeats.json")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_spill_and_load_oldest_first() {
+        let store = temp_store("order");
+
+        store.spill(&[1, 2, 3]).await.unwrap();
+        store.spill(&[4, 5]).await.unwrap();
+        assert_eq!(store.spilled_count().await, 5);
+
+        let segments = store.segments().await;
+        assert_eq!(segments.len(), 2);
+        let oldest: Vec<i32> = store.load(&segments[0]).await.unwrap();
+        assert_eq!(oldest, [1, 2, 3]);
+
+        store.remove(&segments[0]).await.unwrap();
+        assert_eq!(store.spilled_count().await, 2);
+
+        std::fs::remove_dir_all(&store.dir).ok();
+    }
+}
@@ -0,0 +1,220 @@
+use crate::heartbeat::Heartbeat;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Mirrors the server: the time between two heartbeats counts towards the
+// earlier one unless the gap is longer than this, in which case the user is
+// considered to have been away.
+pub const IDLE_GAP_SECONDS: i64 = 5 * 60;
+
+const UNKNOWN: &str = "Unknown";
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct LocalStats {
+    #[serde(rename = "totalSeconds")]
+    pub total_seconds: u64,
+    #[serde(rename = "writingSeconds")]
+    pub writing_seconds: u64,
+    #[serde(rename = "readingSeconds")]
+    pub reading_seconds: u64,
+    // Total time weighted by each heartbeat's activity intensity.
+    #[serde(rename = "engagedSeconds")]
+    pub engaged_seconds: u64,
+    pub projects: BTreeMap<String, u64>,
+    pub languages: BTreeMap<String, u64>,
+    pub branches: BTreeMap<String, u64>,
+}
+
+fn parse_timestamp(heartbeat: &Heartbeat) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&heartbeat.timestamp)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+fn add(map: &mut BTreeMap<String, u64>, key: &Option<String>, seconds: u64) {
+    let key = key.as_deref().unwrap_or(UNKNOWN).to_string();
+    *map.entry(key).or_default() += seconds;
+}
+
+pub fn aggregate(heartbeats: &[Heartbeat]) -> LocalStats {
+    let mut timed: Vec<_> = heartbeats
+        .iter()
+        .filter_map(|heartbeat| Some((parse_timestamp(heartbeat)?, heartbeat)))
+        .collect();
+    timed.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut stats = LocalStats::default();
+    for pair in timed.windows(2) {
+        let ((start, heartbeat), (end, _)) = (pair[0], pair[1]);
+        let gap = (end - start).num_seconds();
+        if gap <= 0 || gap > IDLE_GAP_SECONDS {
+            continue;
+        }
+
+        let seconds = gap as u64;
+        stats.total_seconds += seconds;
+        // Heartbeats recorded before the flag existed count as neither.
+        match heartbeat.is_write {
+            Some(true) => stats.writing_seconds += seconds,
+            Some(false) => stats.reading_seconds += seconds,
+            None => {}
+        }
+        // Heartbeats without an intensity count in full.
+        stats.engaged_seconds += heartbeat
+            .intensity
+            .map_or(seconds, |intensity| seconds * u64::from(intensity) / 100);
+        add(&mut stats.projects, &heartbeat.project, seconds);
+        add(&mut stats.languages, &heartbeat.language, seconds);
+        add(&mut stats.branches, &heartbeat.branch, seconds);
+    }
+    stats
+}
+
+pub fn format_duration(total_seconds: u64) -> String {
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+// The entry with the most time, ties going to the first name.
+pub fn top(times: &BTreeMap<String, u64>) -> Option<(&str, u64)> {
+    times
+        .iter()
+        .rev()
+        .max_by_key(|(_, seconds)| **seconds)
+        .map(|(name, seconds)| (name.as_str(), *seconds))
+}
+
+pub fn since(heartbeats: Vec<Heartbeat>, start: DateTime<Utc>) -> Vec<Heartbeat> {
+    heartbeats
+        .into_iter()
+        .filter(|heartbeat| parse_timestamp(heartbeat).is_some_and(|timestamp| timestamp >= start))
+        .collect()
+}
+
+pub fn between(
+    heartbeats: Vec<Heartbeat>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<Heartbeat> {
+    heartbeats
+        .into_iter()
+        .filter(|heartbeat| {
+            parse_timestamp(heartbeat)
+                .is_some_and(|timestamp| timestamp >= start && timestamp < end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(timestamp: &str, project: &str, language: &str) -> Heartbeat {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "project": project,
+            "language": language,
+            "file": null,
+            "branch": "main",
+            "editor": "Zed",
+            "os": "linux",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_counts_gaps_towards_the_earlier_heartbeat() {
+        let heartbeats = [
+            heartbeat("2024-01-01T10:02:00Z", "ziit", "Go"),
+            heartbeat("2024-01-01T10:00:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:04:00Z", "other", "Rust"),
+            heartbeat("2024-01-01T10:05:00Z", "other", "Rust"),
+        ];
+
+        let stats = aggregate(&heartbeats);
+
+        assert_eq!(stats.total_seconds, 300);
+        assert_eq!(stats.projects["ziit"], 240);
+        assert_eq!(stats.projects["other"], 60);
+        assert_eq!(stats.languages["Rust"], 180);
+        assert_eq!(stats.languages["Go"], 120);
+        assert_eq!(stats.branches["main"], 300);
+    }
+
+    #[test]
+    fn test_aggregate_skips_idle_gaps() {
+        let heartbeats = [
+            heartbeat("2024-01-01T10:00:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:30:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:31:00Z", "ziit", "Rust"),
+        ];
+
+        assert_eq!(aggregate(&heartbeats).total_seconds, 60);
+        assert_eq!(aggregate(&heartbeats[..1]), LocalStats::default());
+    }
+
+    #[test]
+    fn test_aggregate_splits_reading_and_writing() {
+        let mut heartbeats = [
+            heartbeat("2024-01-01T10:00:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:02:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:03:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:04:00Z", "ziit", "Rust"),
+        ];
+        heartbeats[0].is_write = Some(true);
+        heartbeats[1].is_write = Some(false);
+
+        let stats = aggregate(&heartbeats);
+
+        assert_eq!(stats.total_seconds, 240);
+        assert_eq!(stats.writing_seconds, 120);
+        assert_eq!(stats.reading_seconds, 60);
+    }
+
+    #[test]
+    fn test_aggregate_weights_engaged_time_by_intensity() {
+        let mut heartbeats = [
+            heartbeat("2024-01-01T10:00:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:02:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:04:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-01T10:05:00Z", "ziit", "Rust"),
+        ];
+        heartbeats[0].intensity = Some(100);
+        heartbeats[1].intensity = Some(25);
+
+        let stats = aggregate(&heartbeats);
+
+        assert_eq!(stats.total_seconds, 300);
+        assert_eq!(stats.engaged_seconds, 120 + 30 + 60);
+    }
+
+    #[test]
+    fn test_since_filters_older_heartbeats() {
+        let heartbeats = vec![
+            heartbeat("2024-01-01T10:00:00Z", "ziit", "Rust"),
+            heartbeat("2024-01-02T10:00:00Z", "ziit", "Rust"),
+        ];
+        let start = DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(since(heartbeats, start).len(), 1);
+    }
+
+    #[test]
+    fn test_top_prefers_most_time_then_name() {
+        let times: BTreeMap<_, _> = [("b", 60), ("a", 60), ("c", 30)]
+            .into_iter()
+            .map(|(name, seconds)| (name.to_string(), seconds))
+            .collect();
+
+        assert_eq!(top(&times), Some(("a", 60)));
+        assert_eq!(top(&BTreeMap::new()), None);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::config::{get_config_dir, read_config_file};
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const REPORTS_FILE_NAME: &str = "error_reports.jsonl";
+const MAX_STORED_REPORTS: usize = 200;
+// The same error is recorded at most once per window, so a long offline
+// stretch does not fill the file with identical sync failures.
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorKind {
+    Sync,
+    Panic,
+    Config,
+}
+
+// Carries no file names, projects or machine details. Paths and the API key
+// are scrubbed from the message before it is stored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub timestamp: String,
+    pub kind: ErrorKind,
+    pub message: String,
+    pub version: String,
+    pub os: String,
+    #[serde(default)]
+    pub submitted: bool,
+}
+
+fn recent() -> &'static Mutex<HashMap<(ErrorKind, String), Instant>> {
+    static RECENT: OnceLock<Mutex<HashMap<(ErrorKind, String), Instant>>> = OnceLock::new();
+    RECENT.get_or_init(Mutex::default)
+}
+
+fn reports_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(REPORTS_FILE_NAME))
+}
+
+fn anonymize(message: &str, api_key: Option<&str>) -> String {
+    let mut message = message.to_string();
+    if let Some(api_key) = api_key.filter(|key| !key.is_empty()) {
+        message = message.replace(api_key, "<api key>");
+    }
+    if let Some(home) = dirs::home_dir() {
+        let home = home.to_string_lossy();
+        if !home.is_empty() {
+            message = message.replace(home.as_ref(), "~");
+        }
+    }
+
+    message
+        .split(' ')
+        .map(|word| {
+            let trimmed = word.trim_start_matches(['(', '[', '"', '\'']);
+            let bytes = trimmed.as_bytes();
+            let windows_path = bytes.len() > 2 && bytes[1] == b':' && bytes[2] == b'\\';
+            if trimmed.starts_with('/') || trimmed.starts_with("~/") || windows_path {
+                format!("{}<path>", &word[..word.len() - trimmed.len()])
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_duplicate(kind: ErrorKind, message: &str) -> bool {
+    let mut recent = recent().lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    recent.retain(|_, at| now.duration_since(*at) < DUPLICATE_WINDOW);
+    recent.insert((kind, message.to_string()), now).is_some()
+}
+
+fn read_reports(path: &Path) -> Vec<ErrorReport> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn write_reports(path: &Path, reports: &[ErrorReport]) -> Result<()> {
+    let mut data = String::new();
+    for report in reports {
+        data.push_str(&serde_json::to_string(report)?);
+        data.push('\n');
+    }
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+// Synchronous so it also works from the panic hook.
+fn store(kind: ErrorKind, message: &str, api_key: Option<&str>) -> Result<()> {
+    let message = anonymize(message, api_key);
+    if is_duplicate(kind, &message) {
+        return Ok(());
+    }
+
+    let report = ErrorReport {
+        timestamp: Utc::now().to_rfc3339(),
+        kind,
+        message,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        submitted: false,
+    };
+    let path = reports_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&report)?)?;
+    drop(file);
+
+    let reports = read_reports(&path);
+    if reports.len() > MAX_STORED_REPORTS {
+        write_reports(&path, &reports[reports.len() - MAX_STORED_REPORTS..])?;
+    }
+    Ok(())
+}
+
+pub async fn record(kind: ErrorKind, message: &str) {
+    let config = read_config_file().await.ok();
+    if let Err(e) = store(
+        kind,
+        message,
+        config.as_ref().and_then(|config| config.api_key.as_deref()),
+    ) {
+        log::debug!("Could not store error report: {}", e);
+        return;
+    }
+    if config.is_some_and(|config| config.error_reporting.unwrap_or(false)) {
+        if let Err(e) = submit_pending().await {
+            log::debug!("Could not submit error reports: {}", e);
+        }
+    }
+}
+
+// Config errors come from reading the config, so they are only stored and go
+// out with the next submission.
+pub(crate) fn record_config_error(message: &str) {
+    if let Err(e) = store(ErrorKind::Config, message, None) {
+        log::debug!("Could not store error report: {}", e);
+    }
+}
+
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Err(e) = store(ErrorKind::Panic, &info.to_string(), None) {
+            log::debug!("Could not store panic report: {}", e);
+        }
+        previous(info);
+    }));
+}
+
+// Sends everything not yet submitted, only when the user opted in. Panics
+// are stored locally first and go out with the next submission.
+pub async fn submit_pending() -> Result<usize> {
+    let config = read_config_file().await?;
+    if !config.error_reporting.unwrap_or(false) {
+        return Ok(0);
+    }
+    let Some(api_key) = config.api_key else {
+        return Ok(0);
+    };
+    let base_url = config
+        .base_url
+        .unwrap_or_else(|| "https://ziit.app".to_string());
+
+    let path = reports_path()?;
+    let mut reports = read_reports(&path);
+    let pending: Vec<_> = reports
+        .iter()
+        .filter(|report| !report.submitted)
+        .cloned()
+        .collect();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    crate::api::send_error_reports_request(&base_url, &api_key, &pending).await?;
+    for report in &mut reports {
+        report.submitted = true;
+    }
+    write_reports(&path, &reports)?;
+    Ok(pending.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymize_scrubs_paths_and_key() {
+        let message = anonymize(
+            "Failed to read /srv/work/ziit/main.rs with key abc123 (C:\\Users\\me\\x.rs)",
+            Some("abc123"),
+        );
+
+        assert_eq!(message, "Failed to read <path> with key <api key> (<path>");
+        assert_eq!(
+            anonymize("D:\\code\\main.rs missing", None),
+            "<path> missing"
+        );
+    }
+
+    #[test]
+    fn test_duplicates_are_recorded_once() {
+        assert!(!is_duplicate(ErrorKind::Sync, "test duplicate"));
+        assert!(is_duplicate(ErrorKind::Sync, "test duplicate"));
+        assert!(!is_duplicate(ErrorKind::Config, "test duplicate"));
+    }
+}
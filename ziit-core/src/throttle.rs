@@ -0,0 +1,265 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+// Decides when activity is worth reporting. Used both to debounce raw editor
+// events and to rate-limit heartbeats, so the two layers can never disagree.
+//
+// An event passes when it is forced, when it is for a different file than the
+// last one that passed, when the git HEAD moved, or when the interval has
+// elapsed since the last one that passed.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    interval: TimeDelta,
+    last: Option<Emitted>,
+}
+
+#[derive(Debug, Clone)]
+struct Emitted {
+    file: Option<String>,
+    git_head: Option<String>,
+    at: DateTime<Utc>,
+}
+
+impl Throttle {
+    pub fn new(interval_seconds: u64) -> Self {
+        Self {
+            interval: TimeDelta::seconds(interval_seconds as i64),
+            last: None,
+        }
+    }
+
+    pub fn last_file(&self) -> Option<&str> {
+        self.last.as_ref()?.file.as_deref()
+    }
+
+    pub fn last_git_head(&self) -> Option<&str> {
+        self.last.as_ref()?.git_head.as_deref()
+    }
+
+    #[cfg(test)]
+    pub fn last_emitted_at(&self) -> Option<DateTime<Utc>> {
+        self.last.as_ref().map(|last| last.at)
+    }
+
+    pub fn git_head_changed(&self, git_head: &Option<String>) -> bool {
+        matches!(
+            (self.last_git_head(), git_head),
+            (Some(old), Some(new)) if old != new
+        )
+    }
+
+    pub fn should_emit(
+        &self,
+        now: DateTime<Utc>,
+        file: &Option<String>,
+        git_head: &Option<String>,
+        force: bool,
+    ) -> bool {
+        let Some(last) = &self.last else {
+            return true;
+        };
+
+        let file_changed = file.is_some() && *file != last.file;
+        let interval_elapsed = now - last.at >= self.interval;
+
+        force || file_changed || self.git_head_changed(git_head) || interval_elapsed
+    }
+
+    // Checks and records in one step so two callers cannot both pass.
+    pub fn try_emit(
+        &mut self,
+        now: DateTime<Utc>,
+        file: &Option<String>,
+        git_head: &Option<String>,
+        force: bool,
+    ) -> bool {
+        if !self.should_emit(now, file, git_head, force) {
+            return false;
+        }
+
+        self.last = Some(Emitted {
+            file: file.clone(),
+            git_head: git_head.clone(),
+            at: now,
+        });
+        true
+    }
+}
+
+// Token bucket over all heartbeats, the last line of defence against event
+// storms such as a project-wide find-and-replace. The bucket holds a minute's
+// worth of tokens and refills continuously; a limit of 0 disables it.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    tokens: f64,
+    refilled_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimiter {
+    pub fn try_acquire(&mut self, now: DateTime<Utc>, per_minute: u32) -> bool {
+        if per_minute == 0 {
+            return true;
+        }
+        let capacity = f64::from(per_minute);
+        self.tokens = match self.refilled_at {
+            Some(refilled_at) => {
+                let elapsed = (now - refilled_at).num_milliseconds().max(0) as f64 / 60_000.0;
+                (self.tokens + elapsed * capacity).min(capacity)
+            }
+            None => capacity,
+        };
+        self.refilled_at = Some(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: u64 = 120;
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    // Small xorshift generator so the property tests are reproducible without
+    // pulling in a property testing crate.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+    }
+
+    struct Step {
+        at: DateTime<Utc>,
+        file: Option<String>,
+        git_head: Option<String>,
+        force: bool,
+    }
+
+    fn random_steps(seed: u64, count: usize) -> Vec<Step> {
+        let mut rng = Rng(seed);
+        let mut at = Utc::now();
+        (0..count)
+            .map(|_| {
+                at += TimeDelta::seconds(rng.below(INTERVAL * 2) as i64);
+                Step {
+                    at,
+                    file: match rng.below(4) {
+                        0 => None,
+                        n => some(&format!("/file{n}.rs")),
+                    },
+                    git_head: match rng.below(3) {
+                        0 => None,
+                        n => some(&format!("head{n}")),
+                    },
+                    force: rng.below(8) == 0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_first_event_passes() {
+        let mut throttle = Throttle::new(INTERVAL);
+        assert!(throttle.try_emit(Utc::now(), &None, &None, false));
+    }
+
+    #[test]
+    fn test_same_file_within_interval_is_dropped() {
+        let mut throttle = Throttle::new(INTERVAL);
+        let now = Utc::now();
+
+        assert!(throttle.try_emit(now, &some("/a.rs"), &None, false));
+        assert!(!throttle.try_emit(now + TimeDelta::seconds(5), &some("/a.rs"), &None, false));
+        assert!(!throttle.try_emit(now + TimeDelta::seconds(5), &None, &None, false));
+        assert!(throttle.try_emit(now + TimeDelta::seconds(6), &some("/b.rs"), &None, false));
+
+        let later = now + TimeDelta::seconds(6 + INTERVAL as i64);
+        assert!(throttle.try_emit(later, &some("/b.rs"), &None, false));
+        assert_eq!(throttle.last_emitted_at(), Some(later));
+    }
+
+    #[test]
+    fn test_forced_and_branch_changes_pass() {
+        let mut throttle = Throttle::new(INTERVAL);
+        let now = Utc::now();
+
+        assert!(throttle.try_emit(now, &some("/a.rs"), &some("main"), false));
+        assert!(throttle.try_emit(now, &some("/a.rs"), &some("main"), true));
+        assert!(!throttle.git_head_changed(&some("main")));
+        assert!(!throttle.git_head_changed(&None));
+        assert!(throttle.git_head_changed(&some("feature")));
+        assert!(throttle.try_emit(now, &some("/a.rs"), &some("feature"), false));
+        assert_eq!(throttle.last_git_head(), Some("feature"));
+    }
+
+    #[test]
+    fn test_property_forced_events_always_pass() {
+        for seed in 1..50 {
+            let mut throttle = Throttle::new(INTERVAL);
+            for step in random_steps(seed, 200) {
+                let passed = throttle.try_emit(step.at, &step.file, &step.git_head, step.force);
+                assert!(passed || !step.force, "seed {seed}: forced event dropped");
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_elapsed_interval_always_passes() {
+        for seed in 1..50 {
+            let mut throttle = Throttle::new(INTERVAL);
+            for step in random_steps(seed, 200) {
+                let elapsed = throttle
+                    .last_emitted_at()
+                    .is_none_or(|at| step.at - at >= TimeDelta::seconds(INTERVAL as i64));
+                let passed = throttle.try_emit(step.at, &step.file, &step.git_head, step.force);
+                assert!(
+                    passed || !elapsed,
+                    "seed {seed}: event after interval dropped"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_repeats_within_interval_are_dropped() {
+        for seed in 1..50 {
+            let mut throttle = Throttle::new(INTERVAL);
+            for step in random_steps(seed, 200) {
+                let repeat = throttle.last_emitted_at().is_some_and(|at| {
+                    step.at - at < TimeDelta::seconds(INTERVAL as i64)
+                        && (step.file.is_none() || step.file.as_deref() == throttle.last_file())
+                        && !throttle.git_head_changed(&step.git_head)
+                });
+                let passed = throttle.try_emit(step.at, &step.file, &step.git_head, step.force);
+                assert_eq!(passed, !repeat || step.force, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_rate_limiter_refills_over_time() {
+        let mut limiter = RateLimiter::default();
+        let start = Utc::now();
+
+        let passed = (0..10).filter(|_| limiter.try_acquire(start, 5)).count();
+        assert_eq!(passed, 5);
+        assert!(!limiter.try_acquire(start + TimeDelta::seconds(6), 5));
+        assert!(limiter.try_acquire(start + TimeDelta::seconds(12), 5));
+        assert!((0..100).all(|_| RateLimiter::default().try_acquire(start, 0)));
+    }
+}
@@ -0,0 +1,495 @@
+use crate::config::read_config_file;
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeDelta,
+    Utc,
+};
+use std::path::PathBuf;
+
+const ZONEINFO_DIRS: &[&str] = &[
+    "/usr/share/zoneinfo",
+    "/usr/lib/zoneinfo",
+    "/usr/share/lib/zoneinfo",
+    "/etc/zoneinfo",
+];
+
+// The timezone day boundaries are computed in: the system's by default, or
+// the `timezone` config option (`UTC`, `+05:30` or an IANA name such as
+// `Europe/Berlin`). Offsets are looked up for each instant rather than taken
+// from the current one, so days next to a DST change start at the right time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Timezone {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+    Zone(Zone),
+}
+
+impl Timezone {
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Some(Self::Local);
+        }
+        if name.eq_ignore_ascii_case("utc") || name.eq_ignore_ascii_case("z") {
+            return Some(Self::Fixed(Utc.fix()));
+        }
+        if name.starts_with(['+', '-']) {
+            let seconds = parse_offset(name)?;
+            return FixedOffset::east_opt(seconds).map(Self::Fixed);
+        }
+        load_zone(name).map(Self::Zone)
+    }
+
+    pub fn offset_at(&self, at: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Self::Local => at.with_timezone(&Local).offset().fix(),
+            Self::Fixed(offset) => *offset,
+            Self::Zone(zone) => {
+                FixedOffset::east_opt(zone.offset_at(at.timestamp())).unwrap_or(Utc.fix())
+            }
+        }
+    }
+
+    pub fn local_time(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        at.with_timezone(&self.offset_at(at)).naive_local()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.local_time(Utc::now()).date()
+    }
+
+    // When midnight is skipped by a DST change the day starts at the first
+    // instant after the gap.
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        let midnight = date.and_time(NaiveTime::MIN);
+        let mut start = midnight.and_utc();
+        for _ in 0..2 {
+            start = midnight.and_utc()
+                - TimeDelta::seconds(self.offset_at(start).local_minus_utc().into());
+        }
+        start
+    }
+
+    pub fn start_of_today(&self) -> DateTime<Utc> {
+        self.start_of_day(self.today())
+    }
+
+    // What the stats endpoint expects for "today": the offset in effect at
+    // the start of the day, not the current one.
+    pub fn midnight_offset_seconds(&self) -> i32 {
+        self.offset_at(self.start_of_today()).local_minus_utc()
+    }
+}
+
+pub async fn configured() -> Timezone {
+    let Some(name) = read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.timezone)
+    else {
+        return Timezone::Local;
+    };
+    Timezone::parse(&name).unwrap_or_else(|| {
+        log::warn!("Unknown timezone {:?}, using the system timezone", name);
+        Timezone::Local
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    // (UTC seconds, offset in effect from then on), sorted.
+    transitions: Vec<(i64, i32)>,
+    initial_offset: i32,
+    // Covers everything after the last transition.
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    fn offset_at(&self, at: i64) -> i32 {
+        let index = self.transitions.partition_point(|(start, _)| *start <= at);
+        if index == self.transitions.len() {
+            if let Some(rule) = &self.rule {
+                return rule.offset_at(at);
+            }
+        }
+        match index {
+            0 => self.initial_offset,
+            _ => self.transitions[index - 1].1,
+        }
+    }
+}
+
+fn zoneinfo_path(name: &str) -> Option<PathBuf> {
+    if name
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return None;
+    }
+    let dirs = std::env::var_os("TZDIR").map(PathBuf::from);
+    dirs.into_iter()
+        .chain(ZONEINFO_DIRS.iter().map(PathBuf::from))
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn load_zone(name: &str) -> Option<Zone> {
+    let path = zoneinfo_path(name)?;
+    parse_tzif(&std::fs::read(&path).ok()?).or_else(|| {
+        log::warn!("Could not parse timezone file {}", path.display());
+        None
+    })
+}
+
+struct Counts {
+    isut: usize,
+    isstd: usize,
+    leap: usize,
+    time: usize,
+    types: usize,
+    chars: usize,
+}
+
+impl Counts {
+    fn read(header: &[u8]) -> Option<Self> {
+        if header.get(..4)? != b"TZif" {
+            return None;
+        }
+        let count = |index: usize| -> Option<usize> {
+            let bytes = header.get(20 + index * 4..24 + index * 4)?;
+            Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+        };
+        Some(Self {
+            isut: count(0)?,
+            isstd: count(1)?,
+            leap: count(2)?,
+            time: count(3)?,
+            types: count(4)?,
+            chars: count(5)?,
+        })
+    }
+
+    fn block_len(&self, time_size: usize) -> usize {
+        self.time * time_size
+            + self.time
+            + self.types * 6
+            + self.chars
+            + self.leap * (time_size + 4)
+            + self.isstd
+            + self.isut
+    }
+}
+
+const HEADER_LEN: usize = 44;
+
+// RFC 8536. Version 1 files only have 32-bit transition times, later
+// versions repeat the data with 64-bit times followed by a POSIX TZ footer.
+fn parse_tzif(data: &[u8]) -> Option<Zone> {
+    let counts = Counts::read(data)?;
+    let version = *data.get(4)?;
+    if version < b'2' {
+        return parse_block(data.get(HEADER_LEN..)?, &counts, 4, None);
+    }
+
+    let second = data.get(HEADER_LEN + counts.block_len(4)..)?;
+    let counts = Counts::read(second)?;
+    let block = second.get(HEADER_LEN..)?;
+    let footer = block.get(counts.block_len(8)..).and_then(|footer| {
+        let footer = std::str::from_utf8(footer).ok()?;
+        Rule::parse(footer.trim_matches('\n'))
+    });
+    parse_block(block, &counts, 8, footer)
+}
+
+fn parse_block(
+    block: &[u8],
+    counts: &Counts,
+    time_size: usize,
+    rule: Option<Rule>,
+) -> Option<Zone> {
+    let times = block.get(..counts.time * time_size)?;
+    let indices = block.get(counts.time * time_size..counts.time * (time_size + 1))?;
+    let types_start = counts.time * (time_size + 1);
+    let types = block.get(types_start..types_start + counts.types * 6)?;
+    let offsets: Vec<i32> = types
+        .chunks_exact(6)
+        .map(|info| i32::from_be_bytes([info[0], info[1], info[2], info[3]]))
+        .collect();
+
+    let transitions = times
+        .chunks_exact(time_size)
+        .zip(indices)
+        .map(|(time, index)| {
+            let start = match time_size {
+                4 => i64::from(i32::from_be_bytes(time.try_into().ok()?)),
+                _ => i64::from_be_bytes(time.try_into().ok()?),
+            };
+            Some((start, *offsets.get(usize::from(*index))?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Zone {
+        transitions,
+        initial_offset: *offsets.first()?,
+        rule,
+    })
+}
+
+// A POSIX TZ string such as `CET-1CEST,M3.5.0,M10.5.0/3`. Only the
+// month/week/day form of the DST dates is supported, which is the one tzdata
+// uses; other zones keep the offset of their last transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    Fixed(i32),
+    Dst {
+        std_offset: i32,
+        dst_offset: i32,
+        start: RuleDate,
+        end: RuleDate,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RuleDate {
+    month: u32,
+    week: u32,
+    weekday: u32,
+    // Local wall clock seconds after midnight, may be negative or past 24h.
+    seconds: i64,
+}
+
+impl RuleDate {
+    fn parse(spec: &str) -> Option<Self> {
+        let (date, time) = spec.split_once('/').unwrap_or((spec, "2"));
+        let mut parts = date.strip_prefix('M')?.split('.');
+        let mut next = || parts.next()?.parse::<u32>().ok();
+        let (month, week, weekday) = (next()?, next()?, next()?);
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+            return None;
+        }
+        Some(Self {
+            month,
+            week,
+            weekday,
+            seconds: parse_duration(time)?,
+        })
+    }
+
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        let first = NaiveDate::from_ymd_opt(year, self.month, 1)?;
+        let first_weekday = first.weekday().num_days_from_sunday();
+        let mut day = 1 + (self.weekday + 7 - first_weekday) % 7 + (self.week - 1) * 7;
+        // Week 5 means the last such weekday of the month.
+        while NaiveDate::from_ymd_opt(year, self.month, day).is_none() {
+            day -= 7;
+        }
+        NaiveDate::from_ymd_opt(year, self.month, day)
+    }
+
+    fn utc_seconds(self, year: i32, offset: i32) -> Option<i64> {
+        let midnight = self
+            .date(year)?
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+            .timestamp();
+        Some(midnight + self.seconds - i64::from(offset))
+    }
+}
+
+impl Rule {
+    fn parse(spec: &str) -> Option<Self> {
+        let (_, rest) = take_name(spec)?;
+        let offset_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '+' | '-' | ':')))
+            .unwrap_or(rest.len());
+        // POSIX offsets count westwards.
+        let std_offset = -parse_duration(&rest[..offset_len])? as i32;
+        let rest = &rest[offset_len..];
+        if rest.is_empty() {
+            return Some(Self::Fixed(std_offset));
+        }
+
+        let (_, rest) = take_name(rest)?;
+        let (dst_spec, dates) = rest.split_once(',')?;
+        let dst_offset = match dst_spec {
+            "" => std_offset + 3600,
+            spec => -parse_duration(spec)? as i32,
+        };
+        let (start, end) = dates.split_once(',')?;
+        Some(Self::Dst {
+            std_offset,
+            dst_offset,
+            start: RuleDate::parse(start)?,
+            end: RuleDate::parse(end)?,
+        })
+    }
+
+    fn offset_at(&self, at: i64) -> i32 {
+        let (std_offset, dst_offset, start, end) = match self {
+            Self::Fixed(offset) => return *offset,
+            Self::Dst {
+                std_offset,
+                dst_offset,
+                start,
+                end,
+            } => (*std_offset, *dst_offset, *start, *end),
+        };
+
+        let Some(year) =
+            DateTime::from_timestamp(at + i64::from(std_offset), 0).map(|local| local.year())
+        else {
+            return std_offset;
+        };
+        // The start is given in standard time, the end in daylight time.
+        let (Some(dst_start), Some(dst_end)) = (
+            start.utc_seconds(year, std_offset),
+            end.utc_seconds(year, dst_offset),
+        ) else {
+            return std_offset;
+        };
+        let in_dst = if dst_start < dst_end {
+            dst_start <= at && at < dst_end
+        } else {
+            // Southern hemisphere: DST spans the new year.
+            at < dst_end || dst_start <= at
+        };
+        if in_dst {
+            dst_offset
+        } else {
+            std_offset
+        }
+    }
+}
+
+// Zone abbreviations are letters or anything quoted in angle brackets.
+fn take_name(spec: &str) -> Option<(&str, &str)> {
+    if let Some(quoted) = spec.strip_prefix('<') {
+        let end = quoted.find('>')?;
+        return Some((&quoted[..end], &quoted[end + 1..]));
+    }
+    let end = spec
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(spec.len());
+    (end >= 3).then(|| (&spec[..end], &spec[end..]))
+}
+
+// `[+-]hh[:mm[:ss]]` in seconds.
+fn parse_duration(spec: &str) -> Option<i64> {
+    let (sign, digits) = match spec.as_bytes().first()? {
+        b'-' => (-1, &spec[1..]),
+        b'+' => (1, &spec[1..]),
+        _ => (1, spec),
+    };
+    let mut seconds = 0;
+    let mut unit = 3600;
+    for part in digits.split(':') {
+        if unit == 0 || part.is_empty() || part.len() > 3 {
+            return None;
+        }
+        seconds += part.parse::<i64>().ok()? * unit;
+        unit /= 60;
+    }
+    Some(sign * seconds)
+}
+
+// `+05:30`, `-0800` or `+2`.
+fn parse_offset(spec: &str) -> Option<i32> {
+    let (sign, digits) = spec.split_at(1);
+    let normalized = match digits.len() {
+        4 if !digits.contains(':') => format!("{}:{}", &digits[..2], &digits[2..]),
+        _ => digits.to_string(),
+    };
+    let seconds = parse_duration(&normalized)? as i32;
+    Some(if sign == "-" { -seconds } else { seconds })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn rule_zone(rule: &str) -> Timezone {
+        Timezone::Zone(Zone {
+            transitions: Vec::new(),
+            initial_offset: 0,
+            rule: Rule::parse(rule),
+        })
+    }
+
+    #[test]
+    fn test_parse_fixed_offsets() {
+        let hours = |hours: i32| Some(Timezone::Fixed(FixedOffset::east_opt(hours).unwrap()));
+        assert_eq!(Timezone::parse("UTC"), hours(0));
+        assert_eq!(Timezone::parse("+05:30"), hours(5 * 3600 + 30 * 60));
+        assert_eq!(Timezone::parse("-0800"), hours(-8 * 3600));
+        assert_eq!(Timezone::parse("local"), Some(Timezone::Local));
+        assert_eq!(Timezone::parse("+5:99:99:99"), None);
+        assert_eq!(Timezone::parse("../etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_rule_switches_at_dst_changes() {
+        let berlin = rule_zone("CET-1CEST,M3.5.0,M10.5.0/3");
+        let offset = |timestamp| berlin.offset_at(utc(timestamp)).local_minus_utc() / 3600;
+
+        // 2024-03-31 02:00 CET and 2024-10-27 03:00 CEST.
+        assert_eq!(offset("2024-03-31T00:59:59Z"), 1);
+        assert_eq!(offset("2024-03-31T01:00:00Z"), 2);
+        assert_eq!(offset("2024-10-27T00:59:59Z"), 2);
+        assert_eq!(offset("2024-10-27T01:00:00Z"), 1);
+        assert_eq!(offset("2024-07-01T12:00:00Z"), 2);
+    }
+
+    #[test]
+    fn test_southern_hemisphere_rule() {
+        let sydney = rule_zone("AEST-10AEDT,M10.1.0,M4.1.0/3");
+        let offset = |timestamp| sydney.offset_at(utc(timestamp)).local_minus_utc() / 3600;
+
+        assert_eq!(offset("2024-01-15T00:00:00Z"), 11);
+        assert_eq!(offset("2024-07-15T00:00:00Z"), 10);
+        assert_eq!(offset("2024-12-15T00:00:00Z"), 11);
+    }
+
+    #[test]
+    fn test_start_of_day_uses_the_offset_at_midnight() {
+        let new_york = rule_zone("EST5EDT,M3.2.0,M11.1.0");
+
+        // DST starts on 2024-03-10 at 02:00, after midnight, so that day
+        // still starts at EST while the next one starts at EDT.
+        assert_eq!(
+            new_york.start_of_day(NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()),
+            utc("2024-03-10T05:00:00Z")
+        );
+        assert_eq!(
+            new_york.start_of_day(NaiveDate::from_ymd_opt(2024, 3, 11).unwrap()),
+            utc("2024-03-11T04:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_fixed_rule_and_quoted_names() {
+        let zone = rule_zone("<+0330>-3:30");
+        assert_eq!(
+            zone.offset_at(utc("2024-01-01T00:00:00Z"))
+                .local_minus_utc(),
+            3 * 3600 + 30 * 60
+        );
+    }
+
+    #[test]
+    fn test_system_zoneinfo() {
+        // Not every system ships the database.
+        let Some(berlin) = Timezone::parse("Europe/Berlin") else {
+            return;
+        };
+        let offset = |timestamp| berlin.offset_at(utc(timestamp)).local_minus_utc() / 3600;
+        assert_eq!(offset("1990-01-01T00:00:00Z"), 1);
+        assert_eq!(offset("1990-07-01T00:00:00Z"), 2);
+        assert_eq!(offset("2200-07-01T00:00:00Z"), 2);
+    }
+}
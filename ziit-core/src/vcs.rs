@@ -0,0 +1,499 @@
+use crate::config::{DetachedHeadFormat, NestedRepositoryPolicy};
+use std::cell::Cell;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, RwLock};
+use std::time::{Duration, Instant};
+
+const COMMAND_TIMEOUT_MILLIS: u64 = 2000;
+const COMMAND_POLL_INTERVAL_MILLIS: u64 = 10;
+
+static GIT_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+static GIT_NOT_FOUND_WARNED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // Shared by every command of one project detection, so a run of slow
+    // commands can't outlast the caller's timeout.
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+// Runs `f` with all commands it spawns on this thread killed at `deadline`,
+// and no new ones started after it.
+pub fn with_deadline<T>(deadline: Instant, f: impl FnOnce() -> T) -> T {
+    let previous = DEADLINE.replace(Some(deadline));
+    let result = f();
+    DEADLINE.set(previous);
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vcs {
+    Git,
+    Mercurial,
+    Jujutsu,
+    Subversion,
+}
+
+impl Vcs {
+    fn marker(&self) -> &'static str {
+        match self {
+            Vcs::Git => ".git",
+            Vcs::Mercurial => ".hg",
+            Vcs::Jujutsu => ".jj",
+            Vcs::Subversion => ".svn",
+        }
+    }
+}
+
+// Jujutsu comes before git so colocated repositories report bookmarks
+// instead of a detached git HEAD.
+const VCS_PRIORITY: [Vcs; 4] = [Vcs::Jujutsu, Vcs::Git, Vcs::Mercurial, Vcs::Subversion];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    pub vcs: Vcs,
+    pub root: PathBuf,
+    // Set for git repositories provided through `GIT_DIR`/`GIT_WORK_TREE`
+    // (e.g. bare dotfiles repos), which have no `.git` marker in the tree.
+    pub git_dir: Option<PathBuf>,
+}
+
+pub fn find_repository(dir: &Path, policy: NestedRepositoryPolicy) -> Option<Repository> {
+    let mut repositories = find_repositories(dir).into_iter();
+    let repository = match policy {
+        NestedRepositoryPolicy::Innermost => repositories.next(),
+        NestedRepositoryPolicy::Outermost => repositories.last(),
+    };
+    if repository.is_some() {
+        return repository;
+    }
+
+    let git_dir = std::env::var_os("GIT_DIR")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)?;
+    let work_tree = std::env::var_os("GIT_WORK_TREE")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| get_configured_work_tree(&git_dir));
+    env_repository(dir, git_dir, work_tree)
+}
+
+// All repositories containing `dir`, innermost first.
+fn find_repositories(dir: &Path) -> Vec<Repository> {
+    dir.ancestors()
+        .filter_map(|ancestor| {
+            VCS_PRIORITY
+                .into_iter()
+                .find(|vcs| ancestor.join(vcs.marker()).exists())
+                .map(|vcs| Repository {
+                    vcs,
+                    root: ancestor.to_path_buf(),
+                    git_dir: None,
+                })
+        })
+        .collect()
+}
+
+fn get_configured_work_tree(git_dir: &Path) -> Option<PathBuf> {
+    let mut command = Command::new(git_program());
+    command
+        .current_dir(git_dir)
+        .arg(format!("--git-dir={}", git_dir.display()))
+        .args(["config", "--get", "core.worktree"]);
+    let work_tree = run_output(command).filter(|stdout| !stdout.is_empty())?;
+    Some(git_dir.join(work_tree))
+}
+
+pub fn set_git_path(git_path: Option<PathBuf>) {
+    if let Ok(mut current) = GIT_PATH.write() {
+        if *current != git_path {
+            log::info!("Using git executable: {:?}", git_path);
+            *current = git_path;
+            GIT_NOT_FOUND_WARNED.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+fn git_program() -> PathBuf {
+    GIT_PATH
+        .read()
+        .ok()
+        .and_then(|path| path.clone())
+        .unwrap_or_else(|| PathBuf::from("git"))
+}
+
+pub fn git_version() -> Option<String> {
+    let mut command = Command::new(git_program());
+    command.arg("--version");
+    run_output(command).filter(|stdout| !stdout.is_empty())
+}
+
+fn env_repository(dir: &Path, git_dir: PathBuf, work_tree: Option<PathBuf>) -> Option<Repository> {
+    let work_tree = work_tree?;
+    if !dir.starts_with(&work_tree) {
+        return None;
+    }
+
+    Some(Repository {
+        vcs: Vcs::Git,
+        root: work_tree,
+        git_dir: Some(git_dir),
+    })
+}
+
+pub fn run_command(program: &str, dir: &Path, args: &[&str]) -> Option<String> {
+    run_command_output(program, dir, args).filter(|stdout| !stdout.is_empty())
+}
+
+fn run_command_output(program: &str, dir: &Path, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command.current_dir(dir).args(args);
+    run_output(command)
+}
+
+fn run_git(repo: &Repository, dir: &Path, args: &[&str]) -> Option<String> {
+    run_output(git_command(repo, dir, args)).filter(|stdout| !stdout.is_empty())
+}
+
+fn git_command(repo: &Repository, dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(git_program());
+    command
+        .current_dir(dir)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(git_dir) = &repo.git_dir {
+        command
+            .arg(format!("--git-dir={}", git_dir.display()))
+            .arg(format!("--work-tree={}", repo.root.display()));
+    }
+    command.args(args);
+    command
+}
+
+// Returns the trimmed stdout of a successful command, killing it if it runs
+// longer than COMMAND_TIMEOUT_MILLIS or past the thread's deadline.
+fn run_output(mut command: Command) -> Option<String> {
+    let mut deadline = Instant::now() + Duration::from_millis(COMMAND_TIMEOUT_MILLIS);
+    if let Some(shared) = DEADLINE.get() {
+        if Instant::now() >= shared {
+            log::debug!("Deadline passed, not running {:?}", command.get_program());
+            return None;
+        }
+        deadline = deadline.min(shared);
+    }
+
+    let spawned = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            if e.kind() == ErrorKind::NotFound {
+                warn_program_not_found(command.get_program());
+            }
+            return None;
+        }
+    };
+
+    let mut stdout = child.stdout.take()?;
+    let (stdout_tx, stdout_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = stdout_tx.send(stdout.read_to_end(&mut buffer).map(|_| buffer));
+    });
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                log::warn!("Command {:?} timed out, killing it", command.get_program());
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(COMMAND_POLL_INTERVAL_MILLIS)),
+            Err(_) => return None,
+        }
+    };
+
+    // Background processes the command left behind may keep stdout open.
+    let stdout = stdout_rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .ok()?
+        .ok()?;
+    if !status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8(stdout).ok()?;
+    Some(stdout.trim().to_string())
+}
+
+fn warn_program_not_found(program: &std::ffi::OsStr) {
+    if program != git_program().as_os_str() {
+        log::debug!("{:?} not found, skipping", program);
+        return;
+    }
+    if !GIT_NOT_FOUND_WARNED.swap(true, Ordering::Relaxed) {
+        log::warn!(
+            "git executable {:?} could not be found; project and branch detection will be \
+             limited. Set \"gitPath\" in the Ziit config to the full path of git.",
+            program
+        );
+    }
+}
+
+pub fn get_remote_url(repo: &Repository, dir: &Path) -> Option<String> {
+    match repo.vcs {
+        Vcs::Git => run_git(repo, dir, &["config", "--get", "remote.origin.url"]),
+        Vcs::Mercurial => run_command("hg", dir, &["paths", "default"]),
+        Vcs::Jujutsu => {
+            let remotes = run_command("jj", dir, &["git", "remote", "list"])?;
+            parse_jj_remote_list(&remotes)
+        }
+        Vcs::Subversion => run_command("svn", dir, &["info", "--show-item", "repos-root-url"]),
+    }
+}
+
+pub fn get_branch(repo: &Repository, dir: &Path) -> Option<String> {
+    match repo.vcs {
+        Vcs::Git => {
+            let branch = run_git(repo, dir, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+            (branch != "HEAD").then_some(branch)
+        }
+        Vcs::Mercurial => run_command("hg", dir, &["log", "-r", ".", "-T", "{activebookmark}"])
+            .or_else(|| run_command("hg", dir, &["branch"])),
+        Vcs::Jujutsu => ["@", "@-"].iter().find_map(|revision| {
+            let bookmarks = run_command(
+                "jj",
+                dir,
+                &[
+                    "log",
+                    "--no-graph",
+                    "--ignore-working-copy",
+                    "-r",
+                    revision,
+                    "-T",
+                    "bookmarks",
+                ],
+            )?;
+            parse_jj_bookmarks(&bookmarks)
+        }),
+        Vcs::Subversion => {
+            let relative_url = run_command("svn", dir, &["info", "--show-item", "relative-url"])?;
+            parse_svn_branch(&relative_url)
+        }
+    }
+}
+
+pub fn get_git_detached_head(
+    repo: &Repository,
+    dir: &Path,
+    format: DetachedHeadFormat,
+) -> Option<String> {
+    if format == DetachedHeadFormat::Tag {
+        if let Some(tag) = run_git(repo, dir, &["describe", "--tags", "--abbrev=0", "HEAD"]) {
+            return Some(tag);
+        }
+    }
+    run_git(repo, dir, &["rev-parse", "--short", "HEAD"])
+}
+
+pub fn get_changed_file_count(repo: &Repository, dir: &Path) -> Option<usize> {
+    let status = match repo.vcs {
+        Vcs::Git => run_output(git_command(repo, dir, &["status", "--porcelain"])),
+        Vcs::Mercurial => run_command_output("hg", dir, &["status"]),
+        Vcs::Jujutsu => run_command_output("jj", dir, &["diff", "--summary"]),
+        Vcs::Subversion => run_command_output("svn", dir, &["status", "-q"]),
+    }?;
+    Some(
+        status
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count(),
+    )
+}
+
+fn parse_jj_remote_list(output: &str) -> Option<String> {
+    let mut remotes = output.lines().filter_map(|line| line.split_once(' '));
+    let first = remotes.clone().next();
+    remotes
+        .find(|(name, _)| *name == "origin")
+        .or(first)
+        .map(|(_, url)| url.trim().to_string())
+}
+
+fn parse_jj_bookmarks(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .next()
+        .map(|bookmark| bookmark.trim_end_matches(['*', '?']).to_string())
+        .filter(|bookmark| !bookmark.is_empty())
+}
+
+fn parse_svn_branch(relative_url: &str) -> Option<String> {
+    let mut segments = relative_url
+        .trim_start_matches('^')
+        .split('/')
+        .filter(|segment| !segment.is_empty());
+    while let Some(segment) = segments.next() {
+        match segment {
+            "trunk" => return Some("trunk".to_string()),
+            "branches" | "tags" => return segments.next().map(|name| name.to_string()),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_svn_branch() {
+        assert_eq!(parse_svn_branch("^/trunk/src"), Some("trunk".to_string()));
+        assert_eq!(
+            parse_svn_branch("^/project/branches/feature-x/src"),
+            Some("feature-x".to_string())
+        );
+        assert_eq!(parse_svn_branch("^/tags/v1.0"), Some("v1.0".to_string()));
+        assert_eq!(parse_svn_branch("^/project/src"), None);
+    }
+
+    #[test]
+    fn test_parse_jj_output() {
+        assert_eq!(
+            parse_jj_remote_list(
+                "upstream https://github.com/a/b\norigin git@github.com:c/d.git\n"
+            ),
+            Some("git@github.com:c/d.git".to_string())
+        );
+        assert_eq!(
+            parse_jj_bookmarks("main* feature"),
+            Some("main".to_string())
+        );
+        assert_eq!(parse_jj_bookmarks(""), None);
+    }
+
+    #[test]
+    fn test_get_git_detached_head() {
+        let root = std::env::temp_dir().join(format!("ziit-detached-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .current_dir(&root)
+                .args(["-c", "user.name=ziit", "-c", "user.email=ziit@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "initial"]);
+        git(&["tag", "v1.2.3"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+        git(&["checkout", "-q", "--detach", "HEAD"]);
+
+        let repo = find_repository(&root, NestedRepositoryPolicy::Innermost).unwrap();
+        assert_eq!(get_branch(&repo, &root), None);
+        let sha = get_git_detached_head(&repo, &root, DetachedHeadFormat::Sha).unwrap();
+        assert!(sha.len() >= 7 && sha.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(
+            get_git_detached_head(&repo, &root, DetachedHeadFormat::Tag),
+            Some("v1.2.3".to_string())
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_timeout() {
+        let dir = std::env::temp_dir();
+        let started = Instant::now();
+        assert_eq!(run_command("sleep", &dir, &["10"]), None);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            run_command("echo", &dir, &["hello"]),
+            Some("hello".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commands_share_the_deadline() {
+        let dir = std::env::temp_dir();
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(500);
+        with_deadline(deadline, || {
+            assert_eq!(run_command("sleep", &dir, &["10"]), None);
+            assert_eq!(run_command("echo", &dir, &["hello"]), None);
+        });
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_env_repository() {
+        let git_dir = PathBuf::from("/home/user/.dotfiles");
+        let work_tree = Some(PathBuf::from("/home/user"));
+
+        let repo = env_repository(
+            Path::new("/home/user/.config/nvim"),
+            git_dir.clone(),
+            work_tree.clone(),
+        )
+        .unwrap();
+        assert_eq!(repo.root, PathBuf::from("/home/user"));
+        assert_eq!(repo.git_dir, Some(git_dir.clone()));
+
+        assert_eq!(
+            env_repository(Path::new("/srv/other"), git_dir.clone(), work_tree),
+            None
+        );
+        assert_eq!(env_repository(Path::new("/home/user"), git_dir, None), None);
+    }
+
+    #[test]
+    fn test_get_changed_file_count() {
+        let root = std::env::temp_dir().join(format!("ziit-dirty-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        Command::new("git")
+            .current_dir(&root)
+            .args(["init", "-q"])
+            .output()
+            .unwrap();
+
+        let repo = find_repository(&root, NestedRepositoryPolicy::Innermost).unwrap();
+        assert_eq!(get_changed_file_count(&repo, &root), Some(0));
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+        std::fs::write(root.join("b.txt"), "b").unwrap();
+        assert_eq!(get_changed_file_count(&repo, &root), Some(2));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_repository() {
+        let root = std::env::temp_dir().join(format!("ziit-vcs-{}", std::process::id()));
+        let nested = root.join("vendor").join("lib");
+        std::fs::create_dir_all(root.join(".hg")).unwrap();
+        std::fs::create_dir_all(nested.join(".svn")).unwrap();
+
+        let innermost = NestedRepositoryPolicy::Innermost;
+        let outermost = NestedRepositoryPolicy::Outermost;
+        let repo = find_repository(&root.join("src"), innermost).unwrap();
+        assert_eq!((repo.vcs, repo.root), (Vcs::Mercurial, root.clone()));
+        let repo = find_repository(&nested.join("src"), innermost).unwrap();
+        assert_eq!((repo.vcs, repo.root), (Vcs::Subversion, nested.clone()));
+        let repo = find_repository(&nested.join("src"), outermost).unwrap();
+        assert_eq!((repo.vcs, repo.root), (Vcs::Mercurial, root.clone()));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use ziit_core::api::{send_batch_heartbeats_request, send_heartbeat_request};
+use ziit_core::heartbeat::{ActivityEvent, EntityType, Heartbeat, HeartbeatManager, SyncState};
+
+const EMPTY_SUMMARY: &str = r#"{"summaries":[],"timezone":"UTC"}"#;
+
+#[derive(Debug, Clone)]
+struct Recorded {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+// A tiny HTTP/1.1 server standing in for an older Ziit instance. Heartbeat and
+// batch requests are answered from a script of status codes (200 once it runs
+// out), the stats endpoint always returns an empty summary and any other GET
+// is unknown.
+#[derive(Clone)]
+struct MockServer {
+    base_url: String,
+    script: Arc<Mutex<VecDeque<u16>>>,
+    requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl MockServer {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = Self {
+            base_url: format!("http://{}", listener.local_addr().unwrap()),
+            script: Arc::default(),
+            requests: Arc::default(),
+        };
+
+        let handler = server.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tokio::spawn(async move { handler.serve(stream).await });
+            }
+        });
+        server
+    }
+
+    fn respond_with(&self, statuses: &[u16]) {
+        self.script.lock().unwrap().extend(statuses);
+    }
+
+    fn requests_to(&self, path: &str) -> Vec<Recorded> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.path.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    async fn serve(&self, mut stream: TcpStream) {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+        let header_end = loop {
+            let read = stream.read(&mut buffer).await.unwrap_or(0);
+            if read == 0 {
+                return;
+            }
+            data.extend_from_slice(&buffer[..read]);
+            if let Some(position) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+                break position + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default().to_string();
+        let path = request_line.next().unwrap_or_default().to_string();
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let content_length: usize = header("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        while data.len() < header_end + content_length {
+            let read = stream.read(&mut buffer).await.unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer[..read]);
+        }
+        let body = String::from_utf8_lossy(&data[header_end..]).to_string();
+
+        let (status, response_body) = if path.starts_with("/api/external/stats") {
+            (200, EMPTY_SUMMARY.to_string())
+        } else if method != "POST" {
+            (404, "{}".to_string())
+        } else {
+            let status = self.script.lock().unwrap().pop_front().unwrap_or(200);
+            (status, "{}".to_string())
+        };
+        self.requests.lock().unwrap().push(Recorded {
+            method,
+            path,
+            authorization: header("authorization"),
+            body,
+        });
+
+        let response = format!(
+            "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+            response_body.len()
+        );
+        stream.write_all(response.as_bytes()).await.ok();
+        stream.shutdown().await.ok();
+    }
+}
+
+fn heartbeat(file: &str) -> Heartbeat {
+    serde_json::from_value(serde_json::json!({
+        "timestamp": "2024-01-01T10:00:00Z",
+        "project": "ziit",
+        "language": "Rust",
+        "file": file,
+        "branch": "main",
+        "editor": "Zed",
+        "os": "linux",
+    }))
+    .unwrap()
+}
+
+async fn wait_for(
+    sync_state: &mut watch::Receiver<SyncState>,
+    condition: impl Fn(&SyncState) -> bool,
+) -> SyncState {
+    tokio::time::timeout(Duration::from_secs(10), sync_state.wait_for(condition))
+        .await
+        .expect("timed out waiting for the sync state")
+        .map(|state| *state)
+        .unwrap()
+}
+
+// The flags are published before the heartbeat lands in the queue.
+async fn wait_for_queued(manager: &HeartbeatManager, expected: usize) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while manager.status_snapshot().await.queued_heartbeats != expected {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for the offline queue");
+}
+
+#[tokio::test]
+async fn test_send_heartbeat_request_posts_json_with_bearer_token() {
+    let server = MockServer::start().await;
+
+    send_heartbeat_request(&server.base_url, "secret-key", heartbeat("main.rs"))
+        .await
+        .unwrap();
+
+    let requests = server.requests_to("/api/external/heartbeats");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(
+        requests[0].authorization.as_deref(),
+        Some("Bearer secret-key")
+    );
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["file"], "main.rs");
+    assert_eq!(body["project"], "ziit");
+}
+
+#[tokio::test]
+async fn test_batch_request_sends_all_heartbeats() {
+    let server = MockServer::start().await;
+
+    send_batch_heartbeats_request(
+        &server.base_url,
+        "secret-key",
+        vec![heartbeat("a.rs"), heartbeat("b.rs")],
+    )
+    .await
+    .unwrap();
+
+    let requests = server.requests_to("/api/external/batch");
+    assert_eq!(requests.len(), 1);
+    let body: Vec<serde_json::Value> = serde_json::from_str(&requests[0].body).unwrap();
+    let files: Vec<_> = body.iter().map(|heartbeat| &heartbeat["file"]).collect();
+    assert_eq!(files, ["a.rs", "b.rs"]);
+}
+
+#[tokio::test]
+async fn test_error_statuses_are_reported() {
+    let server = MockServer::start().await;
+    server.respond_with(&[401, 429, 500, 500]);
+
+    for status in [401, 429, 500] {
+        let error = send_heartbeat_request(&server.base_url, "key", heartbeat("main.rs"))
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains(&status.to_string()),
+            "{status}: {error}"
+        );
+    }
+    let error = send_batch_heartbeats_request(&server.base_url, "key", vec![heartbeat("a.rs")])
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("500"), "{error}");
+}
+
+// The only test touching the environment, so the config directory it points
+// at cannot leak into the other tests.
+#[tokio::test]
+async fn test_manager_goes_offline_and_recovers() {
+    let server = MockServer::start().await;
+    let home = std::env::temp_dir().join(format!("ziit-mock-server-{}", std::process::id()));
+    std::fs::remove_dir_all(&home).ok();
+    let config_dir = home.join("config");
+    std::fs::create_dir_all(config_dir.join("ziit")).unwrap();
+    std::fs::write(
+        config_dir.join("ziit").join("config.json"),
+        serde_json::json!({ "apiKey": "secret-key", "baseUrl": server.base_url }).to_string(),
+    )
+    .unwrap();
+    std::env::set_var("HOME", &home);
+    std::env::set_var("XDG_CONFIG_HOME", &config_dir);
+
+    let file = home.join("main.rs");
+    std::fs::write(&file, "fn main() {}\n").unwrap();
+    let activity = || ActivityEvent {
+        file_path: Some(file.to_string_lossy().into_owned()),
+        language_id: Some("rust".to_string()),
+        force_send: true,
+        entity_type: EntityType::File,
+        is_write: true,
+        cursor: None,
+    };
+
+    let manager = Arc::new(HeartbeatManager::new(Vec::<PathBuf>::new()).await.unwrap());
+    let mut sync_state = manager.subscribe_sync_state();
+    let _tasks = manager.start_background_tasks();
+
+    // A server error takes the manager offline and queues the heartbeat.
+    server.respond_with(&[500]);
+    manager.record_activity(activity());
+    let state = wait_for(&mut sync_state, |state| !state.online).await;
+    assert!(state.api_key_valid);
+    wait_for_queued(&manager, 1).await;
+
+    // The next offline sync gets through and brings it back online.
+    manager.sync_offline_heartbeats().await.unwrap();
+    wait_for(&mut sync_state, |state| state.online).await;
+    assert_eq!(manager.status_snapshot().await.queued_heartbeats, 0);
+    let batches = server.requests_to("/api/external/batch");
+    assert_eq!(batches.len(), 1);
+    let body: Vec<serde_json::Value> = serde_json::from_str(&batches[0].body).unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0]["file"], "main.rs");
+    // Without a schema endpoint the server only gets the v1 shape.
+    assert!(body[0].get("userAgent").is_none());
+
+    // A rejected key is flagged separately from being offline.
+    server.respond_with(&[401]);
+    manager.record_activity(activity());
+    let state = wait_for(&mut sync_state, |state| !state.api_key_valid).await;
+    assert!(!state.online);
+    wait_for_queued(&manager, 1).await;
+
+    std::fs::remove_dir_all(&home).ok();
+}
@@ -0,0 +1,233 @@
+use std::io::IsTerminal;
+use std::path::Path;
+
+use chrono::Utc;
+use ziit_core::api::{fetch_daily_summary_request, fetch_server_time};
+use ziit_core::commands::get_config_status;
+use ziit_core::heartbeat::inspect_offline_queue;
+
+use crate::i18n::{self, Locale, Message};
+
+// How far the local clock may be off from the server before durations suffer.
+const MAX_CLOCK_DRIFT_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+struct Check {
+    name: String,
+    outcome: Outcome,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: String, outcome: Outcome, detail: String) -> Self {
+        Self {
+            name,
+            outcome,
+            detail,
+        }
+    }
+}
+
+async fn run_checks(locale: Locale) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let text = |message: Message| message.text(locale);
+
+    let status = match get_config_status().await {
+        Ok(status) => status,
+        Err(e) => {
+            checks.push(Check::new(
+                text(Message::DoctorConfig),
+                Outcome::Fail,
+                text(Message::DoctorUnreadable {
+                    error: &e.to_string(),
+                }),
+            ));
+            return checks;
+        }
+    };
+    let config_path = Path::new(&status.config_path);
+    checks.push(if config_path.exists() {
+        Check::new(
+            text(Message::DoctorConfig),
+            Outcome::Pass,
+            status.config_path.clone(),
+        )
+    } else {
+        Check::new(
+            text(Message::DoctorConfig),
+            Outcome::Warn,
+            text(Message::DoctorConfigMissing {
+                path: &status.config_path,
+            }),
+        )
+    });
+
+    let base_url = status.base_url.as_str();
+    let api_key = ziit_core::config::get_api_key().await.ok().flatten();
+    let api_check = match &api_key {
+        None => (Outcome::Fail, text(Message::DoctorApiKeyMissing)),
+        Some(api_key) => match fetch_daily_summary_request(base_url, api_key).await {
+            Ok(_) => (
+                Outcome::Pass,
+                text(Message::DoctorAuthenticated { base_url }),
+            ),
+            Err(e) if e.to_string().contains("401") => (
+                Outcome::Fail,
+                text(Message::DoctorApiKeyRefused { base_url }),
+            ),
+            Err(e) => (
+                Outcome::Fail,
+                text(Message::DoctorUnreachable {
+                    base_url,
+                    error: &e.to_string(),
+                }),
+            ),
+        },
+    };
+    checks.push(Check::new(
+        text(Message::DoctorApi),
+        api_check.0,
+        api_check.1,
+    ));
+
+    if let Some(config_dir) = config_path.parent() {
+        let probe = config_dir.join(".doctor-probe");
+        let writable = tokio::fs::create_dir_all(config_dir).await.is_ok()
+            && tokio::fs::write(&probe, b"ok").await.is_ok();
+        tokio::fs::remove_file(&probe).await.ok();
+        let path = config_dir.display().to_string();
+        checks.push(if writable {
+            Check::new(text(Message::DoctorDataDirectory), Outcome::Pass, path)
+        } else {
+            Check::new(
+                text(Message::DoctorDataDirectory),
+                Outcome::Fail,
+                text(Message::DoctorNotWritable { path: &path }),
+            )
+        });
+    }
+
+    checks.push(match &status.git_version {
+        Some(version) => Check::new(text(Message::DoctorGit), Outcome::Pass, version.clone()),
+        None => Check::new(
+            text(Message::DoctorGit),
+            Outcome::Warn,
+            text(Message::DoctorGitMissing),
+        ),
+    });
+
+    checks.push(match inspect_offline_queue().await {
+        Ok(health) if health.unreadable > 0 => Check::new(
+            text(Message::DoctorOfflineQueue),
+            Outcome::Fail,
+            text(Message::DoctorQueueUnreadable {
+                count: health.unreadable,
+            }),
+        ),
+        Ok(health) => Check::new(
+            text(Message::DoctorOfflineQueue),
+            Outcome::Pass,
+            text(Message::DoctorQueueWaiting {
+                total: health.queued + health.spilled,
+                spilled: health.spilled,
+            }),
+        ),
+        Err(e) => Check::new(
+            text(Message::DoctorOfflineQueue),
+            Outcome::Fail,
+            text(Message::DoctorUnreadable {
+                error: &e.to_string(),
+            }),
+        ),
+    });
+
+    checks.push(match fetch_server_time(base_url).await {
+        Ok(server_time) => {
+            let drift = (Utc::now() - server_time).num_seconds();
+            let outcome = if drift.abs() > MAX_CLOCK_DRIFT_SECONDS {
+                Outcome::Fail
+            } else {
+                Outcome::Pass
+            };
+            Check::new(
+                text(Message::DoctorClock),
+                outcome,
+                text(Message::DoctorClockDrift { seconds: drift }),
+            )
+        }
+        Err(e) => Check::new(
+            text(Message::DoctorClock),
+            Outcome::Warn,
+            text(Message::DoctorClockUnknown {
+                error: &e.to_string(),
+            }),
+        ),
+    });
+
+    checks
+}
+
+fn format_check(check: &Check, locale: Locale, color: bool) -> String {
+    let (label, code) = match check.outcome {
+        Outcome::Pass => (Message::DoctorPass, "32"),
+        Outcome::Warn => (Message::DoctorWarn, "33"),
+        Outcome::Fail => (Message::DoctorFail, "31"),
+    };
+    let label = label.text(locale);
+    let label = if color {
+        format!("\x1b[{code}m{label}\x1b[0m")
+    } else {
+        label
+    };
+    format!("[{}] {}: {}", label, check.name, check.detail)
+}
+
+// Prints the report and returns whether every check passed or only warned.
+pub async fn run() -> bool {
+    // The report is the output, logs only when explicitly asked for.
+    if std::env::var_os("RUST_LOG").is_none() {
+        log::set_max_level(log::LevelFilter::Error);
+    }
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let locale = i18n::resolve(i18n::environment_locale().as_deref()).await;
+    let title = Message::DoctorTitle {
+        version: env!("CARGO_PKG_VERSION"),
+    };
+    println!("{}", title.text(locale));
+
+    let checks = run_checks(locale).await;
+    for check in &checks {
+        println!("{}", format_check(check, locale, color));
+    }
+    checks.iter().all(|check| check.outcome != Outcome::Fail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_check() {
+        let check = Check::new("Git".to_string(), Outcome::Warn, "not found".to_string());
+
+        assert_eq!(
+            format_check(&check, Locale::En, false),
+            "[WARN] Git: not found"
+        );
+        assert_eq!(
+            format_check(&check, Locale::En, true),
+            "[\x1b[33mWARN\x1b[0m] Git: not found"
+        );
+        assert_eq!(
+            format_check(&check, Locale::De, false),
+            "[WARNUNG] Git: not found"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use ziit_core::config::read_config_file;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl Locale {
+    // Accepts BCP 47 tags such as "de-CH" or POSIX ones such as "fr_FR.UTF-8".
+    pub(crate) fn parse(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            "fr" => Some(Self::Fr),
+            "es" => Some(Self::Es),
+            _ => None,
+        }
+    }
+}
+
+// What the CLI uses in place of a client locale, in POSIX precedence order.
+pub(crate) fn environment_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+// The `locale` config option wins over the one the client sent in
+// `InitializeParams`, anything unsupported falls back to English.
+pub(crate) async fn resolve(client_locale: Option<&str>) -> Locale {
+    let configured = read_config_file()
+        .await
+        .ok()
+        .and_then(|config| config.locale);
+    configured
+        .as_deref()
+        .into_iter()
+        .chain(client_locale)
+        .find_map(Locale::parse)
+        .unwrap_or_default()
+}
+
+pub(crate) enum Message<'a> {
+    ApiKeyAccepted,
+    ApiKeyRejected,
+    BackOnline,
+    Offline,
+    DryRun {
+        path: &'a str,
+    },
+    HistoryExported {
+        path: &'a str,
+    },
+    Imported {
+        count: usize,
+    },
+    ImportFailed {
+        error: &'a str,
+    },
+    Status {
+        config_path: &'a str,
+        has_api_key: bool,
+        base_url: &'a str,
+        git_version: Option<&'a str>,
+    },
+    StatusToday {
+        duration: &'a str,
+        as_of: &'a str,
+    },
+    StatusMirror {
+        base_url: &'a str,
+        online: bool,
+        queued: usize,
+    },
+    DoctorTitle {
+        version: &'a str,
+    },
+    DoctorPass,
+    DoctorWarn,
+    DoctorFail,
+    DoctorConfig,
+    DoctorApi,
+    DoctorDataDirectory,
+    DoctorGit,
+    DoctorOfflineQueue,
+    DoctorClock,
+    DoctorUnreadable {
+        error: &'a str,
+    },
+    DoctorConfigMissing {
+        path: &'a str,
+    },
+    DoctorApiKeyMissing,
+    DoctorAuthenticated {
+        base_url: &'a str,
+    },
+    DoctorApiKeyRefused {
+        base_url: &'a str,
+    },
+    DoctorUnreachable {
+        base_url: &'a str,
+        error: &'a str,
+    },
+    DoctorNotWritable {
+        path: &'a str,
+    },
+    DoctorGitMissing,
+    DoctorQueueUnreadable {
+        count: usize,
+    },
+    DoctorQueueWaiting {
+        total: usize,
+        spilled: usize,
+    },
+    DoctorClockDrift {
+        seconds: i64,
+    },
+    DoctorClockUnknown {
+        error: &'a str,
+    },
+}
+
+impl Message<'_> {
+    pub(crate) fn text(&self, locale: Locale) -> String {
+        use Locale::*;
+        match *self {
+            Self::ApiKeyAccepted => match locale {
+                En => "Ziit: API key accepted, syncing queued time.",
+                De => "Ziit: API-Schlüssel akzeptiert, gespeicherte Zeit wird synchronisiert.",
+                Fr => "Ziit : clé API acceptée, synchronisation du temps en attente.",
+                Es => "Ziit: clave de API aceptada, sincronizando el tiempo en cola.",
+            }
+            .to_string(),
+            Self::ApiKeyRejected => match locale {
+                En => "Ziit: the API key was rejected. Your time is queued locally until it is fixed.",
+                De => "Ziit: Der API-Schlüssel wurde abgelehnt. Deine Zeit wird lokal gespeichert, bis er korrigiert ist.",
+                Fr => "Ziit : la clé API a été refusée. Votre temps est conservé localement jusqu'à sa correction.",
+                Es => "Ziit: la clave de API fue rechazada. Tu tiempo se guarda localmente hasta que se corrija.",
+            }
+            .to_string(),
+            Self::BackOnline => match locale {
+                En => "Ziit: back online, syncing queued time.",
+                De => "Ziit: wieder online, gespeicherte Zeit wird synchronisiert.",
+                Fr => "Ziit : de nouveau en ligne, synchronisation du temps en attente.",
+                Es => "Ziit: de nuevo en línea, sincronizando el tiempo en cola.",
+            }
+            .to_string(),
+            Self::Offline => match locale {
+                En => "Ziit: the server is unreachable. Your time is queued locally for now.",
+                De => "Ziit: Der Server ist nicht erreichbar. Deine Zeit wird vorerst lokal gespeichert.",
+                Fr => "Ziit : le serveur est injoignable. Votre temps est conservé localement pour le moment.",
+                Es => "Ziit: no se puede acceder al servidor. Por ahora tu tiempo se guarda localmente.",
+            }
+            .to_string(),
+            Self::DryRun { path } => match locale {
+                En => format!("Ziit: dry run, heartbeats are written to {path} instead of being sent."),
+                De => format!("Ziit: Probelauf, Heartbeats werden nach {path} geschrieben statt gesendet."),
+                Fr => format!("Ziit : simulation, les heartbeats sont écrits dans {path} au lieu d'être envoyés."),
+                Es => format!("Ziit: simulación, los heartbeats se escriben en {path} en lugar de enviarse."),
+            },
+            Self::HistoryExported { path } => match locale {
+                En => format!("Ziit: history exported to {path}"),
+                De => format!("Ziit: Verlauf nach {path} exportiert"),
+                Fr => format!("Ziit : historique exporté vers {path}"),
+                Es => format!("Ziit: historial exportado a {path}"),
+            },
+            Self::Imported { count } => match locale {
+                En => format!("Ziit: imported {count} heartbeats from WakaTime"),
+                De => format!("Ziit: {count} Heartbeats aus WakaTime importiert"),
+                Fr => format!("Ziit : {count} heartbeats importés depuis WakaTime"),
+                Es => format!("Ziit: {count} heartbeats importados desde WakaTime"),
+            },
+            Self::ImportFailed { error } => match locale {
+                En => format!("Ziit: Failed to import WakaTime heartbeats: {error}"),
+                De => format!("Ziit: WakaTime-Heartbeats konnten nicht importiert werden: {error}"),
+                Fr => format!("Ziit : échec de l'import des heartbeats WakaTime : {error}"),
+                Es => format!("Ziit: no se pudieron importar los heartbeats de WakaTime: {error}"),
+            },
+            Self::Status {
+                config_path,
+                has_api_key,
+                base_url,
+                git_version,
+            } => {
+                let (config, api_key, set, not_set, url, git, git_missing) = match locale {
+                    En => ("Config", "API Key", "Set", "Not Set", "Base URL", "Git", "Not found (set gitPath in config)"),
+                    De => ("Konfiguration", "API-Schlüssel", "Gesetzt", "Nicht gesetzt", "Basis-URL", "Git", "Nicht gefunden (gitPath in der Konfiguration setzen)"),
+                    Fr => ("Configuration", "Clé API", "Définie", "Non définie", "URL de base", "Git", "Introuvable (définir gitPath dans la configuration)"),
+                    Es => ("Configuración", "Clave de API", "Configurada", "Sin configurar", "URL base", "Git", "No encontrado (define gitPath en la configuración)"),
+                };
+                format!(
+                    "{config}: {config_path}\n{api_key}: {}\n{url}: {base_url}\n{git}: {}",
+                    if has_api_key { set } else { not_set },
+                    git_version.unwrap_or(git_missing)
+                )
+            }
+            Self::StatusToday { duration, as_of } => match locale {
+                En => format!("Today: {duration} (as of {as_of})"),
+                De => format!("Heute: {duration} (Stand {as_of})"),
+                Fr => format!("Aujourd'hui : {duration} (à {as_of})"),
+                Es => format!("Hoy: {duration} (a las {as_of})"),
+            },
+            Self::StatusMirror {
+                base_url,
+                online,
+                queued,
+            } => {
+                let (mirror, state, queued_label) = match (locale, online) {
+                    (En, true) => ("Mirror", "online", "queued"),
+                    (En, false) => ("Mirror", "offline", "queued"),
+                    (De, true) => ("Spiegel", "online", "in der Warteschlange"),
+                    (De, false) => ("Spiegel", "offline", "in der Warteschlange"),
+                    (Fr, true) => ("Miroir", "en ligne", "en attente"),
+                    (Fr, false) => ("Miroir", "hors ligne", "en attente"),
+                    (Es, true) => ("Espejo", "en línea", "en cola"),
+                    (Es, false) => ("Espejo", "sin conexión", "en cola"),
+                };
+                format!("{mirror} {base_url}: {state}, {queued} {queued_label}")
+            }
+            Self::DoctorTitle { version } => match locale {
+                En => format!("Ziit doctor v{version}"),
+                De => format!("Ziit-Diagnose v{version}"),
+                Fr => format!("Diagnostic Ziit v{version}"),
+                Es => format!("Diagnóstico de Ziit v{version}"),
+            },
+            Self::DoctorPass => match locale {
+                En => "PASS",
+                De | Fr | Es => "OK",
+            }
+            .to_string(),
+            Self::DoctorWarn => match locale {
+                En => "WARN",
+                De => "WARNUNG",
+                Fr => "AVERTISSEMENT",
+                Es => "AVISO",
+            }
+            .to_string(),
+            Self::DoctorFail => match locale {
+                En => "FAIL",
+                De => "FEHLER",
+                Fr => "ÉCHEC",
+                Es => "ERROR",
+            }
+            .to_string(),
+            Self::DoctorConfig => match locale {
+                En => "Config",
+                De => "Konfiguration",
+                Fr => "Configuration",
+                Es => "Configuración",
+            }
+            .to_string(),
+            Self::DoctorApi => "API".to_string(),
+            Self::DoctorDataDirectory => match locale {
+                En => "Data directory",
+                De => "Datenverzeichnis",
+                Fr => "Répertoire de données",
+                Es => "Directorio de datos",
+            }
+            .to_string(),
+            Self::DoctorGit => "Git".to_string(),
+            Self::DoctorOfflineQueue => match locale {
+                En => "Offline queue",
+                De => "Offline-Warteschlange",
+                Fr => "File d'attente hors ligne",
+                Es => "Cola sin conexión",
+            }
+            .to_string(),
+            Self::DoctorClock => match locale {
+                En => "Clock",
+                De => "Uhr",
+                Fr => "Horloge",
+                Es => "Reloj",
+            }
+            .to_string(),
+            Self::DoctorUnreadable { error } => match locale {
+                En => format!("could not be read: {error}"),
+                De => format!("konnte nicht gelesen werden: {error}"),
+                Fr => format!("illisible : {error}"),
+                Es => format!("no se pudo leer: {error}"),
+            },
+            Self::DoctorConfigMissing { path } => match locale {
+                En => format!("{path} does not exist, using defaults"),
+                De => format!("{path} existiert nicht, Standardwerte werden verwendet"),
+                Fr => format!("{path} n'existe pas, valeurs par défaut utilisées"),
+                Es => format!("{path} no existe, se usan los valores predeterminados"),
+            },
+            Self::DoctorApiKeyMissing => match locale {
+                En => "no API key set, run ziit.setApiKey",
+                De => "kein API-Schlüssel gesetzt, ziit.setApiKey ausführen",
+                Fr => "aucune clé API définie, exécutez ziit.setApiKey",
+                Es => "no hay clave de API, ejecuta ziit.setApiKey",
+            }
+            .to_string(),
+            Self::DoctorAuthenticated { base_url } => match locale {
+                En => format!("authenticated against {base_url}"),
+                De => format!("bei {base_url} angemeldet"),
+                Fr => format!("authentifié auprès de {base_url}"),
+                Es => format!("autenticado en {base_url}"),
+            },
+            Self::DoctorApiKeyRefused { base_url } => match locale {
+                En => format!("{base_url} rejected the API key"),
+                De => format!("{base_url} hat den API-Schlüssel abgelehnt"),
+                Fr => format!("{base_url} a refusé la clé API"),
+                Es => format!("{base_url} rechazó la clave de API"),
+            },
+            Self::DoctorUnreachable { base_url, error } => match locale {
+                En => format!("could not reach {base_url}: {error}"),
+                De => format!("{base_url} nicht erreichbar: {error}"),
+                Fr => format!("impossible de joindre {base_url} : {error}"),
+                Es => format!("no se pudo acceder a {base_url}: {error}"),
+            },
+            Self::DoctorNotWritable { path } => match locale {
+                En => format!("{path} is not writable"),
+                De => format!("{path} ist nicht beschreibbar"),
+                Fr => format!("{path} n'est pas accessible en écriture"),
+                Es => format!("{path} no tiene permisos de escritura"),
+            },
+            Self::DoctorGitMissing => match locale {
+                En => "not found, branches will not be detected (set gitPath)",
+                De => "nicht gefunden, Branches werden nicht erkannt (gitPath setzen)",
+                Fr => "introuvable, les branches ne seront pas détectées (définir gitPath)",
+                Es => "no encontrado, no se detectarán las ramas (define gitPath)",
+            }
+            .to_string(),
+            Self::DoctorQueueUnreadable { count } => match locale {
+                En => format!("{count} unreadable entries, they are quarantined on the next start"),
+                De => format!("{count} unlesbare Einträge, sie werden beim nächsten Start in Quarantäne verschoben"),
+                Fr => format!("{count} entrées illisibles, elles seront mises en quarantaine au prochain démarrage"),
+                Es => format!("{count} entradas ilegibles, se pondrán en cuarentena en el próximo inicio"),
+            },
+            Self::DoctorQueueWaiting { total, spilled } => match locale {
+                En => format!("{total} heartbeats waiting ({spilled} spilled to disk)"),
+                De => format!("{total} Heartbeats warten ({spilled} auf die Festplatte ausgelagert)"),
+                Fr => format!("{total} heartbeats en attente ({spilled} déchargés sur le disque)"),
+                Es => format!("{total} heartbeats en espera ({spilled} volcados al disco)"),
+            },
+            Self::DoctorClockDrift { seconds } => match locale {
+                En => format!("{seconds} seconds off from the server"),
+                De => format!("{seconds} Sekunden Abweichung vom Server"),
+                Fr => format!("{seconds} secondes d'écart avec le serveur"),
+                Es => format!("{seconds} segundos de diferencia con el servidor"),
+            },
+            Self::DoctorClockUnknown { error } => match locale {
+                En => format!("could not compare with the server: {error}"),
+                De => format!("Vergleich mit dem Server nicht möglich: {error}"),
+                Fr => format!("comparaison avec le serveur impossible : {error}"),
+                Es => format!("no se pudo comparar con el servidor: {error}"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_parse() {
+        assert_eq!(Locale::parse("de-CH"), Some(Locale::De));
+        assert_eq!(Locale::parse("fr_FR.UTF-8"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("ES"), Some(Locale::Es));
+        assert_eq!(Locale::parse("ja"), None);
+    }
+
+    #[test]
+    fn test_status_keeps_english_labels() {
+        let status = Message::Status {
+            config_path: "/home/me/.config/ziit/config.json",
+            has_api_key: true,
+            base_url: "https://ziit.app",
+            git_version: None,
+        };
+        assert_eq!(
+            status.text(Locale::En),
+            "Config: /home/me/.config/ziit/config.json\nAPI Key: Set\nBase URL: https://ziit.app\nGit: Not found (set gitPath in config)"
+        );
+        assert!(status.text(Locale::De).starts_with("Konfiguration: "));
+    }
+}
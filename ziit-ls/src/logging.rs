@@ -0,0 +1,392 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+use ziit_core::redact;
+
+const DEFAULT_LEVEL: Level = Level::INFO;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+// `RUST_LOG` style directives: a default level and `target=level` overrides,
+// the longest matching target winning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Filter {
+    default: Option<Level>,
+    targets: Vec<(String, Option<Level>)>,
+}
+
+fn parse_level(value: &str) -> Option<Option<Level>> {
+    match value.to_ascii_lowercase().as_str() {
+        "off" => Some(None),
+        "error" => Some(Some(Level::ERROR)),
+        "warn" => Some(Some(Level::WARN)),
+        "info" => Some(Some(Level::INFO)),
+        "debug" => Some(Some(Level::DEBUG)),
+        "trace" => Some(Some(Level::TRACE)),
+        _ => None,
+    }
+}
+
+impl Filter {
+    fn parse(directives: &str) -> Self {
+        let mut filter = Self {
+            default: Some(DEFAULT_LEVEL),
+            targets: Vec::new(),
+        };
+        for directive in directives.split(',').map(str::trim) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Some(level) = parse_level(level) {
+                        filter.targets.push((target.to_string(), level));
+                    }
+                }
+                None => match parse_level(directive) {
+                    Some(level) => filter.default = level,
+                    // A bare target enables everything for it.
+                    None if !directive.is_empty() => filter
+                        .targets
+                        .push((directive.to_string(), Some(Level::TRACE))),
+                    None => {}
+                },
+            }
+        }
+        filter
+    }
+
+    fn level_for(&self, target: &str) -> Option<Level> {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn enabled(&self, target: &str, level: Level) -> bool {
+        self.level_for(target).is_some_and(|max| level <= max)
+    }
+
+    fn max_level(&self) -> Option<Level> {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .chain([self.default])
+            .max()
+            .flatten()
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    values: Vec<(&'static str, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{:?}", value));
+    }
+}
+
+impl Fields {
+    fn record(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.values.push((field.name(), value));
+        }
+    }
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: Vec<(&'static str, String)>,
+    references: usize,
+}
+
+thread_local! {
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Inner {
+    filter: Filter,
+    format: LogFormat,
+    output: Mutex<Box<dyn Write + Send>>,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+}
+
+impl Inner {
+    fn write(&self, level: Level, target: &str, message: &str, fields: &[(&str, String)]) {
+        let entered = ENTERED.with(|entered| entered.borrow().clone());
+        let spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let context: Vec<&SpanData> = entered.iter().filter_map(|id| spans.get(id)).collect();
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        let line = match self.format {
+            LogFormat::Text => {
+                let mut line = format!("[{} {:<5} {}] ", timestamp, level, target);
+                for span in &context {
+                    line.push_str(span.name);
+                    if !span.fields.is_empty() {
+                        line.push('{');
+                        for (index, (name, value)) in span.fields.iter().enumerate() {
+                            if index > 0 {
+                                line.push(' ');
+                            }
+                            let _ = write!(line, "{}={}", name, value);
+                        }
+                        line.push('}');
+                    }
+                    line.push_str(": ");
+                }
+                line.push_str(message);
+                for (name, value) in fields {
+                    let _ = write!(line, " {}={}", name, value);
+                }
+                line
+            }
+            LogFormat::Json => {
+                let to_map = |fields: &[(&str, String)]| -> Map<String, Value> {
+                    fields
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), Value::from(value.as_str())))
+                        .collect()
+                };
+                let spans: Vec<Value> = context
+                    .iter()
+                    .map(|span| json!({ "name": span.name, "fields": to_map(&span.fields) }))
+                    .collect();
+                json!({
+                    "timestamp": timestamp,
+                    "level": level.as_str(),
+                    "target": target,
+                    "message": message,
+                    "fields": to_map(fields),
+                    "spans": spans,
+                })
+                .to_string()
+            }
+        };
+        drop(spans);
+
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(output, "{}", redact::redact(&line));
+    }
+}
+
+struct TracingLogger(Arc<Inner>);
+
+impl Subscriber for TracingLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        // Spans are always kept so log lines inside them get their context.
+        metadata.is_span() || self.0.filter.enabled(metadata.target(), *metadata.level())
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+        self.0
+            .spans
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                id,
+                SpanData {
+                    name: attributes.metadata().name(),
+                    fields: fields.values,
+                    references: 1,
+                },
+            );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        if let Some(span) = self
+            .0
+            .spans
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&span.into_u64())
+        {
+            span.fields.extend(fields.values);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        self.0.write(
+            *metadata.level(),
+            metadata.target(),
+            fields.message.as_deref().unwrap_or_default(),
+            &fields.values,
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(index) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(index);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self
+            .0
+            .spans
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&span.into_u64())
+        {
+            data.references += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.0.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(data) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        data.references -= 1;
+        if data.references > 0 {
+            return false;
+        }
+        spans.remove(&span.into_u64());
+        true
+    }
+}
+
+// Most of the code base still logs through the `log` crate; those records
+// go through the same output and pick up the spans entered on this thread.
+struct LogBridge(Arc<Inner>);
+
+fn to_tracing_level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::ERROR,
+        log::Level::Warn => Level::WARN,
+        log::Level::Info => Level::INFO,
+        log::Level::Debug => Level::DEBUG,
+        log::Level::Trace => Level::TRACE,
+    }
+}
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.0
+            .filter
+            .enabled(metadata.target(), to_tracing_level(metadata.level()))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !log::Log::enabled(self, record.metadata()) {
+            return;
+        }
+        self.0.write(
+            to_tracing_level(record.level()),
+            record.target(),
+            &record.args().to_string(),
+            &[],
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .0
+            .output
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+// Logs to stderr, which Zed shows in its LSP log, or appends to `file`.
+pub(crate) fn init(format: LogFormat, file: Option<&Path>) {
+    let output: Box<dyn Write + Send> = match file {
+        Some(path) => match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Could not open log file {}: {}", path.display(), e);
+                Box::new(std::io::stderr())
+            }
+        },
+        None => Box::new(std::io::stderr()),
+    };
+    let inner = Arc::new(Inner {
+        filter: Filter::parse(&std::env::var("RUST_LOG").unwrap_or_default()),
+        format,
+        output: Mutex::new(output),
+        spans: Mutex::new(HashMap::new()),
+        next_id: AtomicU64::new(1),
+    });
+
+    let max_level = match inner.filter.max_level() {
+        Some(Level::ERROR) => log::LevelFilter::Error,
+        Some(Level::WARN) => log::LevelFilter::Warn,
+        Some(Level::INFO) => log::LevelFilter::Info,
+        Some(Level::DEBUG) => log::LevelFilter::Debug,
+        Some(_) => log::LevelFilter::Trace,
+        None => log::LevelFilter::Off,
+    };
+    if log::set_logger(Box::leak(Box::new(LogBridge(Arc::clone(&inner))))).is_ok() {
+        log::set_max_level(max_level);
+    }
+    let _ = tracing::subscriber::set_global_default(TracingLogger(inner));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_directives() {
+        let filter = Filter::parse("warn,ziit_core=debug,ziit_core::vcs=off");
+
+        assert!(filter.enabled("ziit_ls", Level::WARN));
+        assert!(!filter.enabled("ziit_ls", Level::INFO));
+        assert!(filter.enabled("ziit_core::heartbeat", Level::DEBUG));
+        assert!(!filter.enabled("ziit_core::vcs", Level::ERROR));
+        assert_eq!(filter.max_level(), Some(Level::DEBUG));
+        assert!(Filter::parse("").enabled("tower_lsp", Level::INFO));
+    }
+}
@@ -1,91 +1,226 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use chrono::{DateTime, Local, TimeDelta};
 use clap::{Arg, Command};
+use serde::Deserialize;
 use serde_json::Value;
 use tokio::io::{stdin as tokio_stdin, stdout as tokio_stdout};
 use tokio::sync::{Mutex, OnceCell};
 use tower_lsp::{jsonrpc, lsp_types::*, Client, LanguageServer, LspService, Server};
 use url::Url;
 
-mod api;
-mod commands;
-mod config;
-mod heartbeat;
-mod language;
-mod project;
+mod doctor;
+mod i18n;
+mod logging;
+mod mcp;
+mod metrics;
 
-use config::ZiitConfig;
-use heartbeat::HeartbeatManager;
+use i18n::{Locale, Message};
+use logging::LogFormat;
+use ziit_core::config::ZiitConfig;
+use ziit_core::export::{self, ExportFormat};
+use ziit_core::heartbeat::{
+    ActivityEvent, CursorPosition, EntityType, HeartbeatManager, SyncState,
+};
+use ziit_core::{commands, config, language, redact, report, request_log, stats, timezone};
 
-const HEARTBEAT_DEBOUNCE_SECONDS: i64 = 120;
+// Sent by the editor when its window or pane focus changes, so focus is known
+// directly instead of being inferred from edits and saves.
+#[derive(Debug, Deserialize)]
+struct FocusChangedParams {
+    focused: bool,
+    uri: Option<Url>,
+}
+
+// Sent by the editor while a terminal is in use. The working directory stands
+// in for the file when detecting the project and branch.
+#[derive(Debug, Deserialize)]
+struct TerminalActivityParams {
+    cwd: String,
+}
+
+// Sent by the editor when a debug session starts or stops. `cwd` is the
+// session's working directory and identifies the project being debugged.
+#[derive(Debug, Deserialize)]
+struct DebugSessionParams {
+    active: bool,
+    cwd: String,
+}
+
+enum StateChanged {}
+
+impl notification::Notification for StateChanged {
+    type Params = SyncState;
+    const METHOD: &'static str = "ziit/stateChanged";
+}
 
-#[derive(Debug)]
-struct LastHeartbeatInfo {
-    uri: String,
-    timestamp: DateTime<Local>,
-    is_write: bool,
+fn sync_state_message(
+    previous: SyncState,
+    current: SyncState,
+) -> Option<(MessageType, Message<'static>)> {
+    if previous.api_key_valid != current.api_key_valid {
+        return Some(if current.api_key_valid {
+            (MessageType::INFO, Message::ApiKeyAccepted)
+        } else {
+            (MessageType::ERROR, Message::ApiKeyRejected)
+        });
+    }
+    if previous.online != current.online {
+        return Some(if current.online {
+            (MessageType::INFO, Message::BackOnline)
+        } else {
+            (MessageType::WARNING, Message::Offline)
+        });
+    }
+    None
+}
+
+// Forwards sync state changes to the client as `ziit/stateChanged` plus one
+// message per transition, so users do not have to dig through logs.
+fn forward_sync_state(
+    client: Client,
+    mut sync_state: tokio::sync::watch::Receiver<SyncState>,
+    client_locale: Option<String>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut previous = *sync_state.borrow_and_update();
+        while sync_state.changed().await.is_ok() {
+            let current = *sync_state.borrow_and_update();
+            client.send_notification::<StateChanged>(current).await;
+            if let Some((message_type, message)) = sync_state_message(previous, current) {
+                let locale = i18n::resolve(client_locale.as_deref()).await;
+                client
+                    .show_message(message_type, message.text(locale))
+                    .await;
+            }
+            previous = current;
+        }
+    })
+}
+
+fn forward_daily_recaps(
+    client: Client,
+    mut recaps: tokio::sync::watch::Receiver<Option<String>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while recaps.changed().await.is_ok() {
+            let recap = recaps.borrow_and_update().clone();
+            if let Some(recap) = recap {
+                client.show_message(MessageType::INFO, recap).await;
+            }
+        }
+    })
 }
 
 struct ZiitLanguageServer {
     client: Client,
     heartbeat_manager_cell: Arc<OnceCell<Arc<HeartbeatManager>>>,
-    last_heartbeat_info: Mutex<Option<LastHeartbeatInfo>>,
     task_handles: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>>,
     focused_file: Arc<Mutex<Option<String>>>,
     opened_files: Arc<Mutex<std::collections::HashSet<String>>>,
+    document_languages: Arc<Mutex<std::collections::HashMap<String, String>>>,
+    client_locale: Arc<Mutex<Option<String>>>,
+    dry_run: bool,
 }
 
 impl ZiitLanguageServer {
-    fn new(client: Client) -> Self {
+    fn new(client: Client, dry_run: bool) -> Self {
         Self {
             client,
             heartbeat_manager_cell: Arc::new(OnceCell::new()),
-            last_heartbeat_info: Mutex::new(None),
             task_handles: Arc::new(Mutex::new(Vec::new())),
             focused_file: Arc::new(Mutex::new(None)),
             opened_files: Arc::new(Mutex::new(std::collections::HashSet::new())),
+            document_languages: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            client_locale: Arc::new(Mutex::new(None)),
+            dry_run,
         }
     }
 
-    async fn get_heartbeat_manager(&self) -> Option<Arc<HeartbeatManager>> {
-        self.heartbeat_manager_cell.get().cloned()
-    }
-
-    async fn handle_activity(&self, uri_str: String, language_id: Option<String>, is_write: bool) {
-        let now = Local::now();
-        let mut last_hb_info_guard = self.last_heartbeat_info.lock().await;
-        if !is_write {
-            if let Some(ref last_info) = *last_hb_info_guard {
-                if last_info.uri == uri_str
-                    && !last_info.is_write
-                    && (now - last_info.timestamp) < TimeDelta::seconds(HEARTBEAT_DEBOUNCE_SECONDS)
-                {
-                    self.client
-                        .log_message(
-                            MessageType::LOG,
-                            format!("Ziit LS: Debounced event for {}", uri_str),
-                        )
-                        .await;
-                    return;
-                }
+    // Settings from the editor are written to the config file, which the
+    // heartbeat manager and the CLI read from.
+    async fn apply_client_settings(&self, settings: &Value, source: &str) {
+        let mut current_config = match config::read_config_file().await {
+            Ok(current_config) => {
+                self.client
+                    .log_message(
+                        MessageType::LOG,
+                        redact::redact(&format!(
+                            "Ziit LS: Current config before applying {}: {:?}",
+                            source, current_config
+                        )),
+                    )
+                    .await;
+                current_config
+            }
+            Err(_) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Ziit LS: Failed to read config before applying {}.", source),
+                    )
+                    .await;
+                ZiitConfig::default()
             }
+        };
+
+        let changed = config::apply_client_settings(&mut current_config, settings);
+        if changed.is_empty() {
+            return;
+        }
+        for setting in &changed {
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!("Ziit LS: {} updated from {}.", setting, source),
+                )
+                .await;
         }
 
-        *last_hb_info_guard = Some(LastHeartbeatInfo {
-            uri: uri_str.clone(),
-            timestamp: now,
-            is_write,
-        });
-        drop(last_hb_info_guard);
+        if let Err(e) = config::write_config_file(&current_config).await {
+            self.client
+                .log_message(
+                    MessageType::ERROR,
+                    format!("Ziit LS: Failed to write updated config: {}", e),
+                )
+                .await;
+        } else {
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!("Ziit LS: Config file updated successfully from {}.", source),
+                )
+                .await;
+        }
+    }
+
+    async fn document_language(&self, uri: &str) -> Option<String> {
+        self.document_languages.lock().await.get(uri).cloned()
+    }
+
+    async fn locale(&self) -> Locale {
+        i18n::resolve(self.client_locale.lock().await.as_deref()).await
+    }
 
+    async fn get_heartbeat_manager(&self) -> Option<Arc<HeartbeatManager>> {
+        self.heartbeat_manager_cell.get().cloned()
+    }
+
+    async fn handle_activity(
+        &self,
+        uri_str: String,
+        language_id: Option<String>,
+        is_write: bool,
+        force_send: bool,
+        cursor: Option<CursorPosition>,
+    ) {
         if let Some(hm) = self.get_heartbeat_manager().await {
             self.client
                 .log_message(
                     MessageType::LOG,
                     format!(
                         "Ziit LS: Handling activity for {}: write={}, force_send={}",
-                        uri_str, is_write, is_write
+                        uri_str, is_write, force_send
                     ),
                 )
                 .await;
@@ -111,8 +246,14 @@ impl ZiitLanguageServer {
                     .await;
                 return;
             }
-            hm.handle_editor_activity(file_path, language_id, is_write)
-                .await;
+            hm.record_activity(ActivityEvent {
+                file_path,
+                language_id,
+                force_send,
+                entity_type: EntityType::File,
+                is_write,
+                cursor,
+            });
         } else {
             self.client
                 .log_message(
@@ -122,6 +263,67 @@ impl ZiitLanguageServer {
                 .await;
         }
     }
+
+    async fn terminal_activity(&self, params: TerminalActivityParams) {
+        log::debug!("=== ziit/terminalActivity: cwd={} ===", params.cwd);
+
+        if let Some(hm) = self.get_heartbeat_manager().await {
+            hm.record_activity(ActivityEvent {
+                file_path: Some(params.cwd),
+                language_id: None,
+                force_send: false,
+                entity_type: EntityType::Terminal,
+                is_write: false,
+                cursor: None,
+            });
+        }
+    }
+
+    async fn debug_session(&self, params: DebugSessionParams) {
+        log::debug!(
+            "=== ziit/debugSession: active={} cwd={} ===",
+            params.active,
+            params.cwd
+        );
+
+        if let Some(hm) = self.get_heartbeat_manager().await {
+            hm.set_debug_session(params.cwd, params.active).await;
+        }
+    }
+
+    async fn focus_changed(&self, params: FocusChangedParams) {
+        log::debug!(
+            "=== ziit/focusChanged: focused={} uri={:?} ===",
+            params.focused,
+            params.uri
+        );
+
+        if !params.focused {
+            *self.focused_file.lock().await = None;
+            return;
+        }
+
+        if let Some(hm) = self.get_heartbeat_manager().await {
+            hm.claim_editor_lease().await;
+        }
+
+        let Some(uri) = params.uri else {
+            return;
+        };
+        let uri_string = uri.to_string();
+
+        let mut focused = self.focused_file.lock().await;
+        let focus_changed = focused.as_ref() != Some(&uri_string);
+        *focused = Some(uri_string.clone());
+        drop(focused);
+
+        if focus_changed {
+            log::debug!("File became focused (editor focus): {}", uri_string);
+            let language_id = self.document_language(&uri_string).await;
+            self.handle_activity(uri_string, language_id, false, false, None)
+                .await;
+        }
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -137,90 +339,23 @@ impl LanguageServer for ZiitLanguageServer {
             params.workspace_folders
         );
         log::info!("Initialization params: root_uri: {:?}", params.root_uri);
+        self.client_locale.lock().await.clone_from(&params.locale);
 
-        if let Some(init_options) = params.initialization_options {
-            if let Ok(mut current_config) = config::read_config_file().await {
-                self.client
-                    .log_message(
-                        MessageType::LOG,
-                        format!("Ziit LS: Current config before init: {:?}", current_config),
-                    )
-                    .await;
-                let mut config_changed = false;
-
-                if let Some(api_key_val) = init_options.get("apiKey").and_then(Value::as_str) {
-                    if current_config.api_key.as_deref() != Some(api_key_val) {
-                        current_config.api_key = Some(api_key_val.to_string());
-                        config_changed = true;
-                        self.client
-                            .log_message(
-                                MessageType::INFO,
-                                "Ziit LS: API key updated from initialization options.",
-                            )
-                            .await;
-                    }
-                }
-                if let Some(base_url_val) = init_options.get("baseUrl").and_then(Value::as_str) {
-                    if current_config.base_url.as_deref() != Some(base_url_val) {
-                        current_config.base_url = Some(base_url_val.to_string());
-                        config_changed = true;
-                        self.client
-                            .log_message(
-                                MessageType::INFO,
-                                "Ziit LS: Base URL updated from initialization options.",
-                            )
-                            .await;
-                    }
-                }
+        let workspace_folders = match &params.workspace_folders {
+            Some(folders) if !folders.is_empty() => folders
+                .iter()
+                .filter_map(|folder| folder.uri.to_file_path().ok())
+                .collect(),
+            _ => params
+                .root_uri
+                .iter()
+                .filter_map(|uri| uri.to_file_path().ok())
+                .collect::<Vec<PathBuf>>(),
+        };
 
-                if config_changed {
-                    if let Err(e) = config::write_config_file(&current_config).await {
-                        self.client
-                            .log_message(
-                                MessageType::ERROR,
-                                format!("Ziit LS: Failed to write updated config: {}", e),
-                            )
-                            .await;
-                    } else {
-                        self.client
-                            .log_message(
-                                MessageType::INFO,
-                                "Ziit LS: Config file updated successfully from init options.",
-                            )
-                            .await;
-                    }
-                }
-            } else {
-                self.client
-                    .log_message(
-                        MessageType::ERROR,
-                        "Ziit LS: Failed to read initial config during initialize.",
-                    )
-                    .await;
-                let mut new_config = ZiitConfig::default();
-                let mut new_config_populated = false;
-                if let Some(api_key_val) = init_options.get("apiKey").and_then(Value::as_str) {
-                    new_config.api_key = Some(api_key_val.to_string());
-                    new_config_populated = true;
-                }
-                if let Some(base_url_val) = init_options.get("baseUrl").and_then(Value::as_str) {
-                    new_config.base_url = Some(base_url_val.to_string());
-                    new_config_populated = true;
-                }
-                if new_config_populated {
-                    if let Err(e) = config::write_config_file(&new_config).await {
-                        self.client
-                            .log_message(
-                                MessageType::ERROR,
-                                format!(
-                                    "Ziit LS: Failed to write new config from init options: {}",
-                                    e
-                                ),
-                            )
-                            .await;
-                    }
-                }
-            }
+        if let Some(init_options) = params.initialization_options {
+            self.apply_client_settings(&init_options, "initialization options")
+                .await;
         } else {
             self.client
                 .log_message(
@@ -230,15 +365,27 @@ impl LanguageServer for ZiitLanguageServer {
                 .await;
         }
 
-        match HeartbeatManager::new().await {
+        match HeartbeatManager::new(workspace_folders).await {
             Ok(hm) => {
-                let hm_arc: Arc<HeartbeatManager> = Arc::new(hm);
+                let hm_arc: Arc<HeartbeatManager> =
+                    Arc::new(hm.with_dry_run(self.dry_run).with_client_name(
+                        params.client_info.as_ref().map(|info| info.name.clone()),
+                    ));
 
                 let hm_clone_for_tasks: Arc<HeartbeatManager> = Arc::clone(&hm_arc);
                 let task_handles = hm_clone_for_tasks.start_background_tasks();
 
                 let mut handles = self.task_handles.lock().await;
                 handles.extend(task_handles);
+                handles.push(forward_sync_state(
+                    self.client.clone(),
+                    hm_arc.subscribe_sync_state(),
+                    params.locale.clone(),
+                ));
+                handles.push(forward_daily_recaps(
+                    self.client.clone(),
+                    hm_arc.subscribe_daily_recaps(),
+                ));
 
                 if self.heartbeat_manager_cell.set(hm_arc).is_err() {
                     self.client
@@ -256,6 +403,26 @@ impl LanguageServer for ZiitLanguageServer {
                     )
                     .await;
                 log::info!("=== HeartbeatManager initialized and background tasks started ===");
+
+                // Panics from earlier sessions are only stored locally until now.
+                tokio::spawn(async {
+                    if let Err(e) = ziit_core::telemetry::submit_pending().await {
+                        log::debug!("Could not submit error reports: {}", e);
+                    }
+                });
+
+                if let Some(hm) = self.get_heartbeat_manager().await {
+                    for notice in hm.take_notices() {
+                        self.client.show_message(MessageType::WARNING, notice).await;
+                    }
+                    if hm.is_dry_run().await {
+                        let path = hm.dry_run_path().display().to_string();
+                        let message = Message::DryRun { path: &path };
+                        self.client
+                            .show_message(MessageType::INFO, message.text(self.locale().await))
+                            .await;
+                    }
+                }
             }
             Err(e) => {
                 self.client
@@ -279,12 +446,24 @@ impl LanguageServer for ZiitLanguageServer {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
                     TextDocumentSyncKind::INCREMENTAL,
                 )),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: vec![
                         "ziit.setApiKey".to_string(),
                         "ziit.setBaseUrl".to_string(),
                         "ziit.openDashboard".to_string(),
                         "ziit.showStatus".to_string(),
+                        "ziit.localStats".to_string(),
+                        "ziit.exportHistory".to_string(),
+                        "ziit.importWakaTime".to_string(),
+                        "ziit.weeklyReport".to_string(),
+                        "ziit.recentRequests".to_string(),
                     ],
                     work_done_progress_options: WorkDoneProgressOptions::default(),
                 }),
@@ -329,8 +508,8 @@ impl LanguageServer for ZiitLanguageServer {
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        log::info!("=== did_open called for: {} ===", params.text_document.uri);
-        log::info!("Language ID: {}", params.text_document.language_id);
+        log::debug!("=== did_open called for: {} ===", params.text_document.uri);
+        log::debug!("Language ID: {}", params.text_document.language_id);
         self.client
             .log_message(
                 MessageType::LOG,
@@ -344,6 +523,11 @@ impl LanguageServer for ZiitLanguageServer {
         opened.insert(uri_string.clone());
         drop(opened);
 
+        self.document_languages
+            .lock()
+            .await
+            .insert(uri_string.clone(), params.text_document.language_id);
+
         log::debug!("File opened and tracked: {}", uri_string);
     }
 
@@ -374,16 +558,27 @@ impl LanguageServer for ZiitLanguageServer {
         drop(focused);
 
         if was_just_opened || focus_changed {
-            log::info!("File became focused (first edit): {}", uri_string);
+            log::debug!("File became focused (first edit): {}", uri_string);
         } else {
             log::debug!("Continuing work on focused file: {}", uri_string);
         }
 
-        self.handle_activity(uri_string, None, false).await;
+        let language_id = self.document_language(&uri_string).await;
+        // The start of the last edit is the best guess at the cursor.
+        let cursor = params
+            .content_changes
+            .last()
+            .and_then(|change| change.range)
+            .map(|range| CursorPosition {
+                line: range.start.line,
+                character: range.start.character,
+            });
+        self.handle_activity(uri_string, language_id, true, false, cursor)
+            .await;
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        log::info!("=== did_save called for: {} ===", params.text_document.uri);
+        log::debug!("=== did_save called for: {} ===", params.text_document.uri);
         self.client
             .log_message(
                 MessageType::LOG,
@@ -404,8 +599,39 @@ impl LanguageServer for ZiitLanguageServer {
         *focused = Some(uri_string.clone());
         drop(focused);
 
-        log::info!("File saved (focused): {}", uri_string);
-        self.handle_activity(uri_string, None, true).await;
+        log::debug!("File saved (focused): {}", uri_string);
+        let language_id = self.document_language(&uri_string).await;
+        self.handle_activity(uri_string, language_id, true, true, None)
+            .await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri_string = params.text_document.uri.to_string();
+        log::debug!("=== did_close called for: {} ===", uri_string);
+        self.opened_files.lock().await.remove(&uri_string);
+        self.document_languages.lock().await.remove(&uri_string);
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        self.apply_client_settings(&params.settings, "workspace configuration")
+            .await;
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let to_paths = |folders: Vec<WorkspaceFolder>| -> Vec<PathBuf> {
+            folders
+                .into_iter()
+                .filter_map(|folder| folder.uri.to_file_path().ok())
+                .collect()
+        };
+
+        if let Some(hm) = self.get_heartbeat_manager().await {
+            hm.update_workspace_folders(
+                to_paths(params.event.added),
+                to_paths(params.event.removed),
+            )
+            .await;
+        }
     }
 
     async fn execute_command(
@@ -421,7 +647,7 @@ impl LanguageServer for ZiitLanguageServer {
 
         match params.command.as_str() {
             "ziit.setApiKey" => {
-                if let Some(Value::String(api_key)) = params.arguments.get(0) {
+                if let Some(Value::String(api_key)) = params.arguments.first() {
                     match commands::set_api_key(api_key.clone()).await {
                         Ok(msg) => {
                             self.client
@@ -442,7 +668,7 @@ impl LanguageServer for ZiitLanguageServer {
                 }
             }
             "ziit.setBaseUrl" => {
-                if let Some(Value::String(base_url)) = params.arguments.get(0) {
+                if let Some(Value::String(base_url)) = params.arguments.first() {
                     match commands::set_base_url(base_url.clone()).await {
                         Ok(msg) => {
                             self.client
@@ -484,12 +710,42 @@ impl LanguageServer for ZiitLanguageServer {
             },
             "ziit.showStatus" => match commands::get_config_status().await {
                 Ok(status) => {
-                    let status_msg = format!(
-                        "Config: {}\nAPI Key: {}\nBase URL: {}",
-                        status.config_path,
-                        if status.has_api_key { "Set" } else { "Not Set" },
-                        status.base_url
-                    );
+                    let locale = self.locale().await;
+                    let mut status_msg = Message::Status {
+                        config_path: &status.config_path,
+                        has_api_key: status.has_api_key,
+                        base_url: &status.base_url,
+                        git_version: status.git_version.as_deref(),
+                    }
+                    .text(locale);
+                    if let Some(hm) = self.get_heartbeat_manager().await {
+                        if let Some((fetched_at, summary)) = hm.cached_summary() {
+                            let today: u64 = summary
+                                .summaries
+                                .first()
+                                .map_or(0, |entry| entry.total_seconds);
+                            let as_of = timezone::configured()
+                                .await
+                                .local_time(fetched_at)
+                                .format("%H:%M")
+                                .to_string();
+                            let message = Message::StatusToday {
+                                duration: &stats::format_duration(today),
+                                as_of: &as_of,
+                            };
+                            status_msg.push('\n');
+                            status_msg.push_str(&message.text(locale));
+                        }
+                        for mirror in hm.mirror_statuses().await {
+                            let message = Message::StatusMirror {
+                                base_url: &mirror.base_url,
+                                online: mirror.online,
+                                queued: mirror.queued,
+                            };
+                            status_msg.push('\n');
+                            status_msg.push_str(&message.text(locale));
+                        }
+                    }
                     self.client
                         .log_message(MessageType::INFO, format!("Ziit LS: {}", status_msg))
                         .await;
@@ -503,6 +759,132 @@ impl LanguageServer for ZiitLanguageServer {
                     Err(jsonrpc::Error::internal_error())
                 }
             },
+            "ziit.localStats" => {
+                let range = params
+                    .arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .unwrap_or("today");
+                let start = match range {
+                    "today" => timezone::configured().await.start_of_today(),
+                    "week" => chrono::Utc::now() - chrono::TimeDelta::days(7),
+                    _ => {
+                        return Err(jsonrpc::Error::invalid_params(
+                            "Range must be \"today\" or \"week\"",
+                        ))
+                    }
+                };
+                let Some(hm) = self.get_heartbeat_manager().await else {
+                    return Err(jsonrpc::Error::internal_error());
+                };
+                match hm.local_stats(start).await {
+                    Ok(stats) => Ok(serde_json::to_value(stats).ok()),
+                    Err(e) => {
+                        let error_msg = format!("Failed to compute local stats: {}", e);
+                        self.client
+                            .log_message(MessageType::ERROR, format!("Ziit LS: {}", error_msg))
+                            .await;
+                        Err(jsonrpc::Error::internal_error())
+                    }
+                }
+            }
+            "ziit.exportHistory" => {
+                let argument = |index: usize| params.arguments.get(index).and_then(Value::as_str);
+                let format = ExportFormat::parse(argument(0).unwrap_or("json"))
+                    .map_err(|e| jsonrpc::Error::invalid_params(e.to_string()))?;
+                let path = match argument(1) {
+                    Some(path) => PathBuf::from(path),
+                    None => export::default_export_path(format)
+                        .map_err(|_| jsonrpc::Error::internal_error())?,
+                };
+                let Some(hm) = self.get_heartbeat_manager().await else {
+                    return Err(jsonrpc::Error::internal_error());
+                };
+
+                let result = match hm.history().await {
+                    Ok(history) => match export::export(&history, format) {
+                        Ok(data) => tokio::fs::write(&path, data).await.map_err(Into::into),
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(()) => {
+                        let path_display = path.display().to_string();
+                        let message = Message::HistoryExported {
+                            path: &path_display,
+                        };
+                        self.client
+                            .show_message(MessageType::INFO, message.text(self.locale().await))
+                            .await;
+                        Ok(Some(Value::String(path.display().to_string())))
+                    }
+                    Err(e) => {
+                        let error_msg = format!("Failed to export history: {}", e);
+                        self.client
+                            .log_message(MessageType::ERROR, format!("Ziit LS: {}", error_msg))
+                            .await;
+                        Err(jsonrpc::Error::internal_error())
+                    }
+                }
+            }
+            "ziit.weeklyReport" => {
+                let Some(hm) = self.get_heartbeat_manager().await else {
+                    return Err(jsonrpc::Error::internal_error());
+                };
+                let history = hm.history().await.unwrap_or_else(|e| {
+                    log::warn!("Could not read the local history: {}", e);
+                    Vec::new()
+                });
+                let timezone = timezone::configured().await;
+                let today = timezone.today();
+
+                let summary = match config::get_api_key().await {
+                    Ok(Some(api_key)) => match config::get_base_url().await {
+                        Ok(base_url) => {
+                            ziit_core::api::fetch_summary_request(&base_url, &api_key, "week")
+                                .await
+                                .ok()
+                        }
+                        Err(_) => None,
+                    },
+                    _ => None,
+                };
+                let report = match summary {
+                    Some(summary) => report::from_summary(&summary, &history, today, timezone),
+                    None => report::from_history(history, today, timezone),
+                };
+                Ok(Some(Value::String(report.to_markdown())))
+            }
+            "ziit.recentRequests" => Ok(serde_json::to_value(request_log::recent()).ok()),
+            "ziit.importWakaTime" => {
+                let Some(path) = params.arguments.first().and_then(Value::as_str) else {
+                    return Err(jsonrpc::Error::invalid_params(
+                        "Expected the path of a WakaTime heartbeat export",
+                    ));
+                };
+                match import_wakatime(path, |sent| {
+                    log::info!("Imported {} WakaTime heartbeats so far", sent)
+                })
+                .await
+                {
+                    Ok(count) => {
+                        let message = Message::Imported { count };
+                        self.client
+                            .show_message(MessageType::INFO, message.text(self.locale().await))
+                            .await;
+                        Ok(Some(Value::from(count)))
+                    }
+                    Err(e) => {
+                        let error = e.to_string();
+                        let message = Message::ImportFailed { error: &error };
+                        self.client
+                            .show_message(MessageType::ERROR, message.text(self.locale().await))
+                            .await;
+                        Err(jsonrpc::Error::internal_error())
+                    }
+                }
+            }
             _ => {
                 self.client
                     .log_message(
@@ -516,12 +898,31 @@ impl LanguageServer for ZiitLanguageServer {
     }
 }
 
+async fn export_history(
+    format: anyhow::Result<ExportFormat>,
+    output: Option<String>,
+) -> anyhow::Result<()> {
+    let history = ziit_core::heartbeat::read_history().await?;
+    let data = export::export(&history, format?)?;
+    match output {
+        Some(path) => tokio::fs::write(path, data).await?,
+        None => println!("{}", data),
+    }
+    Ok(())
+}
+
+async fn import_wakatime(path: &str, progress: impl FnMut(usize)) -> anyhow::Result<usize> {
+    let api_key = config::get_api_key()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No API key set"))?;
+    let base_url = config::get_base_url().await?;
+    let data = tokio::fs::read_to_string(path).await?;
+    let heartbeats = ziit_core::import::parse_wakatime_dump(&data)?;
+    ziit_core::import::upload(&base_url, &api_key, &heartbeats, progress).await
+}
+
 #[tokio::main]
 async fn main() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .target(env_logger::Target::Stderr)
-        .init();
-
     let matches = Command::new("ziit-ls")
         .version(env!("CARGO_PKG_VERSION"))
         .author("PandaDEV <contact@pandadev.net>")
@@ -532,7 +933,117 @@ async fn main() {
                 .help("Run in standalone mode")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("metrics-port")
+                .long("metrics-port")
+                .value_parser(clap::value_parser!(u16))
+                .help("Serve Prometheus metrics on this localhost port (overrides metricsPort in the config)"),
+        )
+        .arg(
+            Arg::new("mcp")
+                .long("mcp")
+                .help("Serve coding stats over the Model Context Protocol on stdio")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_parser(["text", "json"])
+                .help("Log as plain text or as one JSON object per line"),
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .help("Append logs to this file instead of stderr"),
+        )
+        .arg(
+            Arg::new("log-payloads")
+                .long("log-payloads")
+                .help("Log heartbeat payloads and the config file at debug level")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Write heartbeats to a local file instead of sending them")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .subcommand(Command::new("doctor").about("Check the setup and print a pass/fail report"))
+        .subcommand(
+            Command::new("export")
+                .about("Export the local heartbeat history in WakaTime's format")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["json", "csv"])
+                        .default_value("json"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("File to write, defaults to stdout"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Upload a WakaTime heartbeat export to Ziit")
+                .arg(Arg::new("file").required(true)),
+        )
         .get_matches();
+    let log_format = matches
+        .get_one::<String>("log-format")
+        .cloned()
+        .or_else(|| std::env::var("ZIIT_LOG_FORMAT").ok())
+        .and_then(|format| LogFormat::parse(&format))
+        .unwrap_or(LogFormat::Text);
+    let log_file = matches
+        .get_one::<String>("log-file")
+        .cloned()
+        .or_else(|| std::env::var("ZIIT_LOG_FILE").ok())
+        .map(PathBuf::from);
+    logging::init(log_format, log_file.as_deref());
+    redact::set_log_payloads(matches.get_flag("log-payloads"));
+    ziit_core::telemetry::install_panic_hook();
+
+    if let Some(import_matches) = matches.subcommand_matches("import") {
+        let path = import_matches
+            .get_one::<String>("file")
+            .expect("file is required");
+        match import_wakatime(path, |sent| eprintln!("Uploaded {} heartbeats", sent)).await {
+            Ok(count) => println!("Imported {} heartbeats from {}", count, path),
+            Err(e) => {
+                eprintln!("Import failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(export_matches) = matches.subcommand_matches("export") {
+        let format = export_matches
+            .get_one::<String>("format")
+            .map_or(Ok(ExportFormat::Json), |format| ExportFormat::parse(format));
+        let output = export_matches.get_one::<String>("output").cloned();
+        if let Err(e) = export_history(format, output).await {
+            eprintln!("Export failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if matches.subcommand_matches("doctor").is_some() {
+        let healthy = doctor::run().await;
+        std::process::exit(if healthy { 0 } else { 1 });
+    }
+
+    if matches.get_flag("mcp") {
+        log::info!("Ziit v{} serving MCP on stdio", env!("CARGO_PKG_VERSION"));
+        if let Err(e) = mcp::serve().await {
+            log::error!("MCP server stopped: {}", e);
+        }
+        return;
+    }
 
     if matches.get_flag("standalone") {
         eprintln!(
@@ -554,10 +1065,37 @@ async fn main() {
         );
     }
 
+    // Off unless asked for, so several editor windows never fight over a port.
+    let metrics_port = match matches.get_one::<u16>("metrics-port") {
+        Some(port) => Some(*port),
+        None => config::read_config_file()
+            .await
+            .ok()
+            .and_then(|config| config.metrics_port),
+    };
+    if let Some(port) = metrics_port {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(port).await {
+                log::error!("Metrics endpoint on port {} stopped: {}", port, e);
+            }
+        });
+    }
+
+    language::load_language_database();
+
     let stdin = tokio_stdin();
     let stdout = tokio_stdout();
 
-    let (service, socket) = LspService::build(ZiitLanguageServer::new).finish();
+    let dry_run = matches.get_flag("dry-run");
+    let (service, socket) =
+        LspService::build(move |client| ZiitLanguageServer::new(client, dry_run))
+            .custom_method("ziit/focusChanged", ZiitLanguageServer::focus_changed)
+            .custom_method(
+                "ziit/terminalActivity",
+                ZiitLanguageServer::terminal_activity,
+            )
+            .custom_method("ziit/debugSession", ZiitLanguageServer::debug_session)
+            .finish();
 
     log::info!("=== LSP service built, starting server loop ===");
     log::info!("Waiting for LSP initialize request from client...");
@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::io::{stdin, stdout, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use ziit_core::api::{fetch_summary_request, DailySummaryResponse};
+use ziit_core::config::{get_api_key, get_base_url};
+use ziit_core::stats::format_duration;
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+const TODAY_TOOL: &str = "today_coding_time";
+const WEEK_PROJECTS_TOOL: &str = "weekly_project_time";
+
+// JSON-RPC error codes used by MCP.
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": TODAY_TOOL,
+            "description": "Total coding time tracked by Ziit today.",
+            "inputSchema": { "type": "object", "properties": {} }
+        },
+        {
+            "name": WEEK_PROJECTS_TOOL,
+            "description": "Coding time per project tracked by Ziit over the last week.",
+            "inputSchema": { "type": "object", "properties": {} }
+        }
+    ])
+}
+
+fn describe_today(summary: &DailySummaryResponse) -> String {
+    let total: u64 = summary
+        .summaries
+        .iter()
+        .map(|entry| entry.total_seconds)
+        .sum();
+    format!("Coding time today: {}", format_duration(total))
+}
+
+fn describe_projects(summary: &DailySummaryResponse) -> String {
+    let mut projects: HashMap<&str, u64> = HashMap::new();
+    for entry in &summary.summaries {
+        for (project, seconds) in entry.projects.iter().flatten() {
+            *projects.entry(project.as_str()).or_default() += seconds;
+        }
+    }
+
+    if projects.is_empty() {
+        return "No project time tracked this week.".to_string();
+    }
+
+    let mut projects: Vec<_> = projects.into_iter().collect();
+    projects.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let lines: Vec<String> = projects
+        .into_iter()
+        .map(|(project, seconds)| format!("- {}: {}", project, format_duration(seconds)))
+        .collect();
+    format!("Coding time per project this week:\n{}", lines.join("\n"))
+}
+
+async fn fetch_summary(time_range: &str) -> Result<DailySummaryResponse> {
+    let api_key = get_api_key()
+        .await?
+        .ok_or_else(|| anyhow!("No Ziit API key configured"))?;
+    let base_url = get_base_url().await?;
+    fetch_summary_request(&base_url, &api_key, time_range).await
+}
+
+async fn call_tool(name: &str) -> Result<String> {
+    match name {
+        TODAY_TOOL => Ok(describe_today(&fetch_summary("today").await?)),
+        WEEK_PROJECTS_TOOL => Ok(describe_projects(&fetch_summary("week").await?)),
+        _ => Err(anyhow!("Unknown tool: {}", name)),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+pub async fn handle_message(message: &Value) -> Option<Value> {
+    let method = message.get("method")?.as_str()?;
+    // Notifications carry no id and get no response.
+    let id = message.get("id")?.clone();
+
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "ziit", "version": env!("CARGO_PKG_VERSION") }
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tool_definitions() }),
+        "tools/call" => {
+            let Some(name) = message.pointer("/params/name").and_then(Value::as_str) else {
+                return Some(error_response(
+                    id,
+                    INVALID_PARAMS,
+                    "Missing tool name".to_string(),
+                ));
+            };
+
+            match call_tool(name).await {
+                Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+                Err(e) => json!({
+                    "content": [{ "type": "text", "text": e.to_string() }],
+                    "isError": true
+                }),
+            }
+        }
+        _ => {
+            return Some(error_response(
+                id,
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            ))
+        }
+    };
+
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+// Serves the Model Context Protocol over newline-delimited JSON on stdio.
+pub async fn serve() -> Result<()> {
+    let mut lines = BufReader::new(stdin()).lines();
+    let mut out = stdout();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("Ignoring malformed MCP message: {}", e);
+                continue;
+            }
+        };
+
+        if let Some(response) = handle_message(&message).await {
+            out.write_all(format!("{}\n", response).as_bytes()).await?;
+            out.flush().await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ziit_core::api::SummaryEntry;
+
+    #[tokio::test]
+    async fn test_initialize_and_list_tools() {
+        let response = handle_message(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}))
+            .await
+            .unwrap();
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+        let response = handle_message(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .await
+            .unwrap();
+        assert_eq!(response["result"]["tools"].as_array().unwrap().len(), 2);
+
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(handle_message(&notification).await.is_none());
+
+        let response = handle_message(&json!({"jsonrpc": "2.0", "id": 3, "method": "nope"}))
+            .await
+            .unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_describe_projects_sums_across_days() {
+        let entry = |projects: &[(&str, u64)]| SummaryEntry {
+            date: "2026-10-15".to_string(),
+            total_seconds: projects.iter().map(|(_, seconds)| seconds).sum(),
+            hourly_data: None,
+            projects: Some(
+                projects
+                    .iter()
+                    .map(|(name, seconds)| (name.to_string(), *seconds))
+                    .collect(),
+            ),
+        };
+        let summary = DailySummaryResponse {
+            summaries: vec![
+                entry(&[("ziit", 3600), ("zed", 600)]),
+                entry(&[("zed", 7200)]),
+            ],
+            timezone: "UTC".to_string(),
+        };
+
+        assert_eq!(
+            describe_projects(&summary),
+            "Coding time per project this week:\n- zed: 2h 10m\n- ziit: 1h 0m"
+        );
+        assert_eq!(describe_today(&summary), "Coding time today: 3h 10m");
+    }
+}
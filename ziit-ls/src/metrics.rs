@@ -0,0 +1,76 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use ziit_core::metrics;
+
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+// Only bound to localhost: the counters are harmless, but a scraper on
+// another machine should go through whatever the self-hoster already uses
+// to expose local services.
+pub(crate) async fn serve(port: u16) -> std::io::Result<()> {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await?;
+    log::info!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream).await {
+                log::debug!("Metrics request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() > MAX_REQUEST_BYTES {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let response = respond(&String::from_utf8_lossy(&request));
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn respond(request: &str) -> String {
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics::render(),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond_serves_metrics_only() {
+        let ok = respond("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.contains("ziit_offline_queue_depth"));
+
+        let missing = respond("GET / HTTP/1.1\r\n\r\n");
+        assert!(missing.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}